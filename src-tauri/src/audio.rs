@@ -0,0 +1,511 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecParameters, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+use symphonia::core::units::TimeBase;
+
+/// De onde veio a duração informada em `AudioInfo`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationSource {
+    /// Número de frames no cabeçalho (Xing/VBRI no mp3, `mdhd` no mp4, STREAMINFO no flac...)
+    Header,
+    /// Último timestamp dos pacotes, lendo o arquivo inteiro sem decodificar
+    PacketScan,
+    /// Frames contados decodificando o arquivo inteiro, o caminho mais lento
+    Decode,
+}
+
+/// Informações da primeira faixa de áudio de um arquivo
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AudioInfo {
+    /// Nome curto do codec (`mp3`, `aac`, `flac`...)
+    pub codec: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<usize>,
+    pub bits_per_sample: Option<u32>,
+    /// Duração em segundos
+    pub duration: Option<f64>,
+    pub duration_source: Option<DurationSource>,
+    /// Taxa média em bits por segundo, pelo tamanho do arquivo e a duração
+    pub bitrate: Option<u64>,
+    /// Tags do arquivo (ID3, Vorbis comments, iTunes...), com nomes normalizados quando
+    /// o symphonia reconhece a tag
+    pub tags: BTreeMap<String, String>,
+}
+
+/// Faz o probe do arquivo com o symphonia, usando a extensão como dica do formato
+///
+/// Quando o cabeçalho não informa o número de frames (mp3 VBR sem Xing, streams gravados
+/// ao vivo...), a duração vem dos timestamps dos pacotes ou, em último caso, da decodificação.
+pub fn probe(path: &Path) -> Result<AudioInfo, String> {
+    let mut format = open(path)?;
+    let mut tags = BTreeMap::new();
+    if let Some(revision) = format
+        .metadata
+        .get()
+        .as_mut()
+        .and_then(|m| m.skip_to_latest())
+    {
+        collect_tags(revision, &mut tags);
+    }
+    let mut format = format.format;
+    if let Some(revision) = format.metadata().skip_to_latest() {
+        collect_tags(revision, &mut tags);
+    }
+
+    // Obtém o primeiro track de áudio
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| "No valid audio track found".to_string())?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    let (duration, duration_source) = match header_duration(&params) {
+        Some(duration) => (Some(duration), Some(DurationSource::Header)),
+        None => match params.time_base {
+            Some(time_base) => (
+                scan_packets(format.as_mut(), track_id, time_base)?,
+                Some(DurationSource::PacketScan),
+            ),
+            None => (
+                decode_duration(format.as_mut(), track_id, &params)?,
+                Some(DurationSource::Decode),
+            ),
+        },
+    };
+    let duration_source = duration.and(duration_source);
+
+    let file_size = std::fs::metadata(path).map(|m| m.len()).ok();
+    let bitrate = match (file_size, duration) {
+        (Some(size), Some(duration)) if duration > 0.0 => {
+            Some((size as f64 * 8.0 / duration) as u64)
+        }
+        _ => None,
+    };
+
+    Ok(AudioInfo {
+        codec: symphonia::default::get_codecs()
+            .get_codec(params.codec)
+            .map(|codec| codec.short_name.to_string()),
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|channels| channels.count()),
+        bits_per_sample: params.bits_per_sample,
+        duration,
+        duration_source,
+        bitrate,
+        tags,
+    })
+}
+
+fn open(path: &Path) -> Result<symphonia::core::probe::ProbeResult, String> {
+    // Abre o arquivo
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+
+    // Cria o MediaSourceStream
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    // Cria um hint para ajudar no probe do formato
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(&extension.to_lowercase());
+    }
+
+    let format_opts = FormatOptions {
+        // Habilita suporte a gapless playback que pode ajudar na precisão
+        enable_gapless: true,
+        ..Default::default()
+    };
+
+    // Faz o probe do formato
+    symphonia::default::get_probe()
+        .format(&hint, mss, &format_opts, &MetadataOptions::default())
+        .map_err(|e| format!("Failed to probe format: {}", e))
+}
+
+/// Duração pelo número de frames do cabeçalho
+fn header_duration(params: &CodecParameters) -> Option<f64> {
+    let n_frames = params.n_frames?;
+    match (params.sample_rate, params.time_base) {
+        (Some(sample_rate), _) => Some(n_frames as f64 / sample_rate as f64),
+        (None, Some(time_base)) => Some(seconds(time_base, n_frames)),
+        _ => None,
+    }
+}
+
+/// Duração pelo fim do último pacote da faixa, sem decodificar o áudio
+///
+/// Funciona mesmo sem decoder para o codec, como o Opus dentro de WebM ou Ogg.
+fn scan_packets(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    time_base: TimeBase,
+) -> Result<Option<f64>, String> {
+    let mut end = None;
+    loop {
+        match format.next_packet() {
+            Ok(packet) if packet.track_id() == track_id => {
+                let packet_end = packet.ts() + packet.dur();
+                end = Some(end.map_or(packet_end, |end: u64| end.max(packet_end)));
+            }
+            Ok(_) => {}
+            Err(e) if is_end_of_stream(&e) => break,
+            // Arquivos truncados ainda têm uma duração útil até onde dá para ler
+            Err(_) if end.is_some() => break,
+            Err(e) => return Err(format!("Failed to read packets: {}", e)),
+        }
+    }
+    Ok(end.map(|end| seconds(time_base, end)))
+}
+
+/// Duração pela quantidade de frames decodificados
+fn decode_duration(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+) -> Result<Option<f64>, String> {
+    let mut decoder = symphonia::default::get_codecs()
+        .make(params, &DecoderOptions::default())
+        .map_err(|e| format!("Failed to create decoder: {}", e))?;
+
+    let mut frames = 0u64;
+    let mut sample_rate = params.sample_rate;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(e) if is_end_of_stream(&e) => break,
+            Err(e) => return Err(format!("Failed to read packets: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(buffer) => {
+                frames += buffer.frames() as u64;
+                sample_rate.get_or_insert(buffer.spec().rate);
+            }
+            // Pacotes corrompidos são pulados, como faria um player
+            Err(Error::DecodeError(_)) => {}
+            Err(e) => return Err(format!("Failed to decode audio: {}", e)),
+        }
+    }
+    Ok(sample_rate
+        .filter(|_| frames > 0)
+        .map(|rate| frames as f64 / rate as f64))
+}
+
+fn is_end_of_stream(error: &Error) -> bool {
+    match error {
+        Error::IoError(e) => e.kind() == std::io::ErrorKind::UnexpectedEof,
+        Error::ResetRequired => true,
+        _ => false,
+    }
+}
+
+fn seconds(time_base: TimeBase, ts: u64) -> f64 {
+    let time = time_base.calc_time(ts);
+    time.seconds as f64 + time.frac
+}
+
+fn collect_tags(revision: &MetadataRevision, tags: &mut BTreeMap<String, String>) {
+    for tag in revision.tags() {
+        let key = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => "title".to_string(),
+            Some(StandardTagKey::Artist) => "artist".to_string(),
+            Some(StandardTagKey::Album) => "album".to_string(),
+            Some(StandardTagKey::AlbumArtist) => "album_artist".to_string(),
+            Some(StandardTagKey::Date) | Some(StandardTagKey::ReleaseDate) => "date".to_string(),
+            Some(StandardTagKey::Genre) => "genre".to_string(),
+            Some(StandardTagKey::Comment) => "comment".to_string(),
+            Some(StandardTagKey::Composer) => "composer".to_string(),
+            Some(StandardTagKey::TrackNumber) => "track".to_string(),
+            _ => tag.key.clone(),
+        };
+        tags.entry(key).or_insert_with(|| tag.value.to_string());
+    }
+}
+
+/// Decodifica a primeira faixa de áudio misturando os canais em mono
+///
+/// Os blocos são entregues a `on_block` junto com a taxa de amostragem à medida que o
+/// arquivo é lido, para não carregar horas de áudio na memória.
+pub fn decode_mono<F>(path: &Path, mut on_block: F) -> Result<(), String>
+where
+    F: FnMut(&[f32], u32),
+{
+    let mut mono = Vec::new();
+    decode(path, |samples, channels, rate| {
+        downmix(samples, channels, &mut mono);
+        on_block(&mono, rate);
+    })
+}
+
+/// Mistura as amostras intercaladas em mono, com a média dos canais
+pub fn downmix(samples: &[f32], channels: usize, mono: &mut Vec<f32>) {
+    mono.clear();
+    mono.extend(
+        samples
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+    );
+}
+
+/// Decodifica a primeira faixa de áudio mantendo os canais
+///
+/// `on_block` recebe as amostras intercaladas, o número de canais e a taxa de amostragem.
+pub fn decode<F>(path: &Path, mut on_block: F) -> Result<(), String>
+where
+    F: FnMut(&[f32], usize, u32),
+{
+    let mut format = open(path)?.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| "No valid audio track found".to_string())?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("Failed to create decoder: {}", e))?;
+
+    let mut interleaved: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(e) if is_end_of_stream(&e) => break,
+            Err(e) => return Err(format!("Failed to read packets: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Pacotes corrompidos são pulados, como faria um player
+            Err(Error::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Failed to decode audio: {}", e)),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let buffer = match &mut interleaved {
+            Some(buffer) if buffer.capacity() >= decoded.capacity() * channels => buffer,
+            _ => interleaved.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        on_block(buffer.samples(), channels, spec.rate);
+    }
+    Ok(())
+}
+
+/// FFT radix-2 in-place, `re` e `im` precisam ter tamanho potência de 2
+pub fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Escreve um WAV PCM de 16 bits aos poucos, o tamanho vai no cabeçalho ao final
+pub struct WavWriter {
+    file: BufWriter<File>,
+    samples: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, sample_rate: u32) -> Result<Self, String> {
+        Self::with_channels(path, sample_rate, 1)
+    }
+
+    /// `write` passa a receber as amostras intercaladas dos `channels` canais
+    pub fn with_channels(path: &Path, sample_rate: u32, channels: u16) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create WAV: {}", e))?;
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            samples: 0,
+        };
+        writer.write_header(sample_rate, channels)?;
+        Ok(writer)
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        for sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.file
+                .write_all(&value.to_le_bytes())
+                .map_err(|e| format!("Failed to write WAV: {}", e))?;
+        }
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    /// Completa o cabeçalho com o tamanho dos dados
+    pub fn finish(mut self) -> Result<(), String> {
+        let data_len = self.samples * 2;
+        let result = (|| {
+            self.file.seek(SeekFrom::Start(4))?;
+            self.file.write_all(&(36 + data_len).to_le_bytes())?;
+            self.file.seek(SeekFrom::Start(40))?;
+            self.file.write_all(&data_len.to_le_bytes())?;
+            self.file.flush()
+        })();
+        result.map_err(|e| format!("Failed to write WAV: {}", e))
+    }
+
+    fn write_header(&mut self, sample_rate: u32, channels: u16) -> Result<(), String> {
+        self.file
+            .write_all(&wav_header(sample_rate, channels, 0))
+            .map_err(|e| format!("Failed to write WAV: {}", e))
+    }
+}
+
+/// Cabeçalho de 44 bytes de um WAV PCM de 16 bits com `data_len` bytes de amostras
+fn wav_header(sample_rate: u32, channels: u16, data_len: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+    header.extend_from_slice(&(channels * 2).to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// Arquivo WAV PCM de 16 bits com silêncio, para os testes que precisam de áudio de verdade
+#[cfg(test)]
+pub fn silent_wav(sample_rate: u32, channels: u16, secs: u32) -> Vec<u8> {
+    let data_len = sample_rate * channels as u32 * 2 * secs;
+    let mut wav = wav_header(sample_rate, channels, data_len);
+    wav.resize(44 + data_len as usize, 0);
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use tempfile::TempDir;
+
+    fn write_wav(dir: &TempDir, name: &str) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, silent_wav(8000, 2, 3)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_probe_wav() {
+        let dir = temp_dir("probe");
+        let path = write_wav(&dir, "probe.WAV");
+
+        let info = probe(&path).unwrap();
+        assert_eq!(info.codec.as_deref(), Some("pcm_s16le"));
+        assert_eq!(info.sample_rate, Some(8000));
+        assert_eq!(info.channels, Some(2));
+        assert_eq!(info.bits_per_sample, Some(16));
+        assert_eq!(info.duration, Some(3.0));
+        assert_eq!(info.duration_source, Some(DurationSource::Header));
+        // 8000 Hz * 2 canais * 16 bits, mais o cabeçalho
+        assert_eq!(info.bitrate.map(|b| b / 1000), Some(256));
+    }
+
+    #[test]
+    fn test_duration_fallbacks() {
+        let dir = temp_dir("fallback");
+        let path = write_wav(&dir, "fallback.wav");
+
+        let mut format = open(&path).unwrap().format;
+        let track = format.default_track().unwrap();
+        let (track_id, time_base) = (track.id, track.codec_params.time_base.unwrap());
+        assert_eq!(
+            scan_packets(format.as_mut(), track_id, time_base).unwrap(),
+            Some(3.0)
+        );
+
+        let mut format = open(&path).unwrap().format;
+        let track = format.default_track().unwrap();
+        let (track_id, params) = (track.id, track.codec_params.clone());
+        assert_eq!(
+            decode_duration(format.as_mut(), track_id, &params).unwrap(),
+            Some(3.0)
+        );
+    }
+
+    #[test]
+    fn test_decode_and_write_wav() {
+        let dir = temp_dir("decode");
+        let path = write_wav(&dir, "decode.wav");
+        let mut samples = Vec::new();
+        let mut rates = Vec::new();
+        decode_mono(&path, |block, rate| {
+            samples.extend_from_slice(block);
+            rates.push(rate);
+        })
+        .unwrap();
+        assert_eq!(samples.len(), 8000 * 3);
+        assert!(rates.iter().all(|rate| *rate == 8000));
+        assert!(samples.iter().all(|sample| *sample == 0.0));
+
+        let output = dir.path().join("writer.wav");
+        let mut writer = WavWriter::create(&output, 16000).unwrap();
+        writer.write(&[0.5; 16000]).unwrap();
+        writer.write(&[-0.5; 8000]).unwrap();
+        writer.finish().unwrap();
+
+        let info = probe(&output).unwrap();
+        assert_eq!(info.sample_rate, Some(16000));
+        assert_eq!(info.channels, Some(1));
+        assert_eq!(info.duration, Some(1.5));
+    }
+
+    #[test]
+    fn test_probe_rejects_garbage() {
+        let dir = temp_dir("probe-garbage");
+        let path = dir.path().join("lixo.mp3");
+        std::fs::write(&path, b"definitivamente nao e audio").unwrap();
+        assert!(probe(&path).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use youtube_dl::{Availability, SingleVideo};
+
+/// Verifica, antes do download, se o vídeo pode ser processado sem login
+pub fn ensure_processable(video: &SingleVideo) -> Result<(), String> {
+    let reason = match video.availability {
+        Some(Availability::Private) => "O vídeo é privado",
+        Some(Availability::PremiumOnly) => "O vídeo é exclusivo para assinantes do YouTube Premium",
+        Some(Availability::SubscriberOnly) => "O vídeo é exclusivo para membros do canal",
+        Some(Availability::NeedsAuth) => {
+            "O vídeo exige login no YouTube (por exemplo, restrição de idade)"
+        }
+        Some(Availability::Public | Availability::Unlisted | Availability::Unknown) | None => {
+            return Ok(())
+        }
+    };
+    Err(format!("{} e não pode ser processado.", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(availability: Option<Availability>) -> SingleVideo {
+        SingleVideo {
+            availability,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ensure_processable() {
+        assert!(ensure_processable(&video(None)).is_ok());
+        assert!(ensure_processable(&video(Some(Availability::Public))).is_ok());
+        assert!(ensure_processable(&video(Some(Availability::Unlisted))).is_ok());
+        assert!(ensure_processable(&video(Some(Availability::Private))).is_err());
+        assert!(ensure_processable(&video(Some(Availability::NeedsAuth))).is_err());
+    }
+}
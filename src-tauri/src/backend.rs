@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::capitu_client::Endpoints;
+
+/// Configurações dos serviços, na pasta de saída
+pub const SETTINGS_FILE: &str = "backend.json";
+/// Perfil ativo, tem prioridade sobre o do arquivo
+pub const PROFILE_VAR: &str = "CAPITUAI_PROFILE";
+/// Endereços avulsos, têm prioridade sobre os do perfil
+pub const TRANSCRIBE_URL_VAR: &str = "CAPITUAI_TRANSCRIBE_URL";
+pub const CHAPTERS_URL_VAR: &str = "CAPITUAI_CHAPTERS_URL";
+
+/// Ambiente dos serviços do CapituAI
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Profile {
+    #[default]
+    Production,
+    /// Mesmos apps no ambiente `staging` do Modal
+    Staging,
+    /// `modal serve` ou uvicorn na própria máquina
+    Local,
+}
+
+impl Profile {
+    /// Endereços de fábrica do perfil, o arquivo de configurações pode trocar qualquer um
+    pub fn endpoints(self) -> Endpoints {
+        let (transcribe_url, chapters_url) = match self {
+            Self::Production => (
+                "https://kamikazebr--transcribe-youtube-fastapi-app.modal.run",
+                "https://kamikazebr--capitu-ai-langchain-fastapi-app.modal.run",
+            ),
+            Self::Staging => (
+                "https://kamikazebr-staging--transcribe-youtube-fastapi-app.modal.run",
+                "https://kamikazebr-staging--capitu-ai-langchain-fastapi-app.modal.run",
+            ),
+            Self::Local => ("http://localhost:8000", "http://localhost:8001"),
+        };
+        Endpoints {
+            transcribe_url: transcribe_url.to_string(),
+            chapters_url: chapters_url.to_string(),
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "production" | "prod" => Ok(Self::Production),
+            "staging" => Ok(Self::Staging),
+            "local" => Ok(Self::Local),
+            other => Err(format!("Perfil desconhecido: {}", other)),
+        }
+    }
+}
+
+/// Endereços trocados de um perfil no arquivo de configurações
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EndpointOverrides {
+    #[serde(default)]
+    pub transcribe_url: Option<String>,
+    #[serde(default)]
+    pub chapters_url: Option<String>,
+}
+
+/// Conteúdo do `backend.json`
+///
+/// ```json
+/// {
+///   "profile": "local",
+///   "profiles": { "local": { "transcribe_url": "http://192.168.0.10:8000" } }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackendSettings {
+    #[serde(default)]
+    pub profile: Option<Profile>,
+    #[serde(default)]
+    pub profiles: HashMap<Profile, EndpointOverrides>,
+}
+
+impl BackendSettings {
+    /// Sem o arquivo ficam as configurações de fábrica
+    pub fn load(folder: &str) -> Result<Self, String> {
+        let path = Path::new(folder).join(SETTINGS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", SETTINGS_FILE, e))
+    }
+}
+
+/// De onde veio o perfil ativo
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileSource {
+    Default,
+    Settings,
+    Environment,
+}
+
+/// Perfil em uso e os endereços resolvidos, mostrado na interface e nos diagnósticos
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ActiveProfile {
+    pub profile: Profile,
+    pub source: ProfileSource,
+    #[serde(flatten)]
+    pub endpoints: Endpoints,
+}
+
+/// Perfil ativo, a partir do `backend.json` em `folder` e das variáveis de ambiente
+pub fn active(folder: &str) -> Result<ActiveProfile, String> {
+    resolve(&BackendSettings::load(folder)?, |name| {
+        std::env::var(name).ok()
+    })
+}
+
+/// Escolhe o perfil e os endereços, nesta ordem de prioridade: variáveis de ambiente, arquivo
+/// de configurações e os endereços de fábrica do perfil
+pub fn resolve(
+    settings: &BackendSettings,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<ActiveProfile, String> {
+    let env = |name: &str| env(name).filter(|value| !value.trim().is_empty());
+
+    let (profile, source) = match env(PROFILE_VAR) {
+        Some(name) => (name.parse()?, ProfileSource::Environment),
+        None => match settings.profile {
+            Some(profile) => (profile, ProfileSource::Settings),
+            None => (Profile::default(), ProfileSource::Default),
+        },
+    };
+
+    let defaults = profile.endpoints();
+    let overrides = settings.profiles.get(&profile).cloned().unwrap_or_default();
+    let url = |var: &str, file: Option<String>, default: String| {
+        let url = env(var).or(file).unwrap_or(default);
+        url.trim().trim_end_matches('/').to_string()
+    };
+    let endpoints = Endpoints {
+        transcribe_url: url(
+            TRANSCRIBE_URL_VAR,
+            overrides.transcribe_url,
+            defaults.transcribe_url,
+        ),
+        chapters_url: url(
+            CHAPTERS_URL_VAR,
+            overrides.chapters_url,
+            defaults.chapters_url,
+        ),
+    };
+
+    for address in [&endpoints.transcribe_url, &endpoints.chapters_url] {
+        if !address.starts_with("http://") && !address.starts_with("https://") {
+            return Err(format!(
+                "Endereço inválido para o perfil {:?}: {}",
+                profile, address
+            ));
+        }
+    }
+
+    Ok(ActiveProfile {
+        profile,
+        source,
+        endpoints,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let active = resolve(&BackendSettings::default(), env(&[])).unwrap();
+        assert_eq!(active.profile, Profile::Production);
+        assert_eq!(active.source, ProfileSource::Default);
+        assert_eq!(active.endpoints, Profile::Production.endpoints());
+
+        let settings: BackendSettings = serde_json::from_str(
+            r#"{
+                "profile": "local",
+                "profiles": {"local": {"transcribe_url": "http://192.168.0.10:8000/"}}
+            }"#,
+        )
+        .unwrap();
+        let active = resolve(&settings, env(&[])).unwrap();
+        assert_eq!(active.profile, Profile::Local);
+        assert_eq!(active.source, ProfileSource::Settings);
+        assert_eq!(active.endpoints.transcribe_url, "http://192.168.0.10:8000");
+        assert_eq!(active.endpoints.chapters_url, "http://localhost:8001");
+
+        // O ambiente ganha do arquivo, e os endereços do arquivo são só do perfil dele
+        let vars = [
+            (PROFILE_VAR, "Staging"),
+            (CHAPTERS_URL_VAR, "https://chapters.example.com"),
+        ];
+        let active = resolve(&settings, env(&vars)).unwrap();
+        assert_eq!(active.profile, Profile::Staging);
+        assert_eq!(active.source, ProfileSource::Environment);
+        assert_eq!(
+            active.endpoints.transcribe_url,
+            Profile::Staging.endpoints().transcribe_url
+        );
+        assert_eq!(
+            active.endpoints.chapters_url,
+            "https://chapters.example.com"
+        );
+
+        assert!(resolve(&settings, env(&[(PROFILE_VAR, "dev")])).is_err());
+        assert!(resolve(&settings, env(&[(TRANSCRIBE_URL_VAR, "localhost:8000")])).is_err());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let active = resolve(&BackendSettings::default(), |_| None).unwrap();
+        assert_eq!(
+            serde_json::to_value(&active).unwrap(),
+            serde_json::json!({
+                "profile": "production",
+                "source": "default",
+                "transcribe_url": "https://kamikazebr--transcribe-youtube-fastapi-app.modal.run",
+                "chapters_url": "https://kamikazebr--capitu-ai-langchain-fastapi-app.modal.run",
+            })
+        );
+    }
+}
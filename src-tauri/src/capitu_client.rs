@@ -0,0 +1,401 @@
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::sleep;
+use youtube_dl::ReplayRange;
+
+use crate::podcast::PodcastChapter;
+use crate::sponsorblock::SponsorSegment;
+
+/// Intervalo entre as consultas de uma tarefa em andamento
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Endereços dos serviços do CapituAI, escolhidos pelo perfil em `backend`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Endpoints {
+    /// Upload, transcrição e cache dos áudios
+    pub transcribe_url: String,
+    /// Geração de capítulos e acompanhamento das tarefas
+    pub chapters_url: String,
+}
+
+/// Erro ao falar com os serviços do CapituAI
+#[derive(Debug)]
+pub enum CapituError {
+    /// Não foi possível enviar a requisição ou ler a resposta
+    Request(reqwest::Error),
+    /// Arquivo local que seria enviado
+    File(String),
+    /// Resposta com status de erro, `body` traz a mensagem do serviço
+    Status { status: StatusCode, body: String },
+    /// Resposta fora do formato esperado
+    Parse {
+        context: &'static str,
+        message: String,
+    },
+    /// O serviço respondeu com um campo `error`
+    Service(String),
+    /// A tarefa terminou sem sucesso
+    Task { task_id: String, result: String },
+}
+
+impl fmt::Display for CapituError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "Failed to reach CapituAI service: {}", e),
+            Self::File(e) => write!(f, "Failed to read audio file: {}", e),
+            Self::Status { status, body } => {
+                write!(f, "CapituAI service returned {}: {}", status, body)
+            }
+            Self::Parse { context, message } => {
+                write!(f, "Failed to parse {} response: {}", context, message)
+            }
+            Self::Service(message) => write!(f, "CapituAI service error: {}", message),
+            Self::Task { task_id, result } => {
+                write!(f, "Task {} finished with status {}", task_id, result)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CapituError {}
+
+impl From<reqwest::Error> for CapituError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Request(e)
+    }
+}
+
+/// Os comandos devolvem `String` para a interface
+impl From<CapituError> for String {
+    fn from(e: CapituError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Resposta do `/upload`, repassada à interface por `upload_audio`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UploadResponse {
+    pub filename_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    /// Quantidade de pedaços, quando o áudio foi enviado dividido
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<usize>,
+}
+
+/// Resposta do `/process-transcription`
+///
+/// Áudios já transcritos voltam sem `task_id`. Os outros campos são mantidos para a resposta
+/// chegar inteira à interface.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionTask {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+/// Resposta do `/transcript`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TranscriptResponse {
+    pub transcript: Value,
+}
+
+/// Pedido de geração de capítulos
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ChapterRequest {
+    pub filename_id: String,
+    /// Transcrição já na linha do tempo do vídeo original, serializada como texto
+    pub transcript: String,
+    /// Trechos do SponsorBlock que devem virar capítulos identificados
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sponsor_segments: Vec<SponsorSegment>,
+    /// Capítulos publicados no feed do podcast, para servir de base aos gerados
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub existing_chapters: Option<Vec<PodcastChapter>>,
+    /// Pausas longas da gravação, pontos naturais para começar um capítulo
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pauses: Vec<Pause>,
+    /// Trechos mais assistidos, que podem destacar ou dar origem a capítulos
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub most_replayed: Vec<ReplayRange>,
+}
+
+/// Pausa na fala, em segundos do vídeo original
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Pause {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Resposta do `/generate-chapters`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ChapterTask {
+    pub task_id: String,
+}
+
+/// Resposta do `/task-status`
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TaskStatus {
+    pub task_result: TaskResult,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TaskResult {
+    /// `pending`, `success`, `timeout_get`...
+    pub result: String,
+    /// Resultado da geração de capítulos
+    #[serde(default)]
+    pub text: Option<Value>,
+}
+
+impl TaskResult {
+    pub fn is_pending(&self) -> bool {
+        self.result == "pending"
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.result == "success"
+    }
+}
+
+/// Cliente dos serviços de transcrição e de capítulos
+pub struct CapituClient {
+    http: reqwest::Client,
+    endpoints: Endpoints,
+    auth_token: Option<String>,
+}
+
+impl CapituClient {
+    pub fn new(endpoints: Endpoints, auth_token: Option<&str>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            endpoints,
+            auth_token: auth_token.map(str::to_string),
+        }
+    }
+
+    /// Envia um arquivo de áudio para ser transcrito depois
+    pub async fn upload(&self, path: &Path) -> Result<UploadResponse, CapituError> {
+        let url = format!("{}/upload", self.endpoints.transcribe_url);
+        println!("Iniciando upload do arquivo: {}", path.display());
+
+        let form = reqwest::multipart::Form::new()
+            .file("file", path)
+            .await
+            .map_err(|e| CapituError::File(e.to_string()))?;
+        println!("Enviando requisição POST para {}", url);
+        let request = self.authorized(self.http.post(url)).multipart(form);
+        self.send(request, "upload").await
+    }
+
+    /// Inicia a transcrição de um áudio já enviado
+    pub async fn start_transcription(
+        &self,
+        filename_id: &str,
+    ) -> Result<TranscriptionTask, CapituError> {
+        let url = format!(
+            "{}/process-transcription/{}",
+            self.endpoints.transcribe_url, filename_id
+        );
+        println!(
+            "Iniciando processamento de transcrição para filename_id: {}",
+            filename_id
+        );
+        let request = self.authorized(self.http.post(url));
+        self.send(request, "process transcription").await
+    }
+
+    /// Transcrição pronta de um áudio enviado
+    pub async fn transcript(&self, filename_id: &str) -> Result<Value, CapituError> {
+        let url = format!(
+            "{}/transcript/{}",
+            self.endpoints.transcribe_url, filename_id
+        );
+        println!("Buscando a transcrição de filename_id: {}", filename_id);
+        let response: TranscriptResponse = self.send(self.http.get(url), "transcript").await?;
+        Ok(response.transcript)
+    }
+
+    /// Pede a geração dos capítulos, com o endpoint autenticado quando há token
+    pub async fn generate_chapters(
+        &self,
+        request: &ChapterRequest,
+    ) -> Result<ChapterTask, CapituError> {
+        let url = match self.auth_token {
+            Some(_) => format!("{}/generate-chapters-auth", self.endpoints.chapters_url),
+            None => format!("{}/generate-chapters", self.endpoints.chapters_url),
+        };
+        println!("Gerando capítulos em {}", url);
+        let request = self.authorized(self.http.post(url)).json(request);
+        self.send(request, "generate chapters").await
+    }
+
+    pub async fn task_status(&self, task_id: &str) -> Result<TaskStatus, CapituError> {
+        let url = format!("{}/task-status/{}", self.endpoints.chapters_url, task_id);
+        self.send(self.http.get(url), "task status").await
+    }
+
+    /// Espera a tarefa sair de `pending`, consultando a cada 5 segundos
+    ///
+    /// Qualquer outro status, inclusive `timeout_get`, é devolvido para quem chamou decidir.
+    pub async fn wait_task(&self, task_id: &str) -> Result<TaskResult, CapituError> {
+        loop {
+            let result = self.task_status(task_id).await?.task_result;
+            if !result.is_pending() {
+                return Ok(result);
+            }
+            println!("Tarefa {} ainda em andamento, aguardando...", task_id);
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.auth_token {
+            Some(token) => request.header("Authorization", format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+        context: &'static str,
+    ) -> Result<T, CapituError> {
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        println!("Resposta de {} ({}): {}", context, status, body);
+        parse(status, &body, context)
+    }
+}
+
+/// Interpreta uma resposta do serviço, que pode vir com status de sucesso e um campo `error`
+fn parse<T: DeserializeOwned>(
+    status: StatusCode,
+    body: &str,
+    context: &'static str,
+) -> Result<T, CapituError> {
+    if !status.is_success() {
+        return Err(CapituError::Status {
+            status,
+            body: body.to_string(),
+        });
+    }
+    let json: Value = serde_json::from_str(body).map_err(|e| CapituError::Parse {
+        context,
+        message: e.to_string(),
+    })?;
+    match json.get("error") {
+        Some(Value::String(message)) => return Err(CapituError::Service(message.clone())),
+        Some(error) if !error.is_null() => return Err(CapituError::Service(error.to_string())),
+        _ => {}
+    }
+    serde_json::from_value(json).map_err(|e| CapituError::Parse {
+        context,
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let upload: UploadResponse = parse(
+            StatusCode::OK,
+            r#"{"filename_id":"abc","extra":1}"#,
+            "upload",
+        )
+        .unwrap();
+        assert_eq!(upload.filename_id, "abc");
+        assert_eq!(
+            serde_json::to_string(&upload).unwrap(),
+            r#"{"filename_id":"abc"}"#
+        );
+
+        let status: TaskStatus = parse(
+            StatusCode::OK,
+            r#"{"task_result":{"result":"success","text":"00:00 Início"}}"#,
+            "task status",
+        )
+        .unwrap();
+        assert!(status.task_result.is_success());
+        assert_eq!(status.task_result.text, Some(Value::from("00:00 Início")));
+
+        // O tempo limite no servidor é um status, não um erro de comunicação
+        let status: TaskStatus = parse(
+            StatusCode::OK,
+            r#"{"task_result":{"result":"timeout_get"}}"#,
+            "task status",
+        )
+        .unwrap();
+        assert!(!status.task_result.is_pending() && !status.task_result.is_success());
+
+        // Áudio já transcrito: sem `task_id`, com a resposta repassada inteira
+        let task: TranscriptionTask = parse(
+            StatusCode::OK,
+            r#"{"status":"done","message":"Já transcrito"}"#,
+            "process transcription",
+        )
+        .unwrap();
+        assert_eq!(task.task_id, None);
+        assert_eq!(
+            serde_json::to_value(&task).unwrap(),
+            serde_json::json!({"status": "done", "message": "Já transcrito"})
+        );
+        let task: TranscriptionTask = parse(
+            StatusCode::OK,
+            r#"{"task_id":"t1"}"#,
+            "process transcription",
+        )
+        .unwrap();
+        assert_eq!(task.task_id.as_deref(), Some("t1"));
+
+        // O frontend procura o código nas mensagens (402 e 429)
+        let error = parse::<ChapterTask>(StatusCode::PAYMENT_REQUIRED, "quota", "chapters");
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "CapituAI service returned 402 Payment Required: quota"
+        );
+
+        let error = parse::<UploadResponse>(StatusCode::OK, r#"{"error":"Sem créditos"}"#, "x");
+        assert!(matches!(error, Err(CapituError::Service(message)) if message == "Sem créditos"));
+
+        let error = parse::<ChapterTask>(StatusCode::OK, r#"{"status":"ok"}"#, "generate chapters");
+        assert!(matches!(
+            error,
+            Err(CapituError::Parse {
+                context: "generate chapters",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_chapter_request() {
+        let request = ChapterRequest {
+            filename_id: "abc".to_string(),
+            transcript: "[]".to_string(),
+            pauses: vec![Pause {
+                start: 1.0,
+                end: 3.5,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "filename_id": "abc",
+                "transcript": "[]",
+                "pauses": [{"start": 1.0, "end": 3.5}],
+            })
+        );
+    }
+}
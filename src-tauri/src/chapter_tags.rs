@@ -0,0 +1,576 @@
+use std::fs;
+use std::path::Path;
+
+use crate::chapters::Chapter;
+
+/// Espaço livre deixado no fim da tag ID3, para outros programas editarem sem reescrever o
+/// arquivo inteiro
+const ID3_PADDING: usize = 1024;
+/// O CTOC e o `chpl` guardam a quantidade de capítulos em um byte
+const MAX_CHAPTERS: usize = 255;
+/// O `chpl` guarda os tempos em unidades de 100 ns
+const CHPL_UNITS_PER_SEC: f64 = 10_000_000.0;
+
+/// Grava os capítulos dentro do próprio arquivo de áudio, mantendo as outras tags
+///
+/// MP3 recebe frames ID3v2 `CHAP` com um `CTOC` apontando para eles, M4A/M4B/MP4 recebem o
+/// átomo de capítulos `moov/udta/chpl`. Capítulos gravados antes são substituídos. O arquivo
+/// é escrito ao lado e renomeado por cima do original, para não ficar pela metade.
+///
+/// O `chpl` não é lido pelo Apple Podcasts nem pelo Apple Books, que só mostram capítulos de
+/// uma trilha de texto do QuickTime (`tref/chap`), e essa trilha não é gravada.
+pub fn embed(path: &Path, chapters: &[Chapter]) -> Result<(), String> {
+    if chapters.len() > MAX_CHAPTERS {
+        return Err(format!(
+            "No máximo {} capítulos podem ser gravados no arquivo",
+            MAX_CHAPTERS
+        ));
+    }
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let data = fs::read(path).map_err(|e| format!("Failed to read audio file: {}", e))?;
+    let tagged = match extension.as_str() {
+        "mp3" => embed_id3(&data, chapters)?,
+        "m4a" | "m4b" | "mp4" => embed_mp4(&data, chapters)?,
+        _ => return Err(format!("Formato sem suporte a capítulos: {}", extension)),
+    };
+
+    let temporary = path.with_extension(format!("{}.tmp", extension));
+    fs::write(&temporary, tagged).map_err(|e| format!("Failed to write audio file: {}", e))?;
+    fs::rename(&temporary, path).map_err(|e| format!("Failed to replace audio file: {}", e))
+}
+
+/// Reescreve a tag ID3v2 com os capítulos, criando uma v2.3 se o arquivo não tiver tag
+fn embed_id3(data: &[u8], chapters: &[Chapter]) -> Result<Vec<u8>, String> {
+    let (version, mut frames, audio_start) = match data.starts_with(b"ID3") {
+        true => read_id3(data)?,
+        false => (3, Vec::new(), 0),
+    };
+
+    let ids: Vec<String> = (0..chapters.len()).map(|i| format!("chp{}", i)).collect();
+    for (chapter, id) in chapters.iter().zip(&ids) {
+        let mut body = id.as_bytes().to_vec();
+        body.push(0);
+        body.extend(millis(chapter.start).to_be_bytes());
+        body.extend(millis(chapter.end).to_be_bytes());
+        // Sem posição em bytes, os players usam os tempos
+        body.extend(u32::MAX.to_be_bytes());
+        body.extend(u32::MAX.to_be_bytes());
+        body.extend(id3_frame(
+            version,
+            b"TIT2",
+            &id3_text(version, &chapter.title),
+        ));
+        frames.extend(id3_frame(version, b"CHAP", &body));
+    }
+    if !chapters.is_empty() {
+        let mut body = b"toc\0".to_vec();
+        body.push(0b11); // índice principal, capítulos em ordem
+        body.push(chapters.len() as u8);
+        for id in &ids {
+            body.extend(id.as_bytes());
+            body.push(0);
+        }
+        frames.extend(id3_frame(version, b"CTOC", &body));
+    }
+    frames.resize(frames.len() + ID3_PADDING, 0);
+
+    let mut tagged = b"ID3".to_vec();
+    tagged.extend([version, 0, 0]);
+    tagged.extend(syncsafe(frames.len() as u32));
+    tagged.extend(frames);
+    tagged.extend(&data[audio_start..]);
+    Ok(tagged)
+}
+
+/// `(versão, frames sem CHAP/CTOC, início do áudio)` da tag no começo de `data`
+fn read_id3(data: &[u8]) -> Result<(u8, Vec<u8>, usize), String> {
+    if data.len() < 10 {
+        return Err("Tag ID3 incompleta".to_string());
+    }
+    let version = data[3];
+    let flags = data[5];
+    if version != 3 && version != 4 {
+        return Err(format!("ID3v2.{} não é suportado", version));
+    }
+    // Tags com "unsynchronisation" precisariam ser decodificadas antes
+    if flags & 0x80 != 0 {
+        return Err("Tags ID3 com unsynchronisation não são suportadas".to_string());
+    }
+    let end = 10 + from_syncsafe(&data[6..10]) as usize;
+    let footer = if flags & 0x10 != 0 { 10 } else { 0 };
+    if end > data.len() {
+        return Err("Tag ID3 maior que o arquivo".to_string());
+    }
+
+    let mut pos = 10;
+    // O cabeçalho estendido é opcional e não é mantido
+    if flags & 0x40 != 0 && pos + 4 <= end {
+        pos += match version {
+            3 => 4 + u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize,
+            _ => from_syncsafe(&data[pos..pos + 4]) as usize,
+        };
+    }
+
+    let mut frames = Vec::new();
+    while pos + 10 <= end && data[pos] != 0 {
+        let id = &data[pos..pos + 4];
+        let size = match version {
+            3 => u32::from_be_bytes(data[pos + 4..pos + 8].try_into().unwrap()),
+            _ => from_syncsafe(&data[pos + 4..pos + 8]),
+        } as usize;
+        let frame_end = (pos + 10 + size).min(end);
+        if id != b"CHAP" && id != b"CTOC" {
+            frames.extend(&data[pos..frame_end]);
+        }
+        pos = frame_end;
+    }
+    Ok((version, frames, end + footer))
+}
+
+fn id3_frame(version: u8, id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut frame = id.to_vec();
+    match version {
+        3 => frame.extend((body.len() as u32).to_be_bytes()),
+        _ => frame.extend(syncsafe(body.len() as u32)),
+    }
+    frame.extend([0, 0]);
+    frame.extend(body);
+    frame
+}
+
+/// Texto em UTF-16 com BOM na v2.3, que não tem UTF-8, e em UTF-8 na v2.4
+fn id3_text(version: u8, text: &str) -> Vec<u8> {
+    match version {
+        3 => {
+            let mut body = vec![1, 0xFF, 0xFE];
+            body.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            body
+        }
+        _ => {
+            let mut body = vec![3];
+            body.extend(text.as_bytes());
+            body
+        }
+    }
+}
+
+fn syncsafe(value: u32) -> [u8; 4] {
+    [21, 14, 7, 0].map(|shift| ((value >> shift) & 0x7F) as u8)
+}
+
+fn from_syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| (value << 7) | (*byte & 0x7F) as u32)
+}
+
+fn millis(seconds: f64) -> u32 {
+    (seconds.max(0.0) * 1000.0).round() as u32
+}
+
+/// Caixa (átomo) de um MP4, com as posições relativas ao trecho lido
+struct Mp4Box {
+    kind: [u8; 4],
+    start: usize,
+    body: usize,
+    end: usize,
+}
+
+fn mp4_boxes(data: &[u8]) -> Result<Vec<Mp4Box>, String> {
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as u64;
+        let kind: [u8; 4] = data[pos + 4..pos + 8].try_into().unwrap();
+        let (size, header) = match size {
+            // Vai até o fim do arquivo
+            0 => ((data.len() - pos) as u64, 8),
+            1 if pos + 16 <= data.len() => (
+                u64::from_be_bytes(data[pos + 8..pos + 16].try_into().unwrap()),
+                16,
+            ),
+            size => (size, 8),
+        };
+        let end = pos as u64 + size;
+        if size < header as u64 || end > data.len() as u64 {
+            return Err(format!(
+                "Átomo MP4 inválido: {}",
+                String::from_utf8_lossy(&kind)
+            ));
+        }
+        boxes.push(Mp4Box {
+            kind,
+            start: pos,
+            body: pos + header,
+            end: end as usize,
+        });
+        pos = end as usize;
+    }
+    Ok(boxes)
+}
+
+fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut atom = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+    atom.extend(kind);
+    atom.extend(body);
+    atom
+}
+
+/// Substitui o `chpl` dentro de `moov/udta`, mantendo o resto do `udta` (tags do iTunes...)
+fn embed_mp4(data: &[u8], chapters: &[Chapter]) -> Result<Vec<u8>, String> {
+    let top = mp4_boxes(data)?;
+    let moov = top
+        .iter()
+        .find(|atom| &atom.kind == b"moov")
+        .ok_or("Arquivo MP4 sem o átomo moov")?;
+
+    let mut moov_body = Vec::new();
+    let mut udta = Vec::new();
+    for atom in mp4_boxes(&data[moov.body..moov.end])? {
+        let raw = &data[moov.body + atom.start..moov.body + atom.end];
+        if &atom.kind != b"udta" {
+            moov_body.extend(raw);
+            continue;
+        }
+        let udta_body = &raw[atom.body - atom.start..];
+        for child in mp4_boxes(udta_body)? {
+            if &child.kind != b"chpl" {
+                udta.extend(&udta_body[child.start..child.end]);
+            }
+        }
+    }
+    udta.extend(mp4_box(b"chpl", &chpl(chapters)));
+    moov_body.extend(mp4_box(b"udta", &udta));
+    let mut new_moov = mp4_box(b"moov", &moov_body);
+
+    // Com o moov antes do mdat, os dados de áudio andam junto com a mudança de tamanho
+    let delta = new_moov.len() as i64 - (moov.end - moov.start) as i64;
+    if delta != 0 {
+        patch_chunk_offsets(&mut new_moov[8..], moov.start as u64, delta)?;
+    }
+
+    let mut tagged = data[..moov.start].to_vec();
+    tagged.extend(new_moov);
+    tagged.extend(&data[moov.end..]);
+    Ok(tagged)
+}
+
+/// Átomo de capítulos do Nero, lido pelo ffmpeg, mp4v2 e pela maioria dos players, mas não
+/// pelos da Apple
+fn chpl(chapters: &[Chapter]) -> Vec<u8> {
+    let mut body = vec![1, 0, 0, 0]; // versão 1, sem flags
+    body.extend([0; 4]);
+    body.push(chapters.len() as u8);
+    for chapter in chapters {
+        let start = (chapter.start.max(0.0) * CHPL_UNITS_PER_SEC).round() as u64;
+        body.extend(start.to_be_bytes());
+        // O tamanho do título cabe em um byte, corta sem quebrar um caractere
+        let mut len = chapter.title.len().min(255);
+        while !chapter.title.is_char_boundary(len) {
+            len -= 1;
+        }
+        body.push(len as u8);
+        body.extend(&chapter.title.as_bytes()[..len]);
+    }
+    body
+}
+
+/// Soma `delta` às posições (`stco`/`co64`) que apontam para depois de `after`
+fn patch_chunk_offsets(container: &mut [u8], after: u64, delta: i64) -> Result<(), String> {
+    for atom in mp4_boxes(container)? {
+        let body = &mut container[atom.body..atom.end];
+        match &atom.kind {
+            b"trak" | b"mdia" | b"minf" | b"stbl" => patch_chunk_offsets(body, after, delta)?,
+            b"stco" | b"co64" => {
+                let width = if &atom.kind == b"stco" { 4 } else { 8 };
+                for entry in body
+                    .get_mut(8..)
+                    .unwrap_or_default()
+                    .chunks_exact_mut(width)
+                {
+                    let offset = match width {
+                        4 => u32::from_be_bytes(entry.try_into().unwrap()) as u64,
+                        _ => u64::from_be_bytes(entry.try_into().unwrap()),
+                    };
+                    if offset <= after {
+                        continue;
+                    }
+                    let shifted = offset
+                        .checked_add_signed(delta)
+                        .ok_or("Offset MP4 inválido")?;
+                    match width {
+                        4 => entry.copy_from_slice(
+                            &u32::try_from(shifted)
+                                .map_err(|_| "Arquivo MP4 grande demais para o stco")?
+                                .to_be_bytes(),
+                        ),
+                        _ => entry.copy_from_slice(&shifted.to_be_bytes()),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio;
+    use crate::test_support::temp_dir;
+
+    fn chapters() -> Vec<Chapter> {
+        vec![
+            Chapter {
+                title: "Abertura".to_string(),
+                start: 0.0,
+                end: 1.0,
+            },
+            Chapter {
+                title: "Entrevista com a convidada".to_string(),
+                start: 1.0,
+                end: 2.5,
+            },
+        ]
+    }
+
+    /// `(id, início, fim, título)` dos frames CHAP da tag
+    ///
+    /// O symphonia 0.5 pula os frames CHAP e CTOC sem ler (e também ignora o `chpl`), então
+    /// os capítulos são lidos aqui seguindo a especificação. O symphonia confere o resto: as
+    /// outras tags, a duração e o áudio.
+    fn read_chap_frames(data: &[u8]) -> Vec<(String, u32, u32, String)> {
+        let (version, frames, _) = read_id3(data).unwrap();
+        assert_eq!(version, 3);
+        let mut all = Vec::new();
+        let end = 10 + from_syncsafe(&data[6..10]) as usize;
+        let mut pos = 10;
+        while pos + 10 <= end && data[pos] != 0 {
+            let size = u32::from_be_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            let body = &data[pos + 10..pos + 10 + size];
+            if &data[pos..pos + 4] == b"CHAP" {
+                let id_end = body.iter().position(|b| *b == 0).unwrap();
+                let time = |at: usize| u32::from_be_bytes(body[at..at + 4].try_into().unwrap());
+                let title: Vec<u16> = body[id_end + 1 + 16 + 10 + 3..]
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect();
+                all.push((
+                    String::from_utf8(body[..id_end].to_vec()).unwrap(),
+                    time(id_end + 1),
+                    time(id_end + 5),
+                    String::from_utf16(&title).unwrap(),
+                ));
+            }
+            pos += 10 + size;
+        }
+        // Os frames mantidos não incluem os capítulos
+        assert!(!frames.windows(4).any(|w| w == b"CHAP"));
+        all
+    }
+
+    #[test]
+    fn test_embed_id3() {
+        let temp = temp_dir("chapter-tags");
+        let dir = temp.path();
+
+        // Tag com título e artista, seguida de 100 frames MP3 silenciosos
+        let mut tags = id3_frame(3, b"TIT2", b"\0Episodio 12");
+        tags.extend(id3_frame(3, b"TPE1", b"\0Capitu"));
+        let mut data = b"ID3\x03\x00\x00".to_vec();
+        data.extend(syncsafe(tags.len() as u32));
+        data.extend(tags);
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0x00];
+        frame.resize(417, 0);
+        data.extend(frame.repeat(100));
+        let path = dir.join("episodio.mp3");
+        fs::write(&path, &data).unwrap();
+        let before = audio::probe(&path).unwrap();
+
+        embed(&path, &chapters()).unwrap();
+        // Gravar de novo substitui os capítulos em vez de duplicar
+        embed(&path, &chapters()).unwrap();
+
+        let tagged = fs::read(&path).unwrap();
+        assert!(tagged.ends_with(&frame.repeat(100)));
+        assert_eq!(
+            read_chap_frames(&tagged),
+            vec![
+                ("chp0".to_string(), 0, 1000, "Abertura".to_string()),
+                (
+                    "chp1".to_string(),
+                    1000,
+                    2500,
+                    "Entrevista com a convidada".to_string()
+                ),
+            ]
+        );
+        assert_eq!(tagged.windows(4).filter(|w| w == b"CTOC").count(), 1);
+
+        // O symphonia continua lendo as tags e o áudio
+        let after = audio::probe(&path).unwrap();
+        assert_eq!(after.tags.get("title").unwrap(), "Episodio 12");
+        assert_eq!(after.tags.get("artist").unwrap(), "Capitu");
+        assert_eq!(after.duration, before.duration);
+        let mut decoded = 0;
+        audio::decode_mono(&path, |block, _| decoded += block.len()).unwrap();
+        assert_eq!(decoded, 100 * 1152);
+    }
+
+    /// M4A de PCM 16 bits mono a 8 kHz com título do iTunes, que o symphonia consegue ler
+    fn pcm_m4a(samples: &[i16]) -> Vec<u8> {
+        let full = |version_flags: u32, fields: &[u32]| {
+            let mut body = version_flags.to_be_bytes().to_vec();
+            for field in fields {
+                body.extend(field.to_be_bytes());
+            }
+            body
+        };
+        let rate = 8000;
+        let count = samples.len() as u32;
+
+        let mut mvhd = full(0, &[0, 0, 1000, count * 1000 / rate, 0x0001_0000]);
+        mvhd.extend([1, 0]);
+        mvhd.resize(100, 0);
+        let mut tkhd = full(1, &[0, 0, 1, 0, count * 1000 / rate, 0, 0, 0]);
+        tkhd.extend([1, 0]);
+        tkhd.resize(84, 0);
+        let mdhd = full(0, &[0, 0, rate, count, 0]);
+        let mut hdlr = full(0, &[0]);
+        hdlr.extend(b"soun");
+        hdlr.extend([0; 13]);
+
+        let mut sowt = vec![0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+        sowt.extend([0, 1, 0, 16, 0, 0, 0, 0]);
+        sowt.extend((rate << 16).to_be_bytes());
+        let mut stsd = full(0, &[1]);
+        stsd.extend(mp4_box(b"sowt", &sowt));
+        let mut stbl = mp4_box(b"stsd", &stsd);
+        stbl.extend(mp4_box(b"stts", &full(0, &[1, count, 1])));
+        stbl.extend(mp4_box(b"stsc", &full(0, &[1, 1, count, 1])));
+        stbl.extend(mp4_box(b"stsz", &full(0, &[2, count])));
+        stbl.extend(mp4_box(b"stco", &full(0, &[1, 0])));
+
+        let mut minf = mp4_box(b"smhd", &full(0, &[0]));
+        minf.extend(mp4_box(b"stbl", &stbl));
+        let mut mdia = mp4_box(b"mdhd", &mdhd);
+        mdia.extend(mp4_box(b"hdlr", &hdlr));
+        mdia.extend(mp4_box(b"minf", &minf));
+        let mut trak = mp4_box(b"tkhd", &tkhd);
+        trak.extend(mp4_box(b"mdia", &mdia));
+        let mut moov = mp4_box(b"mvhd", &mvhd);
+        moov.extend(mp4_box(b"trak", &trak));
+
+        // Título em moov/udta/meta/ilst, o `data` de tipo 1 é texto UTF-8
+        let mut title = full(1, &[0]);
+        title.extend(b"Episodio 12");
+        let mut meta_hdlr = full(0, &[0]);
+        meta_hdlr.extend(b"mdirappl");
+        meta_hdlr.extend([0; 9]);
+        let mut meta = full(0, &[]);
+        meta.extend(mp4_box(b"hdlr", &meta_hdlr));
+        meta.extend(mp4_box(
+            b"ilst",
+            &mp4_box(b"\xa9nam", &mp4_box(b"data", &title)),
+        ));
+        moov.extend(mp4_box(b"udta", &mp4_box(b"meta", &meta)));
+
+        let mut data = mp4_box(b"ftyp", b"M4A \0\0\0\0M4A isom");
+        let moov = mp4_box(b"moov", &moov);
+        let mdat_offset = (data.len() + moov.len() + 8) as u32;
+        data.extend(moov);
+        let audio: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        data.extend(mp4_box(b"mdat", &audio));
+        let position = data.windows(4).position(|w| w == b"stco").unwrap() + 12;
+        data[position..position + 4].copy_from_slice(&mdat_offset.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_embed_m4a_file() {
+        let temp = temp_dir("chapter-tags-m4a");
+        let dir = temp.path();
+
+        let samples: Vec<i16> = (0..24_000)
+            .map(|i| ((i % 80) * 400 - 16_000) as i16)
+            .collect();
+        let path = dir.join("episodio.m4a");
+        fs::write(&path, pcm_m4a(&samples)).unwrap();
+        let decode = |path: &Path| {
+            let mut decoded = Vec::new();
+            audio::decode_mono(path, |block, rate| {
+                assert_eq!(rate, 8000);
+                decoded.extend_from_slice(block);
+            })
+            .unwrap();
+            decoded
+        };
+        let before = decode(&path);
+        assert_eq!(before.len(), samples.len());
+        assert_eq!(
+            audio::probe(&path).unwrap().tags.get("title").unwrap(),
+            "Episodio 12"
+        );
+
+        embed(&path, &chapters()).unwrap();
+
+        // O moov cresceu com o chpl e o symphonia ainda acha o áudio no lugar certo
+        let tagged = fs::read(&path).unwrap();
+        assert_eq!(tagged.windows(4).filter(|w| w == b"chpl").count(), 1);
+        // O chpl entra no mesmo udta do título, que o symphonia continua lendo
+        let info = audio::probe(&path).unwrap();
+        assert_eq!(info.tags.get("title").unwrap(), "Episodio 12");
+        assert!(
+            (info.duration.unwrap() - 3.0).abs() < 0.01,
+            "{:?}",
+            info.duration
+        );
+        assert_eq!(decode(&path), before);
+    }
+
+    #[test]
+    fn test_embed_mp4() {
+        // ftyp, moov com uma trilha (stco apontando para o mdat) e tags do iTunes, depois o mdat
+        let stco = mp4_box(b"stco", &[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0]);
+        let trak = mp4_box(
+            b"trak",
+            &mp4_box(b"mdia", &mp4_box(b"minf", &mp4_box(b"stbl", &stco))),
+        );
+        let meta = mp4_box(b"meta", &[0, 0, 0, 0, 1, 2, 3]);
+        let mut moov_body = mp4_box(b"mvhd", &[0; 20]);
+        moov_body.extend(&trak);
+        moov_body.extend(mp4_box(b"udta", &meta));
+        let mut data = mp4_box(b"ftyp", b"M4A isom");
+        let moov = mp4_box(b"moov", &moov_body);
+        let mdat_offset = (data.len() + moov.len() + 8) as u32;
+        data.extend(moov);
+        data.extend(mp4_box(b"mdat", b"audio"));
+        let position = data.windows(4).position(|w| w == b"stco").unwrap() + 12;
+        data[position..position + 4].copy_from_slice(&mdat_offset.to_be_bytes());
+
+        let tagged = embed_mp4(&embed_mp4(&data, &chapters()).unwrap(), &chapters()).unwrap();
+
+        // A posição do áudio foi atualizada e o conteúdo continua lá
+        let position = tagged.windows(4).position(|w| w == b"stco").unwrap() + 12;
+        let offset = u32::from_be_bytes(tagged[position..position + 4].try_into().unwrap());
+        assert_eq!(&tagged[offset as usize..offset as usize + 5], b"audio");
+        // As tags existentes foram mantidas e há um único chpl
+        assert!(tagged.windows(7).any(|w| w == [0, 0, 0, 0, 1, 2, 3]));
+        assert_eq!(tagged.windows(4).filter(|w| w == b"chpl").count(), 1);
+
+        let start = tagged.windows(4).position(|w| w == b"chpl").unwrap() + 4;
+        let body = &tagged[start..];
+        assert_eq!(body[8], 2);
+        assert_eq!(u64::from_be_bytes(body[9..17].try_into().unwrap()), 0);
+        assert_eq!(&body[18..18 + body[17] as usize], b"Abertura");
+        let second = 18 + body[17] as usize;
+        assert_eq!(
+            u64::from_be_bytes(body[second..second + 8].try_into().unwrap()),
+            10_000_000
+        );
+    }
+}
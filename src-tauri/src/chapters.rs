@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sections::{self, Timeline};
+
+/// Capítulo como a interface recebe da geração, com o `timecode` no vídeo original
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChapterMark {
+    pub timecode: String,
+    pub title: String,
+}
+
+/// Capítulo com início e fim em segundos do áudio baixado
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Chapter {
+    pub title: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Converte os capítulos gerados para trechos do áudio de `duration` segundos
+///
+/// Os timecodes voltam para o áudio pelo `timeline`, cada capítulo termina onde começa o
+/// próximo e o último vai até o fim. Capítulos que caem no mesmo ponto (dentro de um trecho
+/// removido, por exemplo) ficam só com o último.
+pub fn resolve(
+    marks: &[ChapterMark],
+    timeline: &Timeline,
+    duration: f64,
+) -> Result<Vec<Chapter>, String> {
+    let mut starts = Vec::with_capacity(marks.len());
+    for mark in marks {
+        let original = sections::parse_timestamp(mark.timecode.trim())
+            .ok_or_else(|| format!("Timecode inválido: {}", mark.timecode))?;
+        starts.push((timeline.to_audio(original).min(duration), mark.title.trim()));
+    }
+    starts.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut chapters: Vec<Chapter> = Vec::with_capacity(starts.len());
+    for (i, (start, title)) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(duration, |next| next.0);
+        if end > *start {
+            chapters.push(Chapter {
+                title: title.to_string(),
+                start: *start,
+                end,
+            });
+        }
+    }
+    Ok(chapters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sponsorblock::SponsorSegment;
+    use youtube_dl::SponsorBlockCategory;
+
+    fn mark(timecode: &str, title: &str) -> ChapterMark {
+        ChapterMark {
+            timecode: timecode.to_string(),
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        // Áudio sem o patrocínio entre 1:00 e 1:30 do vídeo
+        let timeline = Timeline {
+            removed: vec![SponsorSegment {
+                start: 60.0,
+                end: 90.0,
+                category: SponsorBlockCategory::Sponsor,
+                label: "Patrocínio".to_string(),
+            }],
+            ..Default::default()
+        };
+        let marks = [
+            mark("02:00", "Perguntas"),
+            mark("00:00", "Introdução"),
+            mark("01:00", "Patrocínio"),
+            mark("01:30", " Tema principal "),
+        ];
+        let chapters = resolve(&marks, &timeline, 150.0).unwrap();
+        assert_eq!(
+            chapters,
+            vec![
+                Chapter {
+                    title: "Introdução".to_string(),
+                    start: 0.0,
+                    end: 60.0
+                },
+                Chapter {
+                    title: "Tema principal".to_string(),
+                    start: 60.0,
+                    end: 90.0
+                },
+                Chapter {
+                    title: "Perguntas".to_string(),
+                    start: 90.0,
+                    end: 150.0
+                },
+            ]
+        );
+
+        assert!(resolve(&[mark("1:2", "Errado")], &timeline, 150.0).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio;
+use crate::flac::FlacWriter;
+use crate::metadata::sidecar_path;
+use crate::preprocess::{Resampler, SPEECH_RATE};
+use crate::sections::{self, Timeline};
+use crate::vad::{self, VadConfig};
+
+/// Tamanho padrão dos pedaços enviados ao servidor de transcrição
+pub const DEFAULT_CHUNK_MINUTES: u32 = 20;
+
+/// Janela usada para medir a energia do áudio
+const FRAME_SECS: f64 = 0.05;
+/// Quanto antes ou depois do ponto ideal de corte procurar um silêncio
+const SEARCH_SECS: f64 = 60.0;
+/// Duração do trecho usado para comparar silêncios, evita cortar numa pausa entre sílabas
+const SILENCE_SECS: f64 = 0.5;
+/// O último pedaço pode passar do tamanho alvo em até 25%, em vez de virar um pedaço minúsculo
+const TAIL_TOLERANCE: f64 = 1.25;
+
+/// Pedaço do áudio já enviado, com o ID que o servidor devolveu
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub filename_id: String,
+    /// Início do pedaço no áudio completo, em segundos
+    pub start: f64,
+    pub end: f64,
+    /// Tarefa de transcrição do pedaço, salva por `process_transcription`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+}
+
+/// Pedaço do áudio gravado em disco, antes do envio
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkFile {
+    pub path: PathBuf,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Lista dos pedaços de um áudio, salva como `{id}.chunks.json`
+///
+/// `process_transcription` e `take_transcription` usam o arquivo para tratar os pedaços como
+/// se fossem um único áudio.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChunkManifest {
+    pub chunks: Vec<Chunk>,
+}
+
+impl ChunkManifest {
+    pub fn load(folder: &str, media: &str) -> Option<Self> {
+        let content = fs::read_to_string(sidecar_path(folder, media, "chunks")).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, folder: &str, media: &str) -> Result<(), String> {
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize chunks: {}", e))?;
+        fs::write(sidecar_path(folder, media, "chunks"), content)
+            .map_err(|e| format!("Failed to write chunks: {}", e))
+    }
+}
+
+/// Divide o áudio em pedaços de cerca de `target_secs`, cortando no silêncio mais próximo
+///
+/// Os pedaços são gravados em `folder` como `{stem}.partNN.flac`, em FLAC mono de 16 kHz
+/// qualquer que seja o formato do áudio, para não ficarem maiores que o original.
+pub fn split(path: &Path, folder: &str, target_secs: f64) -> Result<Vec<ChunkFile>, String> {
+    let (energies, frame_secs) = frame_energies(path)?;
+    let cuts = cut_points(&energies, frame_secs, target_secs);
+    let total = energies.len() as f64 * frame_secs;
+    println!("Dividindo {} em {} pedaços", path.display(), cuts.len() + 1);
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let bounds: Vec<(f64, f64)> = std::iter::once(0.0)
+        .chain(cuts.iter().copied())
+        .zip(cuts.iter().copied().chain(std::iter::once(total)))
+        .collect();
+    let files: Vec<ChunkFile> = bounds
+        .iter()
+        .enumerate()
+        .map(|(i, (start, end))| ChunkFile {
+            path: Path::new(folder).join(format!("{}.part{:02}.flac", stem, i + 1)),
+            start: *start,
+            end: *end,
+        })
+        .collect();
+
+    // Segunda leitura do arquivo, agora convertendo e gravando cada pedaço
+    let mut writer = ChunkWriter {
+        cut_samples: cuts
+            .iter()
+            .map(|cut| (cut * SPEECH_RATE as f64).round() as u64)
+            .collect(),
+        files: &files,
+        writer: Some(FlacWriter::create(&files[0].path, SPEECH_RATE)?),
+        current: 0,
+        position: 0,
+    };
+    let mut resampler: Option<Resampler> = None;
+    let mut output = Vec::new();
+    let mut result = Ok(());
+    audio::decode_mono(path, |block, rate| {
+        if result.is_err() {
+            return;
+        }
+        let resampler = resampler.get_or_insert_with(|| Resampler::new(rate, SPEECH_RATE));
+        output.clear();
+        resampler.process(block, &mut output);
+        result = writer.write(&output);
+    })?;
+    result?;
+    if let Some(mut resampler) = resampler {
+        output.clear();
+        resampler.finish(&mut output);
+        writer.write(&output)?;
+    }
+    writer.finish()?;
+
+    Ok(files)
+}
+
+/// Grava as amostras já convertidas, passando para o próximo arquivo em cada corte
+struct ChunkWriter<'a> {
+    /// Cortes em amostras de `SPEECH_RATE`
+    cut_samples: Vec<u64>,
+    files: &'a [ChunkFile],
+    writer: Option<FlacWriter>,
+    current: usize,
+    position: u64,
+}
+
+impl ChunkWriter<'_> {
+    fn write(&mut self, mut samples: &[f32]) -> Result<(), String> {
+        while let Some(&cut) = self.cut_samples.get(self.current) {
+            let remaining = cut.saturating_sub(self.position) as usize;
+            if remaining >= samples.len() {
+                break;
+            }
+            let (head, tail) = samples.split_at(remaining);
+            self.write_current(head)?;
+            if let Some(writer) = self.writer.take() {
+                writer.finish()?;
+            }
+            self.current += 1;
+            self.writer = Some(FlacWriter::create(
+                &self.files[self.current].path,
+                SPEECH_RATE,
+            )?);
+            self.position += remaining as u64;
+            samples = tail;
+        }
+        self.write_current(samples)?;
+        self.position += samples.len() as u64;
+        Ok(())
+    }
+
+    fn write_current(&mut self, samples: &[f32]) -> Result<(), String> {
+        match &mut self.writer {
+            Some(writer) => writer.write(samples),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self.writer {
+            Some(writer) => writer.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Energia de cada janela de `FRAME_SECS`, zerada onde o detector de voz não encontrou fala
+///
+/// Assim os cortes caem nas pausas de verdade e, sem pausa por perto, no trecho mais baixo.
+/// Devolve também a duração real de cada janela.
+fn frame_energies(path: &Path) -> Result<(Vec<f32>, f64), String> {
+    let config = VadConfig {
+        frame_secs: FRAME_SECS,
+        ..Default::default()
+    };
+    let analysis = vad::analyze(path, FRAME_SECS, false)?;
+    let speech = vad::smooth(
+        &vad::classify(&analysis.frames, &config),
+        &config,
+        analysis.frame_secs,
+    );
+    let energies = analysis
+        .frames
+        .iter()
+        .zip(speech)
+        .map(|(frame, speech)| {
+            if speech {
+                10f32.powf(frame.db / 20.0)
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    Ok((energies, analysis.frame_secs))
+}
+
+/// Escolhe os pontos de corte, em segundos, a cada `target_secs`
+///
+/// Cada corte cai no meio do trecho mais silencioso até `SEARCH_SECS` antes ou depois do
+/// ponto ideal. Os cortes seguintes são contados a partir do corte anterior.
+pub fn cut_points(energies: &[f32], frame_secs: f64, target_secs: f64) -> Vec<f64> {
+    let target = (target_secs / frame_secs).round() as usize;
+    let search = ((SEARCH_SECS / frame_secs) as usize).min(target / 2);
+    let window = ((SILENCE_SECS / frame_secs) as usize).max(1);
+    if target == 0 {
+        return Vec::new();
+    }
+
+    // Soma acumulada para calcular a média de qualquer janela em O(1)
+    let prefix: Vec<f64> = std::iter::once(0.0)
+        .chain(energies.iter().scan(0.0, |acc, e| {
+            *acc += *e as f64;
+            Some(*acc)
+        }))
+        .collect();
+    let window_energy = |start: usize| {
+        let end = (start + window).min(energies.len());
+        (prefix[end] - prefix[start]) / (end - start).max(1) as f64
+    };
+
+    let mut cuts = Vec::new();
+    let mut last = 0;
+    while (energies.len() - last) as f64 > target as f64 * TAIL_TOLERANCE {
+        let ideal = last + target;
+        let from = ideal - search;
+        let to = (ideal + search).min(energies.len().saturating_sub(window));
+        let best = (from..=to.max(from))
+            .min_by(|a, b| {
+                window_energy(*a)
+                    .total_cmp(&window_energy(*b))
+                    .then_with(|| a.abs_diff(ideal).cmp(&b.abs_diff(ideal)))
+            })
+            .unwrap_or(ideal);
+        let cut = best + window / 2;
+        cuts.push(cut as f64 * frame_secs);
+        last = cut;
+    }
+    cuts
+}
+
+/// Junta as transcrições dos pedaços, deslocando cada uma pelo início do pedaço
+///
+/// Listas de segmentos são concatenadas, textos são unidos por quebras de linha e objetos
+/// com `segments` têm os segmentos e o `text` unidos.
+pub fn stitch(parts: Vec<(Value, f64)>) -> Value {
+    let parts: Vec<Value> = parts
+        .into_iter()
+        .map(|(mut transcript, offset)| {
+            sections::remap_transcript(&mut transcript, &Timeline::with_offset(offset));
+            transcript
+        })
+        .collect();
+
+    if parts.iter().all(Value::is_array) {
+        Value::Array(
+            parts
+                .into_iter()
+                .flat_map(|part| part.as_array().cloned().unwrap_or_default())
+                .collect(),
+        )
+    } else if parts.iter().all(Value::is_string) {
+        Value::String(
+            parts
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    } else if parts
+        .iter()
+        .all(|part| part.get("segments").is_some_and(Value::is_array))
+    {
+        let segments: Vec<Value> = parts
+            .iter()
+            .flat_map(|part| part["segments"].as_array().cloned().unwrap_or_default())
+            .collect();
+        let text: Vec<&str> = parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect();
+        let mut stitched = parts[0].clone();
+        stitched["segments"] = Value::Array(segments);
+        if !text.is_empty() {
+            stitched["text"] = Value::String(text.join(" "));
+        }
+        stitched
+    } else {
+        Value::Array(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use serde_json::json;
+
+    #[test]
+    fn test_cut_points_prefer_silence() {
+        // 10 minutos de fala com silêncios em 4:10 e 8:20
+        let frame = 0.05;
+        let mut energies = vec![0.5f32; (600.0 / frame) as usize];
+        for silence in [250.0, 500.0] {
+            let start = (silence / frame) as usize;
+            energies[start..start + 20].fill(0.0);
+        }
+
+        let cuts = cut_points(&energies, frame, 240.0);
+        assert_eq!(cuts.len(), 2);
+        // O corte cai dentro do segundo de silêncio
+        assert!((250.0..=251.0).contains(&cuts[0]), "{:?}", cuts);
+        assert!((500.0..=501.0).contains(&cuts[1]), "{:?}", cuts);
+    }
+
+    #[test]
+    fn test_cut_points_short_audio() {
+        assert!(cut_points(&[0.5; 100], 0.05, 240.0).is_empty());
+        // Sem silêncio, corta no ponto ideal
+        let cuts = cut_points(&vec![0.5; 6000], 0.05, 100.0);
+        assert_eq!(cuts.len(), 2);
+        assert!((cuts[0] - 100.0).abs() < 0.5, "{:?}", cuts);
+    }
+
+    #[test]
+    fn test_split() {
+        let dir = temp_dir("chunking");
+        let source = dir.path().join("longo.wav");
+        fs::write(&source, audio::silent_wav(8000, 2, 10)).unwrap();
+
+        let files = split(&source, dir.path().to_str().unwrap(), 5.0).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].start, 0.0);
+        assert_eq!(files[1].end, 10.0);
+        assert!(files[0].path.ends_with("longo.part01.flac"));
+
+        // Um WAV estéreo de 8 kHz vira FLAC mono de 16 kHz
+        let infos: Vec<_> = files
+            .iter()
+            .map(|file| audio::probe(&file.path).unwrap())
+            .collect();
+        assert!(infos
+            .iter()
+            .all(|info| info.sample_rate == Some(SPEECH_RATE) && info.channels == Some(1)));
+        let durations: Vec<f64> = infos.iter().map(|info| info.duration.unwrap()).collect();
+        assert!((durations.iter().sum::<f64>() - 10.0).abs() < 0.01);
+        for (file, duration) in files.iter().zip(&durations) {
+            assert!((file.end - file.start - duration).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_stitch() {
+        let segments = stitch(vec![
+            (json!([{"start": 0.0, "end": 2.0, "text": "a"}]), 0.0),
+            (json!([{"start": 1.0, "end": 3.0, "text": "b"}]), 600.0),
+        ]);
+        assert_eq!(
+            segments,
+            json!([
+                {"start": 0.0, "end": 2.0, "text": "a"},
+                {"start": 601.0, "end": 603.0, "text": "b"}
+            ])
+        );
+
+        let text = stitch(vec![
+            (json!("00:05 Oi"), 0.0),
+            (json!("00:05 Tchau"), 600.0),
+        ]);
+        assert_eq!(text, json!("00:05 Oi\n10:05 Tchau"));
+
+        let objects = stitch(vec![
+            (json!({"text": "a", "segments": [{"start": 1.0}]}), 0.0),
+            (json!({"text": "b", "segments": [{"start": 1.0}]}), 60.0),
+        ]);
+        assert_eq!(
+            objects,
+            json!({"text": "a b", "segments": [{"start": 1.0}, {"start": 61.0}]})
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio;
+use crate::chapters::Chapter;
+use crate::flac::FlacWriter;
+
+/// Nome padrão dos arquivos, `01 - Introdução.mp3`
+pub const DEFAULT_TEMPLATE: &str = "{index:02} - {title}.{ext}";
+
+/// Taxas do MPEG-1 Layer III em kbit/s, pelo índice do cabeçalho
+const MPEG1_BITRATES: [u32; 15] = [
+    0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+/// Taxas do MPEG-2 e 2.5 Layer III em kbit/s
+const MPEG2_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+const MPEG1_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// Aviso enviado à interface a cada arquivo gravado
+#[derive(Clone, Debug, Serialize)]
+pub struct ClipProgress {
+    /// Posição do capítulo, começando em 1
+    pub index: usize,
+    pub total: usize,
+    pub title: String,
+    pub path: String,
+}
+
+/// Grava cada capítulo de `source` como um arquivo em `destination`
+///
+/// MP3 é cortado copiando os frames, sem recodificar, então cada corte cai no frame mais
+/// próximo (26 ms a 44,1 kHz). Os outros formatos não podem ser cortados assim sem um
+/// muxer, e viram FLAC com os mesmos canais e taxa do original.
+pub fn export<F>(
+    source: &Path,
+    chapters: &[Chapter],
+    destination: &Path,
+    template: &str,
+    mut on_clip: F,
+) -> Result<Vec<PathBuf>, String>
+where
+    F: FnMut(&ClipProgress),
+{
+    fs::create_dir_all(destination)
+        .map_err(|e| format!("Failed to create clips directory: {}", e))?;
+
+    let is_mp3 = source
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"));
+    let data = match is_mp3 {
+        true => Some(fs::read(source).map_err(|e| format!("Failed to read audio file: {}", e))?),
+        false => None,
+    };
+    let frames = data.as_deref().and_then(mp3_frames);
+    let ext = if frames.is_some() { "mp3" } else { "flac" };
+
+    let paths: Vec<PathBuf> = chapters
+        .iter()
+        .enumerate()
+        .map(|(i, chapter)| destination.join(file_name(template, i + 1, &chapter.title, ext)))
+        .collect();
+    let mut report = |i: usize| {
+        println!("Capítulo exportado: {}", paths[i].display());
+        on_clip(&ClipProgress {
+            index: i + 1,
+            total: chapters.len(),
+            title: chapters[i].title.clone(),
+            path: paths[i].to_string_lossy().to_string(),
+        });
+    };
+
+    match (data, frames) {
+        (Some(data), Some(frames)) => {
+            for (i, chapter) in chapters.iter().enumerate() {
+                let bytes: Vec<u8> = frames
+                    .iter()
+                    .filter(|frame| frame.time >= chapter.start && frame.time < chapter.end)
+                    .flat_map(|frame| &data[frame.offset..frame.offset + frame.len])
+                    .copied()
+                    .collect();
+                fs::write(&paths[i], bytes).map_err(|e| format!("Failed to write clip: {}", e))?;
+                report(i);
+            }
+        }
+        _ => {
+            // Uma única decodificação, gravando cada capítulo conforme o áudio passa por ele
+            let mut current: Option<(usize, FlacWriter)> = None;
+            let mut next = 0;
+            let mut position = 0u64;
+            let mut error = None;
+            audio::decode(source, |block, channels, rate| {
+                if error.is_some() {
+                    return;
+                }
+                for frame in block.chunks_exact(channels) {
+                    let time = position as f64 / rate as f64;
+                    position += 1;
+                    if current
+                        .as_ref()
+                        .is_some_and(|(i, _)| time >= chapters[*i].end)
+                    {
+                        let (i, writer) = current.take().unwrap();
+                        if let Err(e) = writer.finish() {
+                            error = Some(e);
+                            return;
+                        }
+                        report(i);
+                    }
+                    if current.is_none() && next < chapters.len() && time >= chapters[next].start {
+                        match FlacWriter::with_channels(&paths[next], rate, channels) {
+                            Ok(writer) => current = Some((next, writer)),
+                            Err(e) => {
+                                error = Some(e);
+                                return;
+                            }
+                        }
+                        next += 1;
+                    }
+                    if let Some((_, writer)) = &mut current {
+                        if let Err(e) = writer.write(frame) {
+                            error = Some(e);
+                            return;
+                        }
+                    }
+                }
+            })?;
+            if let Some(e) = error {
+                return Err(e);
+            }
+            if let Some((i, writer)) = current {
+                writer.finish()?;
+                report(i);
+            }
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Preenche o modelo do nome do arquivo
+///
+/// Aceita `{index}` (com `{index:02}` para completar com zeros ou `{index:3}` com espaços), `{title}` e `{ext}`. O título
+/// perde os caracteres que não podem aparecer em nomes de arquivo.
+pub fn file_name(template: &str, index: usize, title: &str, ext: &str) -> String {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        name.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        let field = &rest[open + 1..open + close];
+        match field.split_once(':') {
+            Some(("index", spec)) => {
+                let width = spec.parse().unwrap_or(0);
+                name.push_str(&match spec.starts_with('0') {
+                    true => format!("{:0width$}", index),
+                    false => format!("{:width$}", index),
+                });
+            }
+            _ => match field {
+                "index" => name.push_str(&index.to_string()),
+                "title" => name.push_str(&sanitize(title)),
+                "ext" => name.push_str(ext),
+                _ => name.push_str(&rest[open..=open + close]),
+            },
+        }
+        rest = &rest[open + close + 1..];
+    }
+    name.push_str(rest);
+    name
+}
+
+fn sanitize(title: &str) -> String {
+    let clean: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Windows não aceita nomes terminados em ponto ou espaço
+    let clean = clean.trim().trim_end_matches('.').to_string();
+    if clean.is_empty() {
+        "Capítulo".to_string()
+    } else {
+        clean
+    }
+}
+
+/// Frame de MP3 dentro do arquivo
+struct Mp3Frame {
+    offset: usize,
+    len: usize,
+    /// Início do frame, em segundos
+    time: f64,
+}
+
+/// Frames de áudio de um MP3 (MPEG Layer III), sem as tags e o frame Xing/Info
+///
+/// Devolve `None` se o arquivo tiver frames de outra camada, que não são cortados aqui.
+fn mp3_frames(data: &[u8]) -> Option<Vec<Mp3Frame>> {
+    let mut pos = 0;
+    // Tag ID3v2 no começo, o tamanho é "syncsafe" (7 bits por byte)
+    if data.len() >= 10 && &data[..3] == b"ID3" {
+        let size = data[6..10]
+            .iter()
+            .fold(0usize, |size, byte| (size << 7) | (*byte & 0x7F) as usize);
+        let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+        pos = 10 + size + footer;
+    }
+
+    let mut frames = Vec::new();
+    let mut samples = 0u64;
+    while pos + 4 <= data.len() {
+        let (len, frame_samples, rate) = match frame_header(&data[pos..pos + 4]) {
+            FrameHeader::Layer3 { len, samples, rate } => (len, samples, rate),
+            FrameHeader::OtherLayer => return None,
+            // Lixo entre frames ou a tag ID3v1 no fim, procura o próximo sincronismo
+            FrameHeader::Invalid => {
+                pos += 1;
+                continue;
+            }
+        };
+        if pos + len > data.len() {
+            break;
+        }
+        let frame = &data[pos..pos + len];
+        let is_info = frames.is_empty()
+            && [b"Xing", b"Info", b"VBRI"]
+                .iter()
+                .any(|tag| frame.windows(4).take(48).any(|w| w == *tag));
+        if !is_info {
+            frames.push(Mp3Frame {
+                offset: pos,
+                len,
+                time: samples as f64 / rate as f64,
+            });
+            samples += frame_samples as u64;
+        }
+        pos += len;
+    }
+    (!frames.is_empty()).then_some(frames)
+}
+
+/// O que os 4 bytes em uma posição do MP3 representam
+enum FrameHeader {
+    Layer3 {
+        len: usize,
+        samples: u32,
+        rate: u32,
+    },
+    /// Frame de Layer I ou II
+    OtherLayer,
+    Invalid,
+}
+
+fn frame_header(header: &[u8]) -> FrameHeader {
+    if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+        return FrameHeader::Invalid;
+    }
+    let version = (header[1] >> 3) & 0b11;
+    let layer = (header[1] >> 1) & 0b11;
+    let bitrate_index = (header[2] >> 4) as usize;
+    let rate_index = ((header[2] >> 2) & 0b11) as usize;
+    let padding = ((header[2] >> 1) & 1) as usize;
+    if version == 0b01 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || rate_index == 3
+    {
+        return FrameHeader::Invalid;
+    }
+    if layer != 0b01 {
+        return FrameHeader::OtherLayer;
+    }
+
+    let divisor = match version {
+        0b11 => 1,
+        0b10 => 2,
+        _ => 4,
+    };
+    let rate = MPEG1_SAMPLE_RATES[rate_index] / divisor;
+    let (bitrate, samples, factor) = match version == 0b11 {
+        true => (MPEG1_BITRATES[bitrate_index], 1152, 144),
+        false => (MPEG2_BITRATES[bitrate_index], 576, 72),
+    };
+    FrameHeader::Layer3 {
+        len: (factor * bitrate * 1000 / rate) as usize + padding,
+        samples,
+        rate,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::WavWriter;
+    use crate::test_support::temp_dir;
+
+    fn chapter(title: &str, start: f64, end: f64) -> Chapter {
+        Chapter {
+            title: title.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name(DEFAULT_TEMPLATE, 3, "Perguntas: parte 1/2", "mp3"),
+            "03 - Perguntas_ parte 1_2.mp3"
+        );
+        assert_eq!(
+            file_name("{title}-{index}.{ext}", 12, " ... ", "flac"),
+            "Capítulo-12.flac"
+        );
+        assert_eq!(file_name("{outro} {index:3}", 7, "", "mp3"), "{outro}   7");
+    }
+
+    #[test]
+    fn test_export_mp3() {
+        let temp = temp_dir("clips-mp3");
+        let dir = temp.path();
+
+        // Tag ID3v2 vazia, um frame Info e 100 frames de 128 kbit/s a 44,1 kHz (417 bytes)
+        let mut data = b"ID3\x03\x00\x00\x00\x00\x00\x0A".to_vec();
+        data.extend([0; 10]);
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0x00];
+        frame.resize(417, 0);
+        let mut info = frame.clone();
+        info[36..40].copy_from_slice(b"Info");
+        data.extend(&info);
+        for _ in 0..100 {
+            data.extend(&frame);
+        }
+        data.extend(b"TAG");
+        let source = dir.join("episodio.mp3");
+        fs::write(&source, &data).unwrap();
+
+        let frame_secs = 1152.0 / 44100.0;
+        let chapters = [
+            chapter("Abertura", 0.0, 1.0),
+            chapter("Resto", 1.0, 100.0 * frame_secs),
+        ];
+        let mut progress = Vec::new();
+        let paths = export(&source, &chapters, dir, DEFAULT_TEMPLATE, |clip| {
+            progress.push((clip.index, clip.total))
+        })
+        .unwrap();
+        assert_eq!(progress, vec![(1, 2), (2, 2)]);
+        assert_eq!(paths[0], dir.join("01 - Abertura.mp3"));
+
+        // 39 frames começam antes de 1s, sem a tag nem o frame Info
+        assert_eq!(fs::read(&paths[0]).unwrap(), frame.repeat(39));
+        assert_eq!(fs::metadata(&paths[1]).unwrap().len(), 61 * 417);
+    }
+
+    #[test]
+    fn test_export_flac() {
+        let temp = temp_dir("clips-flac");
+        let dir = temp.path();
+
+        let rate = 8000;
+        let source = dir.join("gravacao.wav");
+        let mut writer = WavWriter::create(&source, rate).unwrap();
+        let samples: Vec<f32> = (0..rate * 3).map(|i| (i / rate) as f32 * 0.25).collect();
+        writer.write(&samples).unwrap();
+        writer.finish().unwrap();
+
+        let chapters = [chapter("Um", 0.0, 1.0), chapter("Dois e três", 1.0, 3.0)];
+        let paths = export(
+            &source,
+            &chapters,
+            &dir.join("clips"),
+            "{index}.{ext}",
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(paths[1], dir.join("clips").join("2.flac"));
+
+        for (path, expected) in paths.iter().zip([vec![0.0], vec![0.25, 0.5]]) {
+            let info = audio::probe(path).unwrap();
+            assert_eq!(info.duration, Some(expected.len() as f64));
+            let mut decoded = Vec::new();
+            audio::decode_mono(path, |block, _| decoded.extend_from_slice(block)).unwrap();
+            for (second, level) in expected.iter().enumerate() {
+                let sample = decoded[second * rate as usize + 100];
+                assert!((sample - level).abs() < 1e-3, "{} {}", sample, level);
+            }
+        }
+    }
+}
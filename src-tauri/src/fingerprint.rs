@@ -0,0 +1,375 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio;
+use crate::preprocess::Resampler;
+
+/// Versão do algoritmo, impressões de versões diferentes não são comparáveis
+pub const FINGERPRINT_VERSION: u32 = 2;
+
+/// Taxa usada no cálculo, as bandas analisadas ficam abaixo de 2 kHz
+const RATE: u32 = 8000;
+/// Janela analisada, 256 ms
+const FRAME_LEN: usize = 2048;
+/// Avanço entre duas janelas, 100 ms
+const HOP_LEN: usize = 800;
+/// Bandas de frequência, uma a mais que os bits de cada código
+const BANDS: usize = 33;
+const MIN_FREQUENCY: f32 = 300.0;
+const MAX_FREQUENCY: f32 = 2000.0;
+/// Proporção máxima de bits diferentes para dois áudios serem o mesmo conteúdo
+const MAX_BIT_ERROR: f64 = 0.35;
+/// Quanto um áudio pode estar deslocado em relação ao outro (vinheta cortada, por exemplo)
+const MAX_OFFSET_SECS: f64 = 30.0;
+/// Diferença de duração aceita entre duas versões do mesmo conteúdo
+const DURATION_TOLERANCE: f64 = 0.1;
+/// Deslocamento máximo para reaproveitar a transcrição e os capítulos de uma duplicata
+///
+/// Os capítulos reaproveitados não são deslocados, então só servem quando os dois áudios
+/// começam no mesmo ponto, dentro da resolução de 100 ms da impressão.
+pub const MAX_REUSE_OFFSET_SECS: f64 = 0.5;
+/// Na busca do deslocamento só um a cada `SEARCH_STRIDE` códigos é comparado
+const SEARCH_STRIDE: usize = 4;
+
+/// Impressão digital acústica: um código de 32 bits a cada 100 ms
+///
+/// Cada bit diz se a diferença de energia entre duas bandas vizinhas subiu ou desceu em
+/// relação à janela anterior (Haitsma e Kalker), o que sobrevive a recodificação, mudança de
+/// volume e de taxa de amostragem.
+pub fn compute(path: &Path) -> Result<Vec<u32>, String> {
+    let mut resampler: Option<Resampler> = None;
+    let mut samples = Vec::new();
+    let mut codes = Vec::new();
+    let mut previous: Option<[f32; BANDS]> = None;
+    let edges = band_edges();
+    let mut analyze = |samples: &mut Vec<f32>, codes: &mut Vec<u32>| {
+        let mut start = 0;
+        while start + FRAME_LEN <= samples.len() {
+            let energies = band_energies(&samples[start..start + FRAME_LEN], &edges);
+            if let Some(previous) = &previous {
+                codes.push(code(previous, &energies));
+            }
+            previous = Some(energies);
+            start += HOP_LEN;
+        }
+        samples.drain(..start);
+    };
+
+    audio::decode_mono(path, |block, rate| {
+        let resampler = resampler.get_or_insert_with(|| Resampler::new(rate, RATE));
+        resampler.process(block, &mut samples);
+        analyze(&mut samples, &mut codes);
+    })?;
+    let mut resampler = resampler.ok_or("O arquivo não tem áudio")?;
+    resampler.finish(&mut samples);
+    analyze(&mut samples, &mut codes);
+    Ok(codes)
+}
+
+/// Duração coberta por `codes`, em segundos
+pub fn duration(codes: &[u32]) -> f64 {
+    codes.len() as f64 * HOP_LEN as f64 / RATE as f64
+}
+
+/// Áudio já indexado
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub media_id: String,
+    pub audio_file: String,
+    /// Tamanho do áudio quando a impressão foi calculada, para perceber se ele mudou
+    pub source_len: u64,
+    pub duration: f64,
+    /// Códigos em little-endian, em base64
+    pub fingerprint: String,
+}
+
+impl IndexEntry {
+    pub fn new(media_id: &str, audio_file: &str, source_len: u64, codes: &[u32]) -> Self {
+        let bytes: Vec<u8> = codes.iter().flat_map(|code| code.to_le_bytes()).collect();
+        Self {
+            media_id: media_id.to_string(),
+            audio_file: audio_file.to_string(),
+            source_len,
+            duration: duration(codes),
+            fingerprint: base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+
+    pub fn codes(&self) -> Vec<u32> {
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.fingerprint)
+            .unwrap_or_default()
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect()
+    }
+}
+
+/// Mídia com o mesmo conteúdo de outra já indexada
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DuplicateMatch {
+    pub media_id: String,
+    /// Nome do arquivo indexado, a chave do cache de transcrição no servidor
+    pub audio_file: String,
+    /// Quanto o áudio novo está adiantado (positivo) ou atrasado em relação ao indexado
+    pub offset: f64,
+    /// Proporção de bits iguais no trecho em comum, de 0 a 1
+    pub similarity: f64,
+}
+
+impl DuplicateMatch {
+    /// Se os dois áudios começam no mesmo ponto, para os tempos de um valerem no outro
+    pub fn is_aligned(&self) -> bool {
+        self.offset.abs() <= MAX_REUSE_OFFSET_SECS
+    }
+}
+
+/// Impressões de todos os áudios da pasta de saída, salvas em `fingerprints.json`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FingerprintIndex {
+    pub version: u32,
+    pub entries: Vec<IndexEntry>,
+}
+
+impl FingerprintIndex {
+    /// Lê o índice, começando um novo se ele não existir ou for de outra versão
+    pub fn load(folder: &str) -> Self {
+        fs::read_to_string(Self::path(folder))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Self>(&content).ok())
+            .filter(|index| index.version == FINGERPRINT_VERSION)
+            .unwrap_or_else(|| Self {
+                version: FINGERPRINT_VERSION,
+                entries: Vec::new(),
+            })
+    }
+
+    pub fn save(&self, folder: &str) -> Result<(), String> {
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize fingerprints: {}", e))?;
+        fs::write(Self::path(folder), content)
+            .map_err(|e| format!("Failed to write fingerprints: {}", e))
+    }
+
+    pub fn get(&self, media_id: &str) -> Option<&IndexEntry> {
+        self.entries.iter().find(|entry| entry.media_id == media_id)
+    }
+
+    /// Adiciona ou substitui a impressão de uma mídia
+    pub fn insert(&mut self, entry: IndexEntry) {
+        self.entries
+            .retain(|other| other.media_id != entry.media_id);
+        self.entries.push(entry);
+    }
+
+    /// A mídia indexada mais parecida com `codes`, se for o mesmo conteúdo
+    ///
+    /// Mídias cujo áudio não está mais em `folder` são ignoradas, não há o que reaproveitar.
+    pub fn find(&self, folder: &str, codes: &[u32], exclude: &str) -> Option<DuplicateMatch> {
+        let duration = duration(codes);
+        self.entries
+            .iter()
+            .filter(|entry| entry.media_id != exclude)
+            .filter(|entry| Path::new(folder).join(&entry.audio_file).exists())
+            .filter(|entry| {
+                (entry.duration - duration).abs()
+                    <= duration.max(entry.duration) * DURATION_TOLERANCE
+            })
+            .filter_map(|entry| {
+                let (offset, similarity) = compare(codes, &entry.codes())?;
+                Some(DuplicateMatch {
+                    media_id: entry.media_id.clone(),
+                    audio_file: entry.audio_file.clone(),
+                    offset: offset as f64 * HOP_LEN as f64 / RATE as f64,
+                    similarity,
+                })
+            })
+            .filter(|found| found.similarity >= 1.0 - MAX_BIT_ERROR)
+            .max_by(|a, b| a.similarity.total_cmp(&b.similarity))
+    }
+
+    fn path(folder: &str) -> PathBuf {
+        Path::new(folder).join("fingerprints.json")
+    }
+}
+
+/// Melhor alinhamento entre duas impressões: `(deslocamento em códigos, semelhança)`
+///
+/// O trecho em comum precisa cobrir quase todo o áudio mais curto, senão um trecho
+/// compartilhado (a mesma vinheta, por exemplo) seria tratado como o mesmo conteúdo.
+fn compare(a: &[u32], b: &[u32]) -> Option<(i64, f64)> {
+    let max_offset = (MAX_OFFSET_SECS * RATE as f64 / HOP_LEN as f64) as i64;
+    let min_overlap = (a.len().min(b.len()) as f64 * (1.0 - DURATION_TOLERANCE)) as usize;
+
+    // Busca rápida do deslocamento com parte dos códigos, depois a medida completa
+    let offset = (-max_offset..=max_offset)
+        .filter(|offset| overlap(a, b, *offset).len() >= min_overlap.max(1))
+        .min_by_key(|offset| bit_errors(a, b, *offset, SEARCH_STRIDE))?;
+    let compared = overlap(a, b, offset).len();
+    let errors = bit_errors(a, b, offset, 1);
+    Some((offset, 1.0 - errors as f64 / (compared as f64 * 32.0)))
+}
+
+/// Índices de `a` que têm correspondente em `b` quando `a[i]` corresponde a `b[i - offset]`
+fn overlap(a: &[u32], b: &[u32], offset: i64) -> std::ops::Range<usize> {
+    let start = offset.max(0) as usize;
+    let end = (b.len() as i64 + offset).clamp(0, a.len() as i64) as usize;
+    start..end.max(start)
+}
+
+fn bit_errors(a: &[u32], b: &[u32], offset: i64, stride: usize) -> u64 {
+    overlap(a, b, offset)
+        .step_by(stride)
+        .map(|i| (a[i] ^ b[(i as i64 - offset) as usize]).count_ones() as u64)
+        .sum()
+}
+
+/// Limites das bandas em índices do FFT, espaçados em escala logarítmica
+fn band_edges() -> [usize; BANDS + 1] {
+    let mut edges = [0; BANDS + 1];
+    let ratio = MAX_FREQUENCY / MIN_FREQUENCY;
+    for (i, edge) in edges.iter_mut().enumerate() {
+        let frequency = MIN_FREQUENCY * ratio.powf(i as f32 / BANDS as f32);
+        *edge = (frequency * FRAME_LEN as f32 / RATE as f32).round() as usize;
+    }
+    edges
+}
+
+fn band_energies(frame: &[f32], edges: &[usize; BANDS + 1]) -> [f32; BANDS] {
+    let mut re: Vec<f32> = frame
+        .iter()
+        .enumerate()
+        .map(|(i, s)| s * 0.5 * (1.0 - (2.0 * PI * i as f32 / FRAME_LEN as f32).cos()))
+        .collect();
+    let mut im = vec![0.0; FRAME_LEN];
+    audio::fft(&mut re, &mut im);
+
+    let mut energies = [0.0; BANDS];
+    for (band, energy) in energies.iter_mut().enumerate() {
+        *energy = (edges[band]..edges[band + 1].max(edges[band] + 1))
+            .map(|k| re[k] * re[k] + im[k] * im[k])
+            .sum();
+    }
+    energies
+}
+
+fn code(previous: &[f32; BANDS], current: &[f32; BANDS]) -> u32 {
+    (0..BANDS - 1).fold(0, |code, band| {
+        let difference =
+            (current[band] - current[band + 1]) - (previous[band] - previous[band + 1]);
+        (code << 1) | (difference > 0.0) as u32
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::WavWriter;
+    use crate::test_support::temp_dir;
+
+    /// "Fala" sintética: tons que mudam a cada 150 ms, com ruído de fundo
+    fn talk(rate: u32, secs: f32, seed: u32, gain: f32) -> Vec<f32> {
+        let mut state = seed;
+        let mut random = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32
+        };
+        let syllable = (rate as f32 * 0.15) as usize;
+        let mut samples = Vec::new();
+        while samples.len() < (rate as f32 * secs) as usize {
+            let (f1, f2) = (300.0 + 600.0 * random(), 900.0 + 900.0 * random());
+            let level = random();
+            for i in 0..syllable {
+                let t = (samples.len() + i) as f32 / rate as f32;
+                let value =
+                    level * (0.3 * (2.0 * PI * f1 * t).sin() + 0.2 * (2.0 * PI * f2 * t).sin());
+                samples.push(gain * value);
+            }
+        }
+        samples
+    }
+
+    fn write(dir: &Path, name: &str, rate: u32, samples: &[f32]) -> PathBuf {
+        let path = dir.join(name);
+        let mut writer = WavWriter::create(&path, rate).unwrap();
+        writer.write(samples).unwrap();
+        writer.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let temp = temp_dir("fingerprint");
+        let dir = temp.path();
+        let folder = dir.to_str().unwrap();
+
+        let original = write(dir, "original.wav", 16000, &talk(16000, 30.0, 1, 1.0));
+        let other = write(dir, "outra.wav", 16000, &talk(16000, 30.0, 2, 1.0));
+        let mut index = FingerprintIndex::load(folder);
+        let len = |path: &Path| fs::metadata(path).unwrap().len();
+        index.insert(IndexEntry::new(
+            "original",
+            "original.wav",
+            len(&original),
+            &compute(&original).unwrap(),
+        ));
+        index.insert(IndexEntry::new(
+            "outra",
+            "outra.wav",
+            len(&other),
+            &compute(&other).unwrap(),
+        ));
+        index.save(folder).unwrap();
+
+        let index = FingerprintIndex::load(folder);
+        assert_eq!(index.entries.len(), 2);
+        assert_eq!(index.get("original").unwrap().source_len, len(&original));
+        let codes = index.get("original").unwrap().codes();
+        assert!(
+            (duration(&codes) - 30.0).abs() < 0.5,
+            "{}",
+            duration(&codes)
+        );
+
+        // O mesmo conteúdo em outra taxa, mais baixo e com 2 s a mais no começo
+        let mut reupload = vec![0.0; 22050 * 2];
+        reupload.extend(talk(22050, 30.0, 1, 0.5));
+        let reupload = write(dir, "reupload.wav", 22050, &reupload);
+        let found = index
+            .find(folder, &compute(&reupload).unwrap(), "reupload")
+            .unwrap();
+        assert_eq!(found.media_id, "original");
+        assert_eq!(found.audio_file, "original.wav");
+        assert!((found.offset - 2.0).abs() < 0.15, "{:?}", found);
+        assert!(found.similarity > 0.8, "{:?}", found);
+        assert!(!found.is_aligned());
+
+        // Sem o silêncio no começo os tempos de um valem no outro
+        let aligned = write(dir, "alinhado.wav", 22050, &talk(22050, 30.0, 1, 0.5));
+        let found = index
+            .find(folder, &compute(&aligned).unwrap(), "alinhado")
+            .unwrap();
+        assert_eq!(found.media_id, "original");
+        assert!(found.is_aligned(), "{:?}", found);
+
+        // Conteúdo diferente com a mesma duração não é duplicado
+        let different = write(dir, "diferente.wav", 16000, &talk(16000, 30.0, 3, 1.0));
+        assert_eq!(
+            index.find(folder, &compute(&different).unwrap(), "diferente"),
+            None
+        );
+        // E a própria mídia não conta
+        assert_eq!(
+            index.find(folder, &codes, "original").map(|m| m.media_id),
+            None
+        );
+
+        // Um áudio apagado da pasta não é mais oferecido como duplicata
+        let aligned_codes = compute(&aligned).unwrap();
+        fs::remove_file(&original).unwrap();
+        assert_eq!(index.find(folder, &aligned_codes, "alinhado"), None);
+    }
+}
@@ -0,0 +1,469 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Amostras por frame, o tamanho que o `flac` usa por padrão
+const BLOCK_SIZE: usize = 4096;
+/// Bits por amostra gravados no arquivo
+const BITS_PER_SAMPLE: u32 = 16;
+/// Maior ordem do preditor fixo definida pelo formato
+const MAX_FIXED_ORDER: usize = 4;
+/// Maior ordem de partição testada para o código de Rice
+const MAX_PARTITION_ORDER: u32 = 6;
+/// Maior parâmetro de Rice com 4 bits (15 é reservado para o escape)
+const MAX_RICE_PARAM: u32 = 14;
+
+/// Escreve um FLAC de 16 bits aos poucos, como o `WavWriter`
+///
+/// Usa só os preditores fixos do formato, que já deixam voz em cerca de metade do tamanho
+/// do WAV sem precisar de LPC. Cada canal é codificado separadamente. O STREAMINFO é
+/// completado ao final.
+///
+/// O symphonia só decodifica, e as crates de codificação trazem LPC e opções de que o envio
+/// não precisa. Com só os preditores fixos o codificador fica pequeno, e `test_round_trip`
+/// confere com o decodificador do symphonia que as amostras de 16 bits voltam idênticas.
+pub struct FlacWriter {
+    file: BufWriter<File>,
+    sample_rate: u32,
+    /// Amostras ainda não gravadas, uma lista por canal
+    pending: Vec<Vec<i32>>,
+    /// Próximo canal em `write`, que recebe as amostras intercaladas
+    channel: usize,
+    frames: u64,
+    samples: u64,
+    min_frame: u32,
+    max_frame: u32,
+}
+
+impl FlacWriter {
+    pub fn create(path: &Path, sample_rate: u32) -> Result<Self, String> {
+        Self::with_channels(path, sample_rate, 1)
+    }
+
+    /// FLAC com até 8 canais, `write` recebe as amostras intercaladas
+    pub fn with_channels(path: &Path, sample_rate: u32, channels: usize) -> Result<Self, String> {
+        if !(1..=8).contains(&channels) {
+            return Err(format!("Unsupported FLAC channel count: {}", channels));
+        }
+        let file = File::create(path).map_err(|e| format!("Failed to create FLAC: {}", e))?;
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            sample_rate,
+            pending: vec![Vec::with_capacity(BLOCK_SIZE); channels],
+            channel: 0,
+            frames: 0,
+            samples: 0,
+            min_frame: u32::MAX,
+            max_frame: 0,
+        };
+        let header = writer.stream_info();
+        writer
+            .file
+            .write_all(&header)
+            .map_err(|e| format!("Failed to write FLAC: {}", e))?;
+        Ok(writer)
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        for sample in samples {
+            self.pending[self.channel].push((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i32);
+            self.channel = (self.channel + 1) % self.pending.len();
+            if self.channel == 0 && self.pending[0].len() == BLOCK_SIZE {
+                self.write_frame()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Grava o último frame e completa o STREAMINFO
+    pub fn finish(mut self) -> Result<(), String> {
+        // Um último grupo incompleto de amostras intercaladas é descartado
+        let complete = self.pending.iter().map(Vec::len).min().unwrap_or(0);
+        self.pending
+            .iter_mut()
+            .for_each(|channel| channel.truncate(complete));
+        if complete > 0 {
+            self.write_frame()?;
+        }
+        let header = self.stream_info();
+        let result = (|| {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.file.write_all(&header)?;
+            self.file.flush()
+        })();
+        result.map_err(|e| format!("Failed to write FLAC: {}", e))
+    }
+
+    fn write_frame(&mut self) -> Result<(), String> {
+        let frame = encode_frame(&self.pending, self.frames);
+        self.file
+            .write_all(&frame)
+            .map_err(|e| format!("Failed to write FLAC: {}", e))?;
+        self.min_frame = self.min_frame.min(frame.len() as u32);
+        self.max_frame = self.max_frame.max(frame.len() as u32);
+        self.samples += self.pending[0].len() as u64;
+        self.frames += 1;
+        self.pending.iter_mut().for_each(Vec::clear);
+        Ok(())
+    }
+
+    /// `fLaC` seguido do bloco STREAMINFO, o único bloco de metadados do arquivo
+    fn stream_info(&self) -> Vec<u8> {
+        let mut bits = BitWriter::default();
+        bits.write(1, 1); // último bloco de metadados
+        bits.write(0, 7); // STREAMINFO
+        bits.write(34, 24);
+        bits.write(BLOCK_SIZE as u64, 16);
+        bits.write(BLOCK_SIZE as u64, 16);
+        bits.write(self.min_frame.min(self.max_frame) as u64, 24);
+        bits.write(self.max_frame as u64, 24);
+        bits.write(self.sample_rate as u64, 20);
+        bits.write((self.pending.len() - 1) as u64, 3);
+        bits.write((BITS_PER_SAMPLE - 1) as u64, 5);
+        bits.write(self.samples, 36);
+        // MD5 zerado significa "não calculado"
+        bits.write(0, 64);
+        bits.write(0, 64);
+
+        let mut header = b"fLaC".to_vec();
+        header.extend(bits.into_bytes());
+        header
+    }
+}
+
+fn encode_frame(channels: &[Vec<i32>], number: u64) -> Vec<u8> {
+    let len = channels[0].len();
+    let mut bits = BitWriter::default();
+    bits.write(0b11_1111_1111_1110, 14); // sincronia
+    bits.write(0, 1);
+    bits.write(0, 1); // blocos de tamanho fixo, o cabeçalho leva o número do frame
+    let full = len == BLOCK_SIZE;
+    // 0b1100 = 4096 amostras, 0b0111 = tamanho em 16 bits no fim do cabeçalho
+    bits.write(if full { 0b1100 } else { 0b0111 }, 4);
+    bits.write(0, 4); // taxa de amostragem do STREAMINFO
+    bits.write((channels.len() - 1) as u64, 4); // canais independentes
+    bits.write(0b100, 3); // 16 bits
+    bits.write(0, 1);
+    write_utf8(&mut bits, number);
+    if !full {
+        bits.write((len - 1) as u64, 16);
+    }
+    let crc = crc8(bits.bytes());
+    bits.write(crc as u64, 8);
+
+    for samples in channels {
+        write_subframe(&mut bits, samples);
+    }
+    bits.align();
+    let crc = crc16(bits.bytes());
+    bits.write(crc as u64, 16);
+    bits.into_bytes()
+}
+
+fn write_subframe(bits: &mut BitWriter, samples: &[i32]) {
+    if samples.iter().all(|sample| *sample == samples[0]) {
+        bits.write(0, 1);
+        bits.write(0b000000, 6); // constante
+        bits.write(0, 1);
+        bits.write_signed(samples[0], BITS_PER_SAMPLE);
+        return;
+    }
+
+    // Escolhe a ordem do preditor com o menor resíduo
+    let best = (0..=MAX_FIXED_ORDER.min(samples.len() - 1))
+        .map(|order| {
+            let residual = fixed_residual(samples, order);
+            let (partition_order, params, cost) = rice_partitions(&residual, samples.len(), order);
+            (order, residual, partition_order, params, cost)
+        })
+        .min_by_key(|candidate| candidate.4);
+    let verbatim_cost = samples.len() as u64 * BITS_PER_SAMPLE as u64;
+
+    match best {
+        Some((order, residual, partition_order, params, cost)) if cost < verbatim_cost => {
+            bits.write(0, 1);
+            bits.write(0b001000 | order as u64, 6); // preditor fixo
+            bits.write(0, 1);
+            for sample in &samples[..order] {
+                bits.write_signed(*sample, BITS_PER_SAMPLE);
+            }
+            bits.write(0b00, 2); // Rice com parâmetro de 4 bits
+            bits.write(partition_order as u64, 4);
+            let partition_len = samples.len() >> partition_order;
+            let mut residual = residual.iter();
+            for (i, param) in params.iter().enumerate() {
+                bits.write(*param as u64, 4);
+                let count = if i == 0 {
+                    partition_len - order
+                } else {
+                    partition_len
+                };
+                for value in residual.by_ref().take(count) {
+                    bits.write_rice(zigzag(*value), *param);
+                }
+            }
+        }
+        _ => {
+            bits.write(0, 1);
+            bits.write(0b000001, 6); // sem compressão
+            bits.write(0, 1);
+            for sample in samples {
+                bits.write_signed(*sample, BITS_PER_SAMPLE);
+            }
+        }
+    }
+}
+
+/// Resíduo do preditor fixo de ordem `order`, a partir da amostra `order`
+fn fixed_residual(samples: &[i32], order: usize) -> Vec<i64> {
+    let s = |i: usize| samples[i] as i64;
+    (order..samples.len())
+        .map(|i| match order {
+            0 => s(i),
+            1 => s(i) - s(i - 1),
+            2 => s(i) - 2 * s(i - 1) + s(i - 2),
+            3 => s(i) - 3 * s(i - 1) + 3 * s(i - 2) - s(i - 3),
+            _ => s(i) - 4 * s(i - 1) + 6 * s(i - 2) - 4 * s(i - 3) + s(i - 4),
+        })
+        .collect()
+}
+
+/// Melhor ordem de partição, o parâmetro de cada partição e o custo total em bits
+fn rice_partitions(residual: &[i64], block_len: usize, order: usize) -> (u32, Vec<u32>, u64) {
+    let mut best = (0, Vec::new(), u64::MAX);
+    for partition_order in 0..=MAX_PARTITION_ORDER {
+        let partition_len = block_len >> partition_order;
+        // Cada partição precisa ter tamanho inteiro e a primeira precisa caber os warm-ups
+        if !block_len.is_multiple_of(1 << partition_order) || partition_len <= order {
+            break;
+        }
+
+        let mut params = Vec::new();
+        let mut cost = 6u64;
+        let mut start = 0;
+        for i in 0..1usize << partition_order {
+            let count = if i == 0 {
+                partition_len - order
+            } else {
+                partition_len
+            };
+            let partition = &residual[start..start + count];
+            start += count;
+            let (param, bits) = (0..=MAX_RICE_PARAM)
+                .map(|param| (param, rice_cost(partition, param)))
+                .min_by_key(|(_, bits)| *bits)
+                .unwrap_or((0, 0));
+            params.push(param);
+            cost += 4 + bits;
+        }
+        if cost < best.2 {
+            best = (partition_order, params, cost);
+        }
+    }
+    best
+}
+
+fn rice_cost(values: &[i64], param: u32) -> u64 {
+    values
+        .iter()
+        .map(|value| (zigzag(*value) >> param) + 1 + param as u64)
+        .sum()
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Número do frame no "UTF-8" estendido do FLAC
+fn write_utf8(bits: &mut BitWriter, value: u64) {
+    if value < 0x80 {
+        bits.write(value, 8);
+        return;
+    }
+    let mut continuation = 1;
+    while value >= 1 << (6 + 5 * continuation) {
+        continuation += 1;
+    }
+    let lead_mask = (0xFFu64 << (7 - continuation)) & 0xFF;
+    bits.write(lead_mask | (value >> (6 * continuation)), 8);
+    for i in (0..continuation).rev() {
+        bits.write(0x80 | ((value >> (6 * i)) & 0x3F), 8);
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |mut crc, byte| {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u64,
+    used: u32,
+}
+
+impl BitWriter {
+    /// Escreve os `count` bits menos significativos de `value`, do mais significativo ao menos
+    fn write(&mut self, value: u64, count: u32) {
+        for i in (0..count).rev() {
+            self.current = (self.current << 1) | ((value >> i) & 1);
+            self.used += 1;
+            if self.used == 8 {
+                self.bytes.push(self.current as u8);
+                self.current = 0;
+                self.used = 0;
+            }
+        }
+    }
+
+    fn write_signed(&mut self, value: i32, count: u32) {
+        self.write(value as u64 & ((1 << count) - 1), count);
+    }
+
+    fn write_rice(&mut self, value: u64, param: u32) {
+        for _ in 0..value >> param {
+            self.write(0, 1);
+        }
+        self.write(1, 1);
+        self.write(value, param);
+    }
+
+    fn align(&mut self) {
+        if self.used > 0 {
+            self.write(0, 8 - self.used);
+        }
+    }
+
+    /// Bytes completos escritos até agora
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio;
+    use crate::test_support::temp_dir;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_utf8() {
+        for (value, expected) in [
+            (0u64, vec![0x00]),
+            (0x7F, vec![0x7F]),
+            (0x80, vec![0xC2, 0x80]),
+            (0x7FF, vec![0xDF, 0xBF]),
+            (0x800, vec![0xE0, 0xA0, 0x80]),
+        ] {
+            let mut bits = BitWriter::default();
+            write_utf8(&mut bits, value);
+            assert_eq!(bits.into_bytes(), expected, "{:#x}", value);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = temp_dir("flac");
+        let path = dir.path().join("tom.flac");
+        let rate = 16000;
+        // Tom com ruído, silêncio (subframe constante) e um bloco final incompleto
+        let mut seed = 3u32;
+        let samples: Vec<f32> = (0..rate * 3 + 1234)
+            .map(|i| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let noise = (seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+                match i / rate {
+                    1 => 0.0,
+                    _ => 0.4 * (2.0 * PI * 300.0 * i as f32 / rate as f32).sin() + 0.01 * noise,
+                }
+            })
+            .collect();
+        let mut writer = FlacWriter::create(&path, rate).unwrap();
+        writer.write(&samples[..5000]).unwrap();
+        writer.write(&samples[5000..]).unwrap();
+        writer.finish().unwrap();
+
+        let info = audio::probe(&path).unwrap();
+        assert_eq!(info.codec.as_deref(), Some("flac"));
+        assert_eq!(info.sample_rate, Some(rate));
+        assert_eq!(info.channels, Some(1));
+        assert_eq!(info.duration, Some(samples.len() as f64 / rate as f64));
+
+        let mut decoded = Vec::new();
+        audio::decode_mono(&path, |block, _| decoded.extend_from_slice(block)).unwrap();
+        assert_eq!(decoded.len(), samples.len());
+        // Sem perdas, a menos da conversão para 16 bits
+        let expected = samples
+            .iter()
+            .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i32 as f32 / 32768.0);
+        assert!(decoded
+            .iter()
+            .zip(expected)
+            .all(|(a, b)| (a - b).abs() < 1e-6));
+
+        // E menor que o mesmo áudio em WAV
+        let size = std::fs::metadata(&path).unwrap().len();
+        assert!(size < samples.len() as u64 * 2 * 3 / 4, "{}", size);
+    }
+
+    #[test]
+    fn test_stereo() {
+        let dir = temp_dir("flac-stereo");
+        let path = dir.path().join("estereo.flac");
+        let rate = 44100;
+        // Canal esquerdo com um tom, direito em silêncio
+        let samples: Vec<f32> = (0..rate as usize * 2)
+            .flat_map(|i| [0.5 * (2.0 * PI * 440.0 * i as f32 / rate as f32).sin(), 0.0])
+            .collect();
+        let mut writer = FlacWriter::with_channels(&path, rate, 2).unwrap();
+        writer.write(&samples).unwrap();
+        writer.finish().unwrap();
+
+        let info = audio::probe(&path).unwrap();
+        assert_eq!(info.channels, Some(2));
+        assert_eq!(info.duration, Some(2.0));
+
+        let mut decoded = Vec::new();
+        audio::decode(&path, |block, channels, _| {
+            assert_eq!(channels, 2);
+            decoded.extend_from_slice(block);
+        })
+        .unwrap();
+        assert_eq!(decoded.len(), samples.len());
+        assert!(decoded.iter().skip(1).step_by(2).all(|s| *s == 0.0));
+        assert!(decoded
+            .iter()
+            .zip(&samples)
+            .all(|(a, b)| (a - b).abs() < 1e-4));
+    }
+}
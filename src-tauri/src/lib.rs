@@ -1,103 +1,118 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-use serde_json::Value;
-use std::path::Path;
+mod audio;
+mod availability;
+mod backend;
+mod capitu_client;
+mod chapter_tags;
+mod chapters;
+mod chunking;
+mod clips;
+mod fingerprint;
+mod flac;
+mod live;
+mod local_media;
+mod loudness;
+mod media;
+mod metadata;
+mod podcast;
+mod preprocess;
+mod sections;
+mod sponsorblock;
+#[cfg(test)]
+mod test_support;
+mod thumbnails;
+mod transcriber;
+mod vad;
+mod waveform;
+mod youtube_url;
+
+use backend::ActiveProfile;
+use capitu_client::{CapituClient, CapituError, ChapterRequest, Pause, UploadResponse};
+use chapters::ChapterMark;
+use chunking::{Chunk, ChunkManifest};
+use fingerprint::{DuplicateMatch, FingerprintIndex, IndexEntry};
+use live::LivePolicy;
+use local_media::{ImportMode, ImportedMedia};
+use loudness::LoudnessReport;
+use metadata::MediaMetadata;
+use podcast::{Episode, Feed};
+use preprocess::UploadFormat;
+use sections::Timeline;
+use sponsorblock::SponsorMode;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, PoisonError};
 use std::time::Duration;
 use tauri::{command, Emitter, Manager, Window};
 use tauri_plugin_deep_link::DeepLinkExt;
 use tauri_plugin_oauth::start;
-use tokio::time::sleep;
+use tokio::task::JoinHandle;
+use transcriber::{Engine, LocalTranscript, Transcriber, TranscriberConfig};
+use vad::{VadConfig, VoiceActivity};
+use waveform::{PeakCache, Peaks};
 
-use youtube_dl::YoutubeDl;
+use youtube_dl::{ReplayOptions, ReplayRange, SponsorBlockCategory, TimeRange, YoutubeDl};
+use youtube_url::YoutubeUrl;
 // use std::process::Command;
-use std::fs::File;
 // use std::io::BufReader;
-use symphonia::core::formats::FormatOptions;
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
 // use symphonia::core::units::Time;
 
 #[cfg(target_os = "macos")]
 const OUTPUT_FOLDER: &str = "/Users/Shared/capituai/output";
 #[cfg(not(target_os = "macos"))]
 const OUTPUT_FOLDER: &str = "../output";
-const CAPITU_LANGCHAIN_URL: &str = "https://kamikazebr--capitu-ai-langchain-fastapi-app.modal.run";
-
-const TRANSCRIBE_YOUTUBE_URL: &str = "https://kamikazebr--transcribe-youtube-fastapi-app.modal.run";
-
-/// Extrai o ID do vídeo de uma URL do YouTube
-///
-/// Esta função extrai o ID de 11 caracteres de qualquer URL do YouTube usando o seguinte método:
-/// 1. Divide a string em partes usando caracteres inválidos como separadores
-/// 2. Procura por uma parte que tenha exatamente 11 caracteres (tamanho padrão dos IDs)
-///
-/// # Exemplos
-/// ```rust
-/// let id = extract_youtube_video_id("https://youtu.be/dQw4w9WgXcQ");
-/// assert_eq!(id, Some("dQw4w9WgXcQ".to_string()));
-///
-/// // Funciona com vários formatos de URL
-/// let id = extract_youtube_video_id("https://www.youtube.com/watch?v=dQw4w9WgXcQ");
-/// let id = extract_youtube_video_id("https://youtube.com/shorts/dQw4w9WgXcQ");
-/// let id = extract_youtube_video_id("https://youtu.be/dQw4w9WgXcQ");
-/// ```
-fn extract_youtube_video_id(url: &str) -> Option<String> {
-    // IDs do YouTube sempre têm 11 caracteres
-    // Procura por uma sequência de exatamente 11 caracteres que podem ser letras, números, - ou _
-    url.split(|c| !matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_'))
-        .find(|s| s.len() == 11)
-        .map(String::from)
-}
 
 #[command]
 fn get_audio_duration(file_path: &str) -> Result<f64, String> {
-    // Abre o arquivo
     let file_path = format!("{}/{}", OUTPUT_FOLDER, file_path);
     println!("get_audio_duration::File path: {}", file_path);
-    let file = File::open(file_path).map_err(|e| format!("Failed to open file: {}", e))?;
-
-    // Cria o MediaSourceStream
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
-
-    // Cria um hint para ajudar no probe do formato
-    let mut hint = Hint::new();
-    hint.with_extension("mp3");
-
-    // Usa o probe padrão do symphonia que já tem todos os formatos habilitados
-    let mut format_opts = FormatOptions::default();
-    // Habilita suporte a gapless playback que pode ajudar na precisão
-    format_opts.enable_gapless = true;
-
-    // Faz o probe do formato
-    let probed = symphonia::default::get_probe()
-        .format(&hint, mss, &format_opts, &MetadataOptions::default())
-        .map_err(|e| format!("Failed to probe format: {}", e))?;
-
-    // Obtém o primeiro track de áudio
-    let track = probed
-        .format
-        .tracks()
-        .iter()
-        .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
-        .ok_or_else(|| "No valid audio track found".to_string())?;
-
-    // Tenta obter a duração das propriedades do track
-    if let Some(n_frames) = track.codec_params.n_frames {
-        if let Some(sample_rate) = track.codec_params.sample_rate {
-            let duration = (n_frames as f64) / (sample_rate as f64);
-            return Ok(duration);
-        }
-    }
+    audio::probe(Path::new(&file_path))?
+        .duration
+        .ok_or_else(|| "Could not determine duration".to_string())
+}
 
-    // Se não conseguiu pelos frames, tenta obter a duração do formato
-    if let Some(time_base) = track.codec_params.time_base {
-        if let Some(n_frames) = track.codec_params.n_frames {
-            let duration = (n_frames as f64) * (time_base.numer as f64) / (time_base.denom as f64);
-            return Ok(duration);
-        }
-    }
+/// Duração, codec, taxa de amostragem, canais, bitrate e tags de um arquivo da pasta de saída
+#[command]
+fn audio_info(file_path: &str) -> Result<audio::AudioInfo, String> {
+    let file_path = format!("{}/{}", OUTPUT_FOLDER, file_path);
+    println!("audio_info::File path: {}", file_path);
+    audio::probe(Path::new(&file_path))
+}
 
-    Err("Could not determine duration".to_string())
+/// Importa uma gravação do computador para a pasta de saída, sem passar pelo yt-dlp
+///
+/// O arquivo recebe um ID baseado no conteúdo e segue para `upload_audio` e
+/// `process_transcription` como um áudio baixado. O hash, a cópia e a leitura do arquivo
+/// rodam fora do runtime, vídeos grandes levam um tempo.
+#[command]
+async fn import_media(path: String, mode: Option<ImportMode>) -> Result<ImportedMedia, String> {
+    tokio::task::spawn_blocking(move || {
+        let source = Path::new(&path);
+        println!("Importando arquivo local: {}", path);
+        let imported = local_media::import(source, OUTPUT_FOLDER, mode.unwrap_or_default())?;
+
+        // A biblioteca mostra o nome original do arquivo como título
+        let title = source
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| imported.media_id.clone());
+        let audio_file = Path::new(&imported.file_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        MediaMetadata::from_file(
+            &imported.media_id,
+            &audio_file,
+            &title,
+            imported.info.duration,
+        )
+        .save(OUTPUT_FOLDER)?;
+        index_fingerprint_later(&imported.media_id, Path::new(&imported.file_path));
+
+        Ok(imported)
+    })
+    .await
+    .map_err(|e| format!("Failed to import media: {}", e))?
 }
 
 #[command]
@@ -110,229 +125,818 @@ async fn start_server(window: Window) -> Result<u16, String> {
     .map_err(|err| err.to_string())
 }
 
+/// Interpreta o link colado pelo usuário, para a interface mostrar o que será baixado
+#[command]
+fn parse_url(url: &str) -> Result<YoutubeUrl, String> {
+    YoutubeUrl::parse(url)
+}
+
 #[command]
-async fn download_audio(url: &str) -> Result<String, String> {
-    // Extrai o ID do vídeo da URL usando a nova função
-    let video_id = extract_youtube_video_id(url)
-        .ok_or_else(|| "URL do YouTube inválida ou ID do vídeo não encontrado".to_string())?;
+async fn download_audio(
+    url: &str,
+    range: Option<TimeRange>,
+    live_policy: Option<LivePolicy>,
+    live_window_secs: Option<u64>,
+    sponsor_mode: Option<SponsorMode>,
+    sponsor_categories: Option<Vec<SponsorBlockCategory>>,
+) -> Result<String, String> {
+    let sponsor_mode = sponsor_mode.unwrap_or_default();
+    let sponsor_categories =
+        sponsor_categories.unwrap_or_else(|| sponsorblock::DEFAULT_CATEGORIES.to_vec());
+
+    // Links do YouTube já trazem o ID, então o cache é consultado sem chamar o yt-dlp
+    let youtube_url = YoutubeUrl::parse(url).ok();
+    let url = &match &youtube_url {
+        // Usa o link canônico, senão um `list=` na URL faz o yt-dlp baixar a playlist inteira
+        Some(youtube_url) => youtube_url.watch_url()?,
+        // Outros sites (Vimeo, SoundCloud, Twitch...) vão direto para o yt-dlp
+        None => url.to_string(),
+    };
 
     // Garante que o diretório de saída exista
     std::fs::create_dir_all(OUTPUT_FOLDER)
         .map_err(|e| format!("Failed to create output directory: {}", e))?;
 
-    let file_path = format!("{}/{}.mp3", OUTPUT_FOLDER, video_id);
+    let audio_file = |media_key: &str| {
+        // Trechos do vídeo ganham um nome próprio para não colidir com o áudio completo
+        let mut file_stem = sections::file_stem(media_key, range.as_ref());
+        // O áudio sem os patrocínios também não pode ser confundido com o original
+        if sponsor_mode == SponsorMode::Exclude {
+            file_stem.push_str("_sponsorless");
+        }
+        let file_path = format!("{}/{}.mp3", OUTPUT_FOLDER, file_stem);
+        (file_stem, file_path)
+    };
 
     // Verifica se o arquivo já existe
+    if let Some(video_id) = youtube_url.as_ref().and_then(|u| u.video_id.as_deref()) {
+        let (_, file_path) = audio_file(video_id);
+        if Path::new(&file_path).exists() {
+            println!("Arquivo já existe: {}", file_path);
+            return Ok(file_path);
+        }
+    }
+
+    // Consulta os metadados antes para não ficar preso baixando uma live que nunca termina
+    let mut metadata = YoutubeDl::new(url);
+    // Com o SponsorBlock ativo o yt-dlp já devolve os trechos em `sponsorblock_chapters`
+    sponsor_mode.apply(&mut metadata, &sponsor_categories);
+    let video = metadata
+        .socket_timeout("15")
+        .run_async()
+        .await
+        .map_err(|e| format!("Failed to fetch video metadata: {}", e))?
+        .into_single_video()
+        .ok_or_else(|| "A URL não aponta para um único vídeo".to_string())?;
+
+    // A chave vem do extrator e do ID informados pelo yt-dlp, assim qualquer site suportado
+    // segue o mesmo caminho de download, upload e capítulos
+    let (file_stem, file_path) = audio_file(&media::media_key(&video));
     if Path::new(&file_path).exists() {
         println!("Arquivo já existe: {}", file_path);
         return Ok(file_path);
     }
 
+    availability::ensure_processable(&video)?;
+
+    let window = Duration::from_secs(live_window_secs.unwrap_or(live::DEFAULT_LIVE_WINDOW_SECS));
+    let live_action = live::plan_download(&video, live_policy.unwrap_or_default(), window)?;
+    println!(
+        "Live status: {:?}, ação: {:?}",
+        video.live_status, live_action
+    );
+
     // Configura o youtube-dl para baixar apenas o áudio
-    YoutubeDl::new(url)
-        .extract_audio(true) // Define para extrair apenas o áudio
+    let mut ytdl = YoutubeDl::new(url);
+    live_action.apply(&mut ytdl);
+    sponsor_mode.apply(&mut ytdl, &sponsor_categories);
+    if let Some(range) = range {
+        println!("Baixando apenas o trecho {}", range);
+        ytdl.download_section(range);
+    }
+    ytdl.extract_audio(true) // Define para extrair apenas o áudio
         // .format("bestaudio")
         .extra_arg("-o")
-        .extra_arg(&format!("{}.mp3", video_id)) // Usa o ID do vídeo como nome do arquivo
+        .extra_arg(&format!("{}.mp3", file_stem)) // Usa a chave da mídia como nome do arquivo
         .extra_arg("--audio-format")
         .extra_arg("mp3")
         // .socket_timeout("15")
-        .download_to(OUTPUT_FOLDER)
+        // Uma live pode levar horas, então o yt-dlp roda sem ocupar uma thread do runtime
+        .download_to_async(OUTPUT_FOLDER)
+        .await
         .map_err(|e| format!("Failed to download audio: {}", e))?;
 
+    // Guarda como o áudio se relaciona com o vídeo original para ajustar os timestamps
+    let mut segments = sponsorblock::segments(&video, &sponsor_categories);
+    if let Some(range) = range {
+        segments.retain(|segment| {
+            segment.end > range.start && range.end.is_none_or(|end| segment.start < end)
+        });
+    }
+    let mut timeline = Timeline::with_offset(range.map_or(0.0, |range| range.start));
+    match sponsor_mode {
+        SponsorMode::Keep => {}
+        SponsorMode::Exclude => timeline.removed = segments,
+        SponsorMode::Label => timeline.labeled = segments,
+    }
+    if timeline != Timeline::default() {
+        timeline.save(OUTPUT_FOLDER, &file_stem)?;
+    }
+
+    // Salva os metadados para a biblioteca não precisar consultar o yt-dlp de novo
+    MediaMetadata::from_video(&video, &format!("{}.mp3", file_stem), range).save(OUTPUT_FOLDER)?;
+    index_fingerprint_later(&file_stem, Path::new(&file_path));
+
     Ok(file_path)
 }
 
+/// Metadados salvos no download, aceita o ID do áudio ou o nome do arquivo
 #[command]
-async fn upload_audio(file_path: &str, auth_token: &str) -> Result<String, String> {
-    let url = format!("{}/upload", TRANSCRIBE_YOUTUBE_URL);
+fn read_metadata(media_id: &str) -> Result<MediaMetadata, String> {
+    MediaMetadata::load(OUTPUT_FOLDER, media_id)
+}
 
-    println!("Iniciando upload do arquivo: {}", file_path);
+/// Caminho do áudio de uma mídia da pasta de saída
+fn media_audio_path(media_id: &str) -> Result<PathBuf, String> {
+    metadata::audio_path(OUTPUT_FOLDER, media_id)
+}
 
-    // Cria um formulário multipart apenas com o arquivo
-    println!("Criando formulário multipart...");
-    let form = reqwest::multipart::Form::new()
-        .file("file", file_path)
-        .await
-        .map_err(|e| format!("Failed to create form: {}", e))?;
-
-    // Cria um cliente HTTP assíncrono
-    let client = reqwest::Client::new();
-
-    // Envia a requisição POST de forma assíncrona
-    println!("Enviando requisição POST para {}", url);
-    let response = client
-        .post(url)
-        .header("Authorization", format!("Bearer {}", auth_token))
-        .multipart(form)
-        .send()
+/// Trechos de fala e de silêncio do áudio
+///
+/// O resultado fica salvo ao lado do áudio e é reaproveitado enquanto nem o áudio nem `config`
+/// mudarem.
+#[command]
+async fn voice_activity(
+    media_id: &str,
+    config: Option<VadConfig>,
+) -> Result<VoiceActivity, String> {
+    detect_voice_activity(media_id, config.unwrap_or_default()).await
+}
+
+/// Decodifica o áudio numa thread de bloqueio, fora do runtime assíncrono
+async fn detect_voice_activity(media_id: &str, config: VadConfig) -> Result<VoiceActivity, String> {
+    let media_id = media_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let path = media_audio_path(&media_id)?;
+        vad::voice_activity(&path, OUTPUT_FOLDER, &media_id, &config)
+    })
+    .await
+    .map_err(|e| format!("Failed to detect voice activity: {}", e))?
+}
+
+/// Forma de onda do áudio em `buckets` picos (mínimo, máximo e RMS), para o editor de capítulos
+///
+/// `start` e `end` limitam o trecho, em segundos, para dar zoom. Os picos são calculados uma
+/// vez em várias resoluções e ficam salvos ao lado do áudio.
+#[command]
+async fn waveform_peaks(
+    media_id: &str,
+    buckets: usize,
+    start: Option<f64>,
+    end: Option<f64>,
+) -> Result<Peaks, String> {
+    let path = media_audio_path(media_id)?;
+    let media_id = media_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        waveform::peaks(&path, OUTPUT_FOLDER, &media_id, buckets, start, end)
+    })
+    .await
+    .map_err(|e| format!("Failed to compute waveform peaks: {}", e))?
+}
+
+/// Volume integrado, faixa de volume, pico real e distorção do áudio (EBU R128)
+///
+/// Os avisos mostram antes da transcrição se a gravação está baixa ou distorcida demais.
+#[command]
+async fn loudness_report(media_id: &str) -> Result<LoudnessReport, String> {
+    let path = media_audio_path(media_id)?;
+    let media_id = media_id.to_string();
+    tokio::task::spawn_blocking(move || loudness::report(&path, OUTPUT_FOLDER, &media_id))
         .await
-        .map_err(|e| format!("Failed to upload file: {}", e))?;
-    let response_status = response.status();
-    println!("Requisição enviada, status: {}", response_status);
-
-    // Verifica se o status é de sucesso
-    if !response_status.is_success() {
-        let error_text = response
-            .text()
-            .await
-            .map_err(|e| format!("Failed to read error response: {}", e))?;
-        return Err(format!(
-            "Upload failed with status {}: {}",
-            response_status, error_text
-        ));
+        .map_err(|e| format!("Failed to measure loudness: {}", e))?
+}
+
+/// Impressões digitais sendo calculadas em segundo plano, `find_duplicate` espera a da mídia
+static PENDING_FINGERPRINTS: LazyLock<Mutex<HashMap<String, JoinHandle<Option<IndexEntry>>>>> =
+    LazyLock::new(Default::default);
+/// Duas mídias indexadas ao mesmo tempo não podem sobrescrever uma a outra no índice
+static FINGERPRINT_INDEX: Mutex<()> = Mutex::new(());
+
+/// Calcula a impressão digital em segundo plano, sem atrasar o fim do download
+fn index_fingerprint_later(media_id: &str, path: &Path) {
+    let (id, path) = (media_id.to_string(), path.to_path_buf());
+    let task = tokio::task::spawn_blocking(move || index_fingerprint(&id, &path));
+    let mut pending = PENDING_FINGERPRINTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    pending.retain(|_, task| !task.is_finished());
+    pending.insert(media_id.to_string(), task);
+}
+
+/// Calcula a impressão digital do áudio e adiciona ao índice da pasta de saída
+///
+/// Decodifica o áudio inteiro, então deve rodar fora do runtime. Falhas só são registradas,
+/// sem a impressão a mídia apenas não é comparada com as outras.
+fn index_fingerprint(media_id: &str, path: &Path) -> Option<IndexEntry> {
+    let audio_file = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let source_len = file_len(path).unwrap_or_default();
+    let entry = match fingerprint::compute(path) {
+        Ok(codes) => IndexEntry::new(media_id, &audio_file, source_len, &codes),
+        Err(e) => {
+            println!("Não foi possível calcular a impressão digital: {}", e);
+            return None;
+        }
+    };
+    let _guard = FINGERPRINT_INDEX
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut index = FingerprintIndex::load(OUTPUT_FOLDER);
+    index.insert(entry.clone());
+    if let Err(e) = index.save(OUTPUT_FOLDER) {
+        println!("Não foi possível salvar a impressão digital: {}", e);
     }
+    Some(entry)
+}
 
-    // Lê a resposta como texto de forma assíncrona
-    println!("Lendo resposta do servidor...");
-    let result = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-    println!("Resposta recebida: {}", result);
+/// Outra mídia da biblioteca com o mesmo áudio, reenviada com outro ID por exemplo
+///
+/// Quando existe, a transcrição e os capítulos dela podem ser reaproveitados em vez de
+/// transcrever o áudio de novo. Como os capítulos reaproveitados não são deslocados, áudios
+/// que começam em outro ponto (uma vinheta a mais, por exemplo) não contam como duplicata.
+#[command]
+async fn find_duplicate(media_id: &str) -> Result<Option<DuplicateMatch>, String> {
+    // A impressão do download que acabou de terminar ainda pode estar sendo calculada
+    let pending = PENDING_FINGERPRINTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(media_id);
+    if let Some(task) = pending {
+        task.await
+            .map_err(|e| format!("Failed to compute fingerprint: {}", e))?;
+    }
 
-    Ok(result)
+    let media_id = media_id.to_string();
+    let path = media_audio_path(&media_id)?;
+    tokio::task::spawn_blocking(move || {
+        let source_len = file_len(&path)?;
+        let index = FingerprintIndex::load(OUTPUT_FOLDER);
+        let entry = match index
+            .get(&media_id)
+            .filter(|entry| entry.source_len == source_len)
+        {
+            Some(entry) => entry.clone(),
+            // Mídias de antes do índice, ou cujo áudio foi trocado, são indexadas agora
+            None => index_fingerprint(&media_id, &path)
+                .ok_or_else(|| "Não foi possível calcular a impressão digital".to_string())?,
+        };
+        let duplicate =
+            FingerprintIndex::load(OUTPUT_FOLDER).find(OUTPUT_FOLDER, &entry.codes(), &media_id);
+        Ok(duplicate.filter(|duplicate| {
+            println!(
+                "{} tem o mesmo áudio de {} (semelhança {:.2}, deslocamento {:.1}s)",
+                media_id, duplicate.media_id, duplicate.similarity, duplicate.offset
+            );
+            duplicate.is_aligned()
+        }))
+    })
+    .await
+    .map_err(|e| format!("Failed to find duplicate: {}", e))?
 }
 
+/// Grava cada capítulo como um arquivo de áudio, para cortes e episódios separados
+///
+/// Os arquivos vão para `destination` (por padrão `clips/{id}` na pasta de saída) com nomes
+/// no formato de `template`, como `{index:02} - {title}.{ext}`. Um evento `clip_exported`
+/// é emitido a cada arquivo gravado.
 #[command]
-async fn process_transcription(filename_id: &str, auth_token: &str) -> Result<String, String> {
-    let url = format!(
-        "{}/process-transcription/{}",
-        TRANSCRIBE_YOUTUBE_URL, filename_id
+async fn export_chapter_clips(
+    window: Window,
+    media_id: &str,
+    chapters: Vec<ChapterMark>,
+    destination: Option<String>,
+    template: Option<String>,
+) -> Result<Vec<String>, String> {
+    let path = media_audio_path(media_id)?;
+    let duration = audio::probe(&path)?
+        .duration
+        .ok_or("Não foi possível obter a duração do áudio")?;
+    let timeline = Timeline::load(OUTPUT_FOLDER, media_id).unwrap_or_default();
+    let chapters = chapters::resolve(&chapters, &timeline, duration)?;
+
+    let destination = destination
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(OUTPUT_FOLDER).join("clips").join(media_id));
+    let template = template.unwrap_or_else(|| clips::DEFAULT_TEMPLATE.to_string());
+    println!(
+        "Exportando {} capítulos de {} para {}",
+        chapters.len(),
+        path.display(),
+        destination.display()
     );
+    let paths = tokio::task::spawn_blocking(move || {
+        clips::export(&path, &chapters, &destination, &template, |progress| {
+            let _ = window.emit("clip_exported", progress);
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to export clips: {}", e))??;
+    Ok(paths
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Grava os capítulos gerados dentro do próprio arquivo de áudio
+///
+/// Players de podcast e de audiolivro passam a mostrar os capítulos do arquivo. Funciona com
+/// MP3 (ID3 `CHAP`/`CTOC`) e M4A/M4B (`chpl`), mantendo as tags que o arquivo já tinha. Em
+/// M4A/M4B só os players que leem o `chpl` (VLC, mpv e outros baseados no ffmpeg) mostram os
+/// capítulos, o Apple Podcasts e o Apple Books não.
+///
+/// O arquivo muda de tamanho, mas não de áudio, então os caches validados pelo tamanho
+/// (transcrição local, forma de onda, volume e fala) passam a valer para o novo tamanho.
+#[command]
+async fn embed_chapters(media_id: &str, chapters: Vec<ChapterMark>) -> Result<String, String> {
+    let path = media_audio_path(media_id)?;
+    let duration = audio::probe(&path)?
+        .duration
+        .ok_or("Não foi possível obter a duração do áudio")?;
+    let timeline = Timeline::load(OUTPUT_FOLDER, media_id).unwrap_or_default();
+    let chapters = chapters::resolve(&chapters, &timeline, duration)?;
 
     println!(
-        "Iniciando processamento de transcrição para filename_id: {}",
-        filename_id
+        "Gravando {} capítulos em {}",
+        chapters.len(),
+        path.display()
     );
+    let media_id = media_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let old_len = file_len(&path)?;
+        chapter_tags::embed(&path, &chapters)?;
+        keep_caches(&media_id, old_len, file_len(&path)?);
+        Ok(path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| format!("Failed to embed chapters: {}", e))?
+}
 
-    let client = reqwest::Client::new();
+fn file_len(path: &Path) -> Result<u64, String> {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .map_err(|e| format!("Failed to read audio file: {}", e))
+}
 
-    let response = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", auth_token))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to process transcription: {}", e))?;
+/// Atualiza o tamanho do áudio nos caches que ainda eram do arquivo antes das tags
+fn keep_caches(media_id: &str, old_len: u64, new_len: u64) {
+    if old_len == new_len {
+        return;
+    }
+    let mut results = Vec::new();
+    if let Some(mut transcript) = LocalTranscript::load(OUTPUT_FOLDER, media_id) {
+        if transcript.source_len == old_len {
+            transcript.source_len = new_len;
+            results.push(transcript.save(OUTPUT_FOLDER, media_id));
+        }
+    }
+    if let Some(mut cache) = PeakCache::load(OUTPUT_FOLDER, media_id) {
+        if cache.source_len == old_len {
+            cache.source_len = new_len;
+            results.push(cache.save(OUTPUT_FOLDER, media_id));
+        }
+    }
+    if let Some(mut report) = LoudnessReport::load(OUTPUT_FOLDER, media_id) {
+        if report.source_len == old_len {
+            report.source_len = new_len;
+            results.push(report.save(OUTPUT_FOLDER, media_id));
+        }
+    }
+    if let Some(mut activity) = VoiceActivity::load(OUTPUT_FOLDER, media_id) {
+        if activity.source_len == old_len {
+            activity.source_len = new_len;
+            results.push(activity.save(OUTPUT_FOLDER, media_id));
+        }
+    }
+    {
+        let _guard = FINGERPRINT_INDEX
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut index = FingerprintIndex::load(OUTPUT_FOLDER);
+        if let Some(mut entry) = index.get(media_id).cloned() {
+            if entry.source_len == old_len {
+                entry.source_len = new_len;
+                index.insert(entry);
+                results.push(index.save(OUTPUT_FOLDER));
+            }
+        }
+    }
+    for error in results.into_iter().filter_map(Result::err) {
+        println!(
+            "Não foi possível atualizar o cache de {}: {}",
+            media_id, error
+        );
+    }
+}
 
-    let result = response
-        .text()
+/// Todos os áudios baixados, com os metadados salvos ao lado de cada um
+#[command]
+fn list_library() -> Result<Vec<MediaMetadata>, String> {
+    metadata::list(OUTPUT_FOLDER)
+}
+
+/// Trechos mais assistidos novamente do vídeo, segundo o mapa de calor do YouTube
+///
+/// O resultado pode ser passado para `take_transcription` para destacar esses trechos
+/// nos capítulos. Só links do YouTube têm mapa de calor, e links de vídeo dentro de uma
+/// playlist (`watch?v=…&list=…`) são reduzidos ao vídeo.
+#[command]
+async fn most_replayed(
+    url: &str,
+    options: Option<ReplayOptions>,
+) -> Result<Vec<ReplayRange>, String> {
+    let url = YoutubeUrl::parse(url)?.watch_url()?;
+    let video = YoutubeDl::new(url)
+        .socket_timeout("15")
+        .run_async()
         .await
-        .map_err(|e| format!("Failed to read status response: {}", e))?;
-    println!("Result process transcription: {}", result);
-    Ok(result)
+        .map_err(|e| format!("Failed to fetch video metadata: {}", e))?
+        .into_single_video()
+        .ok_or_else(|| "A URL não aponta para um único vídeo".to_string())?;
+
+    let ranges = video.most_replayed(&options.unwrap_or_default());
+    println!("Trechos mais assistidos: {:?}", ranges);
+    Ok(ranges)
+}
+
+/// Baixa uma única vez a miniatura do vídeo para o cache local, ao lado do áudio
+///
+/// Devolve o conteúdo como `data:` URL, para que a interface funcione offline e sem fazer
+/// requisições ao YouTube. Com `data_url: false` devolve o caminho do arquivo, que a webview
+/// não carrega direto.
+#[command]
+async fn cache_thumbnail(
+    url: &str,
+    width: Option<u32>,
+    height: Option<u32>,
+    data_url: Option<bool>,
+) -> Result<String, String> {
+    let width = width.unwrap_or(thumbnails::DEFAULT_WIDTH);
+    let height = height.unwrap_or(thumbnails::DEFAULT_HEIGHT);
+
+    // Links do YouTube já trazem o ID, então o cache é consultado sem chamar o yt-dlp
+    let youtube_url = YoutubeUrl::parse(url).ok();
+    let cached = youtube_url
+        .as_ref()
+        .and_then(|u| u.video_id.as_deref())
+        .and_then(|video_id| thumbnails::find_cached(OUTPUT_FOLDER, video_id, width, height));
+
+    let path = match cached {
+        Some(path) => path,
+        None => {
+            let url = match &youtube_url {
+                Some(youtube_url) => youtube_url.watch_url()?,
+                None => url.to_string(),
+            };
+            let video = YoutubeDl::new(url)
+                .socket_timeout("15")
+                .run_async()
+                .await
+                .map_err(|e| format!("Failed to fetch video metadata: {}", e))?
+                .into_single_video()
+                .ok_or_else(|| "A URL não aponta para um único vídeo".to_string())?;
+            let media_key = media::media_key(&video);
+            match thumbnails::find_cached(OUTPUT_FOLDER, &media_key, width, height) {
+                Some(path) => path,
+                None => {
+                    let thumbnail = video
+                        .best_thumbnail(width as f64, height as f64)
+                        .ok_or_else(|| "O vídeo não tem miniatura".to_string())?;
+
+                    std::fs::create_dir_all(OUTPUT_FOLDER)
+                        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+                    let path = thumbnails::cache_path(
+                        OUTPUT_FOLDER,
+                        &media_key,
+                        width,
+                        height,
+                        thumbnails::extension(&thumbnail),
+                    );
+                    println!(
+                        "Baixando miniatura {:?} para {}",
+                        thumbnail.url,
+                        path.display()
+                    );
+                    thumbnails::download(&thumbnail, &path).await?;
+                    path
+                }
+            }
+        }
+    };
+
+    if data_url.unwrap_or(true) {
+        thumbnails::data_url(&path)
+    } else {
+        Ok(path.to_string_lossy().to_string())
+    }
 }
 
+/// Lista os episódios de um podcast a partir do feed RSS ou Atom
 #[command]
-async fn take_transcription(filename_id: &str, auth_token: Option<&str>) -> Result<String, String> {
-    let url = format!("{}/transcript/{}", TRANSCRIBE_YOUTUBE_URL, filename_id);
+async fn podcast_feed(url: &str) -> Result<Feed, String> {
+    println!("Buscando feed: {}", url);
+    podcast::fetch_feed(url).await
+}
+
+/// Baixa um episódio para a mesma pasta do `download_audio`
+///
+/// Quando o feed publica `podcast:chapters`, eles são salvos junto e enviados para a geração
+/// de capítulos. Se o arquivo já existe mas ficou sem metadados (um download anterior
+/// interrompido depois do áudio), os metadados são gravados agora.
+#[command]
+async fn download_episode(episode: Episode) -> Result<String, String> {
+    std::fs::create_dir_all(OUTPUT_FOLDER)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    let media_id = episode.media_id();
+    let audio_file = format!("{}.{}", media_id, episode.extension());
+    let file_path = format!("{}/{}", OUTPUT_FOLDER, audio_file);
+    if Path::new(&file_path).exists() {
+        println!("Arquivo já existe: {}", file_path);
+        if MediaMetadata::load(OUTPUT_FOLDER, &media_id).is_err() {
+            save_episode(&episode, &audio_file).await?;
+        }
+        return Ok(file_path);
+    }
 
     println!(
-        "Verificando status da transcrição para filename_id: {}",
-        filename_id
+        "Baixando episódio {} de {}",
+        episode.title, episode.enclosure_url
     );
+    podcast::download(&episode, Path::new(&file_path)).await?;
+    save_episode(&episode, &audio_file).await?;
+
+    Ok(file_path)
+}
+
+/// Capítulos do autor, metadados e impressão digital de um episódio já baixado
+async fn save_episode(episode: &Episode, audio_file: &str) -> Result<(), String> {
+    let media_id = episode.media_id();
+    let path = Path::new(OUTPUT_FOLDER).join(audio_file);
+
+    // Os capítulos do autor são opcionais, uma falha aqui não perde o download
+    if let Some(chapters_url) = &episode.chapters_url {
+        let saved = podcast::fetch_chapters(chapters_url)
+            .await
+            .and_then(|chapters| podcast::save_chapters(OUTPUT_FOLDER, &media_id, &chapters));
+        if let Err(e) = saved {
+            println!("Não foi possível importar os capítulos: {}", e);
+        }
+    }
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .send()
+    let probe_path = path.clone();
+    let duration = tokio::task::spawn_blocking(move || audio::probe(&probe_path))
         .await
-        .map_err(|e| format!("Failed to check transcription status: {}", e))?;
-    println!(
-        "Requisição de status enviada, status: {}",
-        response.status()
-    );
+        .map_err(|e| format!("Failed to probe audio: {}", e))?
+        .ok()
+        .and_then(|info| info.duration);
+    episode.metadata(audio_file, duration).save(OUTPUT_FOLDER)?;
+    index_fingerprint_later(&media_id, &path);
+    Ok(())
+}
 
-    let result = response
-        .text()
+/// Envia o áudio para transcrição
+///
+/// O áudio é convertido antes para mono de 16 kHz no `format` pedido (FLAC por padrão,
+/// `original` envia o arquivo sem mexer). Com `normalize`, o volume é ajustado para o alvo da
+/// EBU R128 na mesma conversão.
+///
+/// Áudios mais longos que `chunk_minutes` (padrão `DEFAULT_CHUNK_MINUTES`, 0 desliga) são
+/// divididos nos silêncios e enviados em pedaços. A lista dos pedaços fica salva ao lado do
+/// áudio e o `filename_id` devolvido é o do áudio original, então o resto do fluxo não muda.
+#[command]
+async fn upload_audio(
+    file_path: &str,
+    auth_token: &str,
+    chunk_minutes: Option<u32>,
+    format: Option<UploadFormat>,
+    normalize: Option<bool>,
+) -> Result<String, String> {
+    let client = capitu_client(Some(auth_token))?;
+    let source = PathBuf::from(file_path);
+    let media_id = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("Invalid audio file name")?
+        .to_string();
+
+    // A medição e a conversão decodificam o áudio inteiro, fora do runtime assíncrono
+    let format = format.unwrap_or_default();
+    let normalize = normalize.unwrap_or(false) && format != UploadFormat::Original;
+    let prepared = {
+        let (source, media_id) = (source.clone(), media_id.clone());
+        tokio::task::spawn_blocking(move || {
+            let gain_db = if normalize {
+                let report = loudness::report(&source, OUTPUT_FOLDER, &media_id)?;
+                if !report.warnings.is_empty() {
+                    println!("Avisos de volume: {:?}", report.warnings);
+                }
+                let gain = report
+                    .normalization_gain(loudness::TARGET_LUFS)
+                    .unwrap_or_default();
+                println!("Normalizando {} com ganho de {:.1} dB", media_id, gain);
+                gain
+            } else {
+                0.0
+            };
+
+            // O modelo só usa mono de 16 kHz, o arquivo original fica para ser ouvido
+            preprocess::prepare(&source, OUTPUT_FOLDER, format, gain_db)
+        })
         .await
-        .map_err(|e| format!("Failed to read status response: {}", e))?;
+        .map_err(|e| format!("Failed to prepare audio: {}", e))??
+    };
+    let result = upload_prepared(&client, &prepared, &media_id, chunk_minutes).await;
+    if prepared != source {
+        let _ = std::fs::remove_file(&prepared);
+    }
+    serde_json::to_string(&result?).map_err(|e| format!("Failed to serialize upload: {}", e))
+}
 
-    // Parse the JSON response
-    let json: Value = serde_json::from_str(&result)
-        .map_err(|e| format!("Failed to parse JSON response: {}", e))?;
+async fn upload_prepared(
+    client: &CapituClient,
+    path: &Path,
+    media_id: &str,
+    chunk_minutes: Option<u32>,
+) -> Result<UploadResponse, String> {
+    let chunk_secs = chunk_minutes.unwrap_or(chunking::DEFAULT_CHUNK_MINUTES) as f64 * 60.0;
+    let duration = audio::probe(path)
+        .ok()
+        .and_then(|info| info.duration)
+        .unwrap_or_default();
+
+    if chunk_secs <= 0.0 || duration <= chunk_secs {
+        return Ok(client.upload(path).await?);
+    }
 
-    // Extract the transcript or handle errors
-    if let Some(transcript) = json.get("transcript") {
-        // println!("Transcrição: {}", transcript);
-        let json_body_new =
-            serde_json::json!({"filename_id":filename_id,"transcript":transcript.to_string()});
+    println!(
+        "Áudio com {:.0}s, dividindo em pedaços de {:.0}s",
+        duration, chunk_secs
+    );
+    let folder = path
+        .parent()
+        .and_then(|parent| parent.to_str())
+        .unwrap_or(OUTPUT_FOLDER)
+        .to_string();
+    let source = path.to_path_buf();
+    let parts = tokio::task::spawn_blocking(move || chunking::split(&source, &folder, chunk_secs))
+        .await
+        .map_err(|e| format!("Failed to split audio: {}", e))??;
+
+    let mut chunks = Vec::with_capacity(parts.len());
+    for part in &parts {
+        let result = client.upload(&part.path).await;
+        // Os pedaços já enviados não servem para nada no disco
+        let _ = std::fs::remove_file(&part.path);
+        chunks.push(Chunk {
+            filename_id: result?.filename_id,
+            start: part.start,
+            end: part.end,
+            task_id: None,
+        });
+    }
 
-        // println!("JSON Body New: {}", json_body_new.clone());
+    let count = chunks.len();
+    ChunkManifest { chunks }.save(OUTPUT_FOLDER, media_id)?;
+    println!("{} pedaços enviados para {}", count, media_id);
 
-        // Escolhe a URL baseado na presença do token
-        let new_url_chapters = match auth_token {
-            Some(_) => format!("{}/generate-chapters-auth", CAPITU_LANGCHAIN_URL),
-            None => format!("{}/generate-chapters", CAPITU_LANGCHAIN_URL),
-        };
+    Ok(UploadResponse {
+        filename_id: media_id.to_string(),
+        task_id: None,
+        chunks: Some(count),
+    })
+}
 
-        println!("New URL Chapters: {}", new_url_chapters);
+/// Inicia a transcrição com o motor escolhido (o serviço por padrão)
+///
+/// Para áudios enviados em pedaços, todos são iniciados e este comando espera os anteriores
+/// terminarem. A resposta é a do último pedaço, que o frontend acompanha como de costume.
+/// O motor local só responde quando a transcrição termina, sem `task_id`.
+#[command]
+async fn process_transcription(
+    filename_id: &str,
+    auth_token: &str,
+    engine: Option<TranscriberConfig>,
+) -> Result<String, String> {
+    let transcriber = transcriber(engine, Some(auth_token))?;
+    Ok(transcriber.start(filename_id).await?.to_string())
+}
 
-        let mut request = client.post(&new_url_chapters).json(&json_body_new);
+fn transcriber(
+    engine: Option<TranscriberConfig>,
+    auth_token: Option<&str>,
+) -> Result<Engine, String> {
+    Ok(engine
+        .unwrap_or_default()
+        .build(OUTPUT_FOLDER, capitu_client(auth_token)?))
+}
 
-        // Adiciona o header de autorização se o token estiver presente
-        if let Some(token) = auth_token {
-            request = request.header("Authorization", format!("Bearer {}", token));
-        }
+/// Cliente com os endereços do perfil ativo, relido a cada comando
+fn capitu_client(auth_token: Option<&str>) -> Result<CapituClient, String> {
+    let active = backend::active(OUTPUT_FOLDER)?;
+    Ok(CapituClient::new(active.endpoints, auth_token))
+}
 
-        let response = request
-            .send()
-            .await
-            .map_err(|e| format!("Failed to generate chapters: {}", e))?;
+/// Perfil dos serviços em uso (produção, staging ou local) e os seus endereços
+///
+/// Vem de `backend.json` na pasta de saída e das variáveis `CAPITUAI_*`, veja `backend`.
+/// O frontend usa os mesmos endereços nas consultas que faz direto.
+#[command]
+fn backend_profile() -> Result<ActiveProfile, String> {
+    backend::active(OUTPUT_FOLDER)
+}
 
-        let result = response
-            .json::<Value>()
-            .await
-            .map_err(|e| format!("Failed to parse generate chapters response: {}", e))?;
-        // println!("Result Generate Chapters: {:?}", result.clone().to_string());
-
-        let task_id = result
-            .get("task_id")
-            .ok_or("Task ID not found")?
-            .as_str()
-            .ok_or("Task ID is not a string")?;
-        println!("Task ID: {}", task_id);
+/// Informações do ambiente para anexar a um relato de problema
+#[command]
+fn diagnostics(app: tauri::AppHandle) -> Result<String, String> {
+    let report = serde_json::json!({
+        "version": app.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "output_folder": OUTPUT_FOLDER,
+        "backend": backend::active(OUTPUT_FOLDER)?,
+    });
+    serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("Failed to serialize diagnostics: {}", e))
+}
 
-        // Chamada para task-status
-        let new_url_full_chapters = format!("{}/task-status/{}", CAPITU_LANGCHAIN_URL, task_id);
+/// Busca a transcrição e gera os capítulos
+///
+/// Os timestamps voltam para a linha do tempo do vídeo original usando o `Timeline` salvo
+/// por `download_audio`. `offset_secs` substitui o início do trecho salvo, se informado.
+/// `most_replayed` (de `most_replayed`) é enviado junto para destacar esses trechos.
+/// `engine` precisa ser o mesmo usado em `process_transcription`.
+#[command]
+async fn take_transcription(
+    filename_id: &str,
+    auth_token: Option<&str>,
+    offset_secs: Option<f64>,
+    most_replayed: Option<Vec<ReplayRange>>,
+    engine: Option<TranscriberConfig>,
+) -> Result<String, String> {
+    let client = capitu_client(auth_token)?;
+
+    let mut transcript = transcriber(engine, auth_token)?
+        .transcript(filename_id)
+        .await?;
+
+    // Desloca a transcrição antes de gerar os capítulos, assim eles já saem
+    // com os timestamps do vídeo original
+    let mut timeline = Timeline::load(OUTPUT_FOLDER, filename_id).unwrap_or_default();
+    if let Some(offset) = offset_secs {
+        timeline.offset = offset;
+    }
+    if !timeline.is_identity() {
+        println!("Ajustando timestamps da transcrição: {:?}", timeline);
+        sections::remap_transcript(&mut transcript, &timeline);
+    }
+    let mut request = ChapterRequest {
+        filename_id: filename_id.to_string(),
+        transcript: transcript.to_string(),
+        sponsor_segments: timeline.labeled.clone(),
+        existing_chapters: podcast::load_chapters(OUTPUT_FOLDER, filename_id),
+        most_replayed: most_replayed.unwrap_or_default(),
+        ..Default::default()
+    };
+    match detect_voice_activity(filename_id, VadConfig::default()).await {
+        Ok(activity) => {
+            request.pauses = activity
+                .pauses(vad::PAUSE_SECS)
+                .iter()
+                .map(|pause| Pause {
+                    start: timeline.to_original(pause.start),
+                    end: timeline.to_original(pause.end),
+                })
+                .collect();
+        }
+        Err(e) => println!("Não foi possível detectar as pausas: {}", e),
+    }
 
-        loop {
-            let response = client
-                .get(&new_url_full_chapters)
-                .send()
-                .await
-                .map_err(|e| format!("Failed to get task status: {}", e))?;
-            let result = response
-                .json::<Value>()
-                .await
-                .map_err(|e| format!("Failed to parse task status response: {}", e))?;
-            // println!("Result Full Chapters: {:?}", result.clone());
-
-            if let Some(task_result) = result.get("task_result") {
-                if let Some(status) = task_result.get("result") {
-                    if status.as_str().unwrap() == "pending" {
-                        println!("Result is pending, waiting...");
-                        sleep(Duration::from_secs(5)).await; // Espera 5 segundos antes de tentar novamente
-                        continue;
-                    } else if status == "timeout_get" {
-                        println!("Result is timeout_get, waiting...");
-                    } else {
-                        let text = task_result.get("text");
-                        if let Some(text) = text {
-                            println!("Text: {:?}", text.as_str().unwrap());
-                            return Ok(text.to_string());
-                        } else {
-                            println!("Text not found in response: {}", task_result.to_string());
-                            return Err("Text not found in response.".into());
-                        }
-                    }
-                } else {
-                    println!("Unexpected status result");
-                }
-            }
+    let task = client.generate_chapters(&request).await?;
+    println!("Task ID: {}", task.task_id);
+
+    let result = client.wait_task(&task.task_id).await?;
+    match result.text {
+        Some(text) => {
+            println!("Text: {}", text);
+            Ok(text.to_string())
         }
-    } else {
-        Err("Transcription not found in response.".into())
+        None => Err(CapituError::Task {
+            task_id: task.task_id,
+            result: result.result,
+        }
+        .into()),
     }
 }
 
@@ -378,7 +982,24 @@ pub fn run() {
             take_transcription,
             process_transcription,
             start_server,
-            get_audio_duration
+            get_audio_duration,
+            audio_info,
+            most_replayed,
+            cache_thumbnail,
+            read_metadata,
+            list_library,
+            voice_activity,
+            waveform_peaks,
+            loudness_report,
+            find_duplicate,
+            export_chapter_clips,
+            embed_chapters,
+            backend_profile,
+            diagnostics,
+            parse_url,
+            import_media,
+            podcast_feed,
+            download_episode
         ])
         .setup(|app| {
             #[cfg(debug_assertions)] // only include this code on debug builds
@@ -389,6 +1010,11 @@ pub fn run() {
             }
 
             // #[cfg(any(target_os = "linux", target_os = "windows", windows))]
+            match backend::active(OUTPUT_FOLDER) {
+                Ok(active) => println!("Perfil dos serviços: {:?}", active),
+                Err(e) => println!("Configuração dos serviços inválida: {}", e),
+            }
+
             let result = app.deep_link().register("capituai");
             if let Err(e) = result {
                 println!("Error: {:?}", e);
@@ -401,6 +1027,7 @@ pub fn run() {
 
 #[cfg(test)]
 mod tests {
+    use serde_json::Value;
     use std::env;
 
     use super::*;
@@ -467,7 +1094,7 @@ mod tests {
     async fn test_take_transcription() {
         let filename_id = VIDEO_ID; // Use o task_id retornado pelo upload
 
-        let result = take_transcription(filename_id, None).await;
+        let result = take_transcription(filename_id, None, None, None, None).await;
         assert!(
             result.is_ok(),
             "Failed to check transcription status: {:?}",
@@ -478,7 +1105,8 @@ mod tests {
 
         println!("Transcription status: {:?}", json_result);
 
-        let new_url_chapters = format!("{}/generate-chapters", CAPITU_LANGCHAIN_URL);
+        let active = backend::active(OUTPUT_FOLDER).unwrap();
+        let new_url_chapters = format!("{}/generate-chapters", active.endpoints.chapters_url);
 
         let client = reqwest::Client::new();
         let response = client
@@ -493,78 +1121,13 @@ mod tests {
         let task_id = result.get("task_id").unwrap().as_str().unwrap();
         println!("Task ID: {}", task_id);
 
-        let new_url_full_chapters = format!("{}/task-status/{}", CAPITU_LANGCHAIN_URL, task_id);
-
-        let client = reqwest::Client::new();
-
-        loop {
-            let response = client.get(&new_url_full_chapters).send().await.unwrap();
-            let result = response.json::<Value>().await.unwrap();
-            println!("Result Full Chapters: {:?}", result.clone());
-
-            if let Some(task_result) = result.get("task_result") {
-                if let Some(status) = task_result.get("result") {
-                    if status.as_str().unwrap() == "pending" {
-                        println!("Result is pending, waiting...");
-                        sleep(Duration::from_secs(5)).await; // Espera 5 segundos antes de tentar novamente
-                        continue;
-                    } else {
-                        let text = task_result.get("text").unwrap();
-                        println!("Text: {:?}", text.as_str().unwrap());
-                        break;
-                    }
-                } else {
-                    println!("Unexpected status result");
-                }
-            }
-        }
-    }
-
-    #[test]
-    fn test_extract_youtube_video_id() {
-        // Testa diferentes formatos de URL do YouTube
-        let test_cases = vec![
-            // Formato padrão watch?v=
-            (
-                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
-                Some("dQw4w9WgXcQ"),
-            ),
-            // Formato curto youtu.be
-            ("https://youtu.be/dQw4w9WgXcQ", Some("dQw4w9WgXcQ")),
-            // Formato de shorts
-            (
-                "https://youtube.com/shorts/dQw4w9WgXcQ",
-                Some("dQw4w9WgXcQ"),
-            ),
-            // Com parâmetros adicionais
-            (
-                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&feature=share",
-                Some("dQw4w9WgXcQ"),
-            ),
-            // Com timestamp
-            ("https://youtu.be/dQw4w9WgXcQ?t=123", Some("dQw4w9WgXcQ")),
-            // URL inválida
-            ("https://youtube.com/invalid", None),
-            // String vazia
-            ("", None),
-            // ID muito curto
-            ("https://youtu.be/abc123", None),
-            // ID muito longo
-            ("https://youtu.be/abc123456789", None),
-        ];
-
-        // Executa todos os casos de teste
-        for (input, expected) in test_cases {
-            let result = extract_youtube_video_id(input);
-            assert_eq!(
-                result.as_deref(),
-                expected,
-                "Falha ao extrair ID de '{}'. Esperado: {:?}, Obtido: {:?}",
-                input,
-                expected,
-                result
-            );
-        }
+        let result = capitu_client(None)
+            .unwrap()
+            .wait_task(task_id)
+            .await
+            .unwrap();
+        println!("Result Full Chapters: {:?}", result);
+        assert!(result.text.is_some());
     }
 
     #[test]
@@ -0,0 +1,204 @@
+use serde::Deserialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use youtube_dl::{LiveStatus, SingleVideo, YoutubeDl};
+
+/// Janela padrão (em segundos) para esperar ou gravar uma live/estreia
+pub const DEFAULT_LIVE_WINDOW_SECS: u64 = 2 * 60 * 60;
+
+/// Intervalo entre as verificações do `--wait-for-video`
+const WAIT_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// O que fazer quando a URL aponta para uma live ou estreia
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LivePolicy {
+    /// Recusa lives em andamento e estreias que ainda não começaram
+    #[default]
+    Refuse,
+    /// Espera a estreia começar (`--wait-for-video`) dentro da janela
+    Wait,
+    /// Grava a live desde o início (`--live-from-start`) dentro da janela
+    FromStart,
+}
+
+/// Como o download deve ser configurado depois de avaliar o `live_status`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LiveAction {
+    /// Vídeo comum, baixa normalmente
+    Download,
+    /// Espera a estreia começar e baixa em seguida
+    WaitForVideo { window: Duration },
+    /// Grava a live desde o início, limitado à janela
+    RecordFromStart { wait: bool, window: Duration },
+}
+
+impl LiveAction {
+    /// Aplica as opções correspondentes ao builder do yt-dlp
+    pub fn apply(self, ytdl: &mut YoutubeDl) {
+        match self {
+            LiveAction::Download => {}
+            LiveAction::WaitForVideo { window } => {
+                ytdl.wait_for_video(WAIT_RETRY_INTERVAL, None)
+                    .process_timeout(window);
+            }
+            LiveAction::RecordFromStart { wait, window } => {
+                if wait {
+                    ytdl.wait_for_video(WAIT_RETRY_INTERVAL, None);
+                }
+                ytdl.live_from_start(true).process_timeout(window);
+            }
+        }
+    }
+}
+
+/// Decide se o vídeo pode ser baixado de acordo com o `live_status` e a política escolhida
+pub fn plan_download(
+    video: &SingleVideo,
+    policy: LivePolicy,
+    window: Duration,
+) -> Result<LiveAction, String> {
+    // Extratores antigos só preenchem `is_live`/`was_live`
+    let status = video
+        .live_status
+        .unwrap_or(match (video.is_live, video.was_live) {
+            (Some(true), _) => LiveStatus::IsLive,
+            (_, Some(true)) => LiveStatus::WasLive,
+            _ => LiveStatus::NotLive,
+        });
+
+    match (status, policy) {
+        (LiveStatus::NotLive | LiveStatus::WasLive | LiveStatus::Unknown, _) => {
+            Ok(LiveAction::Download)
+        }
+        (LiveStatus::IsLive | LiveStatus::PostLive, LivePolicy::FromStart) => {
+            Ok(LiveAction::RecordFromStart {
+                wait: false,
+                window,
+            })
+        }
+        (LiveStatus::IsLive, _) => Err(
+            "O vídeo é uma live em andamento. Aguarde o fim da transmissão ou grave desde o início."
+                .to_string(),
+        ),
+        (LiveStatus::PostLive, _) => Err(
+            "A live terminou há pouco e o YouTube ainda está processando o vídeo. Tente novamente mais tarde."
+                .to_string(),
+        ),
+        (LiveStatus::IsUpcoming, LivePolicy::Refuse) => {
+            Err(format!("O vídeo ainda não começou. {}", premiere_availability(video)))
+        }
+        (LiveStatus::IsUpcoming, policy) => {
+            if let Some(wait) = time_until_release(video) {
+                if wait > window {
+                    return Err(format!(
+                        "A estreia começa depois da janela de espera de {}. {}",
+                        format_duration(window),
+                        premiere_availability(video)
+                    ));
+                }
+            }
+            Ok(match policy {
+                LivePolicy::FromStart => LiveAction::RecordFromStart { wait: true, window },
+                _ => LiveAction::WaitForVideo { window },
+            })
+        }
+    }
+}
+
+/// Tempo que falta para a estreia, se o yt-dlp informou o `release_timestamp`
+fn time_until_release(video: &SingleVideo) -> Option<Duration> {
+    let release = u64::try_from(video.release_timestamp?).ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(release.saturating_sub(now)))
+}
+
+/// Mensagem dizendo quando a estreia estará disponível
+fn premiere_availability(video: &SingleVideo) -> String {
+    match time_until_release(video) {
+        Some(wait) if wait.is_zero() => "A estreia deve começar a qualquer momento.".to_string(),
+        Some(wait) => format!(
+            "Estará disponível em aproximadamente {}.",
+            format_duration(wait)
+        ),
+        None => "O horário da estreia não foi informado.".to_string(),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let total_minutes = duration.as_secs().div_ceil(60);
+    let (days, hours, minutes) = (
+        total_minutes / (24 * 60),
+        total_minutes / 60 % 24,
+        total_minutes % 60,
+    );
+    match (days, hours) {
+        (0, 0) => format!("{}min", minutes),
+        (0, _) => format!("{}h {}min", hours, minutes),
+        _ => format!("{}d {}h {}min", days, hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(DEFAULT_LIVE_WINDOW_SECS);
+
+    fn video(status: LiveStatus, release_in: Option<i64>) -> SingleVideo {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        SingleVideo {
+            live_status: Some(status),
+            release_timestamp: release_in.map(|secs| now + secs),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_regular_video_downloads() {
+        for status in [LiveStatus::NotLive, LiveStatus::WasLive] {
+            let result = plan_download(&video(status, None), LivePolicy::Refuse, WINDOW);
+            assert_eq!(result, Ok(LiveAction::Download));
+        }
+    }
+
+    #[test]
+    fn test_live_policies() {
+        let live = video(LiveStatus::IsLive, None);
+        assert!(plan_download(&live, LivePolicy::Refuse, WINDOW).is_err());
+        assert!(plan_download(&live, LivePolicy::Wait, WINDOW).is_err());
+        assert_eq!(
+            plan_download(&live, LivePolicy::FromStart, WINDOW),
+            Ok(LiveAction::RecordFromStart {
+                wait: false,
+                window: WINDOW
+            })
+        );
+    }
+
+    #[test]
+    fn test_upcoming_premiere() {
+        let soon = video(LiveStatus::IsUpcoming, Some(30 * 60));
+        let error = plan_download(&soon, LivePolicy::Refuse, WINDOW).unwrap_err();
+        assert!(error.contains("30min"), "{}", error);
+        assert_eq!(
+            plan_download(&soon, LivePolicy::Wait, WINDOW),
+            Ok(LiveAction::WaitForVideo { window: WINDOW })
+        );
+
+        let later = video(LiveStatus::IsUpcoming, Some(26 * 60 * 60));
+        let error = plan_download(&later, LivePolicy::Wait, WINDOW).unwrap_err();
+        assert!(error.contains("1d 2h 0min"), "{}", error);
+    }
+
+    #[test]
+    fn test_legacy_is_live_flag() {
+        let live = SingleVideo {
+            is_live: Some(true),
+            ..Default::default()
+        };
+        assert!(plan_download(&live, LivePolicy::Refuse, WINDOW).is_err());
+    }
+}
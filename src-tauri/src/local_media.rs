@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use crate::audio::{self, AudioInfo};
+
+/// Prefixo das chaves de arquivos importados, para não colidir com as chaves do yt-dlp
+const LOCAL_PREFIX: &str = "local";
+
+/// Quantos caracteres do hash entram no ID, 64 bits bastam para uma biblioteca local
+const ID_HASH_LEN: usize = 16;
+
+/// Como o arquivo entra na pasta de saída
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Copia o arquivo, o original pode ser apagado depois
+    #[default]
+    Copy,
+    /// Cria um hard link, sem ocupar espaço. Volta a copiar se o link não for possível
+    /// (outro disco, sistema de arquivos sem suporte...)
+    Link,
+}
+
+/// Arquivo local já importado para a pasta de saída
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportedMedia {
+    /// Chave da mídia, derivada do conteúdo do arquivo
+    pub media_id: String,
+    /// Caminho na pasta de saída, pronto para o `upload_audio`
+    pub file_path: String,
+    pub info: AudioInfo,
+}
+
+/// Importa um arquivo de áudio ou vídeo para a pasta de saída
+///
+/// O ID vem do conteúdo, então importar o mesmo arquivo de novo (mesmo renomeado) reaproveita
+/// o que já está na pasta.
+pub fn import(source: &Path, folder: &str, mode: ImportMode) -> Result<ImportedMedia, String> {
+    if !source.is_file() {
+        return Err(format!("Arquivo não encontrado: {}", source.display()));
+    }
+
+    // Faz o probe antes de copiar, para não guardar arquivos que o pipeline não consegue ler
+    let info = audio::probe(source)
+        .map_err(|e| format!("Formato não suportado ({}): {}", source.display(), e))?;
+
+    let media_id = content_id(source)?;
+    let extension = source
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_else(|| "bin".to_string());
+    let target = Path::new(folder).join(format!("{}.{}", media_id, extension));
+
+    if target.exists() {
+        println!("Arquivo já importado: {}", target.display());
+    } else {
+        fs::create_dir_all(folder)
+            .map_err(|e| format!("Failed to create output directory: {}", e))?;
+        store(source, &target, mode)?;
+    }
+
+    Ok(ImportedMedia {
+        media_id,
+        file_path: target.to_string_lossy().to_string(),
+        info,
+    })
+}
+
+/// ID a partir do SHA-256 do conteúdo, no formato `local_{hash}`
+pub fn content_id(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| format!("Failed to read file: {}", e))?;
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Ok(format!("{}_{}", LOCAL_PREFIX, &hash[..ID_HASH_LEN]))
+}
+
+fn store(source: &Path, target: &Path, mode: ImportMode) -> Result<(), String> {
+    if mode == ImportMode::Link {
+        match fs::hard_link(source, target) {
+            Ok(()) => return Ok(()),
+            Err(e) => println!("Não foi possível criar o link ({}), copiando", e),
+        }
+    }
+
+    // Copia com outro nome primeiro, assim uma cópia interrompida não é tratada como importada
+    let partial = target.with_extension("part");
+    fs::copy(source, &partial).map_err(|e| format!("Failed to copy file: {}", e))?;
+    fs::rename(&partial, target).map_err(|e| format!("Failed to copy file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::silent_wav;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_import() {
+        let temp = temp_dir("import");
+        let dir = temp.path();
+        let folder = dir.join("output");
+        let folder = folder.to_str().unwrap();
+
+        let source = dir.join("Gravação Bruta.WAV");
+        fs::write(&source, silent_wav(16000, 1, 2)).unwrap();
+
+        let copied = import(&source, folder, ImportMode::Copy).unwrap();
+        assert!(copied.media_id.starts_with("local_"));
+        assert_eq!(copied.media_id.len(), "local_".len() + ID_HASH_LEN);
+        assert!(copied
+            .file_path
+            .ends_with(&format!("{}.wav", copied.media_id)));
+        assert_eq!(copied.info.duration, Some(2.0));
+        assert_eq!(
+            fs::read(&copied.file_path).unwrap(),
+            fs::read(&source).unwrap()
+        );
+
+        // O mesmo conteúdo com outro nome cai no mesmo ID
+        let renamed = dir.join("copia.wav");
+        fs::copy(&source, &renamed).unwrap();
+        let linked = import(&renamed, folder, ImportMode::Link).unwrap();
+        assert_eq!(linked.media_id, copied.media_id);
+        assert_eq!(linked.file_path, copied.file_path);
+
+        let garbage = dir.join("notas.txt");
+        fs::write(&garbage, "não é áudio").unwrap();
+        assert!(import(&garbage, folder, ImportMode::Copy).is_err());
+        assert!(import(&dir.join("nao-existe.mp3"), folder, ImportMode::Copy).is_err());
+    }
+}
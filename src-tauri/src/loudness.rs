@@ -0,0 +1,595 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use crate::audio;
+use crate::metadata::sidecar_path;
+use crate::preprocess;
+
+/// Versão do formato do `{id}.loudness.json`, incrementar ao mudar os campos
+pub const LOUDNESS_VERSION: u32 = 2;
+/// Volume alvo da normalização, o da EBU R128
+pub const TARGET_LUFS: f64 = -23.0;
+/// Pico máximo depois da normalização, para o ganho não causar distorção
+pub const MAX_TRUE_PEAK: f64 = -1.0;
+
+/// Abaixo disso a transcrição costuma perder palavras
+const QUIET_LUFS: f64 = -35.0;
+/// Variação de volume a partir da qual os trechos baixos tendem a se perder
+const WIDE_RANGE_LU: f64 = 20.0;
+/// Proporção de amostras no limite a partir da qual o áudio é considerado distorcido
+const CLIPPING_WARNING: f64 = 0.0005;
+/// Amostras a partir desse valor contam como cortadas (-0,01 dBFS)
+const CLIP_LEVEL: f32 = 0.999;
+/// Portão absoluto da BS.1770, blocos mais baixos são ignorados
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Portão relativo da medida integrada, em LU abaixo da média
+const INTEGRATED_GATE: f64 = -10.0;
+/// Portão relativo da faixa de volume (EBU Tech 3342)
+const RANGE_GATE: f64 = -20.0;
+/// Sub-blocos de 100 ms em cada bloco de 400 ms e de 3 s
+const MOMENTARY_SUBBLOCKS: usize = 4;
+const SHORT_TERM_SUBBLOCKS: usize = 30;
+/// Coeficientes de cada lado da interpolação do pico real
+const TRUE_PEAK_HALF: usize = 6;
+/// Posições calculadas entre duas amostras (4x, como pede a BS.1770)
+const TRUE_PEAK_PHASES: usize = 3;
+
+/// Problemas que atrapalham a transcrição
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoudnessWarning {
+    /// Nenhum trecho acima do portão absoluto, o áudio é silêncio
+    Silent,
+    /// Volume integrado abaixo de `QUIET_LUFS`
+    TooQuiet,
+    /// Variação de volume acima de `WIDE_RANGE_LU`, os trechos baixos podem se perder
+    WideRange,
+    /// Muitas amostras no limite, o áudio está distorcido
+    Clipping,
+}
+
+/// Medidas de volume segundo a EBU R128 / ITU-R BS.1770, salvas como `{id}.loudness.json`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessReport {
+    pub version: u32,
+    /// Tamanho do áudio quando foi medido, para perceber se ele mudou
+    pub source_len: u64,
+    /// Volume integrado em LUFS, `None` se o áudio for só silêncio
+    pub integrated: Option<f64>,
+    /// Volume integrado da mistura em mono, que é o áudio enviado para transcrição
+    ///
+    /// Num estéreo com os dois canais iguais fica uns 3 LU abaixo de `integrated`.
+    pub mono_integrated: Option<f64>,
+    /// Faixa de volume (LRA) em LU
+    pub loudness_range: Option<f64>,
+    /// Pico real (entre amostras) em dBTP
+    pub true_peak: f64,
+    /// Maior amostra em dBFS
+    pub sample_peak: f64,
+    /// Proporção das amostras no limite, de 0 a 1
+    pub clipping_ratio: f64,
+    pub warnings: Vec<LoudnessWarning>,
+}
+
+/// Relatório de volume do áudio, usando o salvo ao lado dele se o arquivo não mudou
+pub fn report(path: &Path, folder: &str, media: &str) -> Result<LoudnessReport, String> {
+    let source_len = fs::metadata(path)
+        .map_err(|e| format!("Failed to read audio file: {}", e))?
+        .len();
+    if let Some(report) =
+        LoudnessReport::load(folder, media).filter(|report| report.source_len == source_len)
+    {
+        return Ok(report);
+    }
+
+    println!("Medindo o volume de {}", path.display());
+    let mut report = analyze(path)?;
+    report.source_len = source_len;
+    report.save(folder, media)?;
+    Ok(report)
+}
+
+/// Decodifica o áudio e mede o volume, dos canais originais e da mistura em mono
+pub fn analyze(path: &Path) -> Result<LoudnessReport, String> {
+    let mut meters: Option<(Meter, Option<Meter>)> = None;
+    let mut mono = Vec::new();
+    audio::decode(path, |samples, channels, rate| {
+        let (meter, downmix) = meters.get_or_insert_with(|| {
+            (
+                Meter::new(channels, rate),
+                (channels > 1).then(|| Meter::new(1, rate)),
+            )
+        });
+        meter.push(samples);
+        if let Some(downmix) = downmix {
+            audio::downmix(samples, channels, &mut mono);
+            downmix.push(&mono);
+        }
+    })?;
+    let (meter, downmix) = meters.ok_or("O arquivo não tem áudio")?;
+    let mut report = meter.finish();
+    report.mono_integrated = match downmix {
+        Some(downmix) => downmix.finish().integrated,
+        None => report.integrated,
+    };
+    Ok(report)
+}
+
+impl LoudnessReport {
+    pub fn load(folder: &str, media: &str) -> Option<Self> {
+        let content = fs::read_to_string(sidecar_path(folder, media, "loudness")).ok()?;
+        serde_json::from_str::<Self>(&content)
+            .ok()
+            .filter(|report| report.version == LOUDNESS_VERSION)
+    }
+
+    pub fn save(&self, folder: &str, media: &str) -> Result<(), String> {
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize loudness: {}", e))?;
+        fs::write(sidecar_path(folder, media, "loudness"), content)
+            .map_err(|e| format!("Failed to write loudness: {}", e))
+    }
+
+    /// Ganho em dB que leva a mistura em mono a `target` LUFS sem passar de `MAX_TRUE_PEAK`
+    ///
+    /// O pico da mistura nunca passa do maior pico dos canais, então o limite é conservador.
+    pub fn normalization_gain(&self, target: f64) -> Option<f64> {
+        let gain = target - self.mono_integrated?;
+        Some(gain.min(MAX_TRUE_PEAK - self.true_peak))
+    }
+}
+
+/// Medidor da BS.1770, alimentado com blocos de amostras intercaladas
+struct Meter {
+    channels: usize,
+    weights: Vec<f64>,
+    filters: Vec<KWeighting>,
+    /// Amostras por sub-bloco de 100 ms
+    subblock_len: usize,
+    /// Soma dos quadrados de cada canal no sub-bloco atual
+    sums: Vec<f64>,
+    count: usize,
+    /// Potência ponderada de cada sub-bloco de 100 ms
+    subblocks: Vec<f64>,
+    true_peak: TruePeak,
+    sample_peak: f32,
+    clipped: u64,
+    samples: u64,
+}
+
+impl Meter {
+    fn new(channels: usize, rate: u32) -> Self {
+        // Em 5.1 o LFE fica de fora e os canais de trás pesam mais
+        let weights = match channels {
+            6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+            _ => vec![1.0; channels],
+        };
+        Self {
+            channels,
+            weights,
+            filters: (0..channels).map(|_| KWeighting::new(rate)).collect(),
+            subblock_len: (rate as usize / 10).max(1),
+            sums: vec![0.0; channels],
+            count: 0,
+            subblocks: Vec::new(),
+            true_peak: TruePeak::new(channels),
+            sample_peak: 0.0,
+            clipped: 0,
+            samples: 0,
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, sample) in frame.iter().enumerate() {
+                let magnitude = sample.abs();
+                self.sample_peak = self.sample_peak.max(magnitude);
+                if magnitude >= CLIP_LEVEL {
+                    self.clipped += 1;
+                }
+                let filtered = self.filters[channel].process(*sample as f64);
+                self.sums[channel] += filtered * filtered;
+            }
+            self.count += 1;
+            if self.count == self.subblock_len {
+                let power = self
+                    .sums
+                    .iter()
+                    .zip(&self.weights)
+                    .map(|(sum, weight)| weight * sum / self.count as f64)
+                    .sum();
+                self.subblocks.push(power);
+                self.sums.fill(0.0);
+                self.count = 0;
+            }
+        }
+        self.samples += samples.len() as u64;
+        self.true_peak.push(samples);
+    }
+
+    fn finish(self) -> LoudnessReport {
+        let momentary = windows(&self.subblocks, MOMENTARY_SUBBLOCKS);
+        let short_term = windows(&self.subblocks, SHORT_TERM_SUBBLOCKS);
+        let integrated = gated_loudness(&momentary, INTEGRATED_GATE);
+        let loudness_range = loudness_range(&short_term);
+        let clipping_ratio = self.clipped as f64 / self.samples.max(1) as f64;
+
+        let mut warnings = Vec::new();
+        match integrated {
+            None => warnings.push(LoudnessWarning::Silent),
+            Some(lufs) if lufs < QUIET_LUFS => warnings.push(LoudnessWarning::TooQuiet),
+            _ => {}
+        }
+        if loudness_range.is_some_and(|range| range > WIDE_RANGE_LU) {
+            warnings.push(LoudnessWarning::WideRange);
+        }
+        if clipping_ratio > CLIPPING_WARNING {
+            warnings.push(LoudnessWarning::Clipping);
+        }
+
+        LoudnessReport {
+            version: LOUDNESS_VERSION,
+            source_len: 0,
+            integrated,
+            mono_integrated: integrated,
+            loudness_range,
+            true_peak: decibels(self.true_peak.finish().max(self.sample_peak) as f64),
+            sample_peak: decibels(self.sample_peak as f64),
+            clipping_ratio,
+            warnings,
+        }
+    }
+}
+
+/// Potência média de cada janela de `size` sub-blocos, avançando um sub-bloco por vez
+fn windows(subblocks: &[f64], size: usize) -> Vec<f64> {
+    subblocks
+        .windows(size)
+        .map(|window| window.iter().sum::<f64>() / size as f64)
+        .collect()
+}
+
+/// Volume médio dos blocos acima do portão absoluto e do portão relativo `relative_gate`
+fn gated_loudness(blocks: &[f64], relative_gate: f64) -> Option<f64> {
+    let gated = gate(blocks, relative_gate);
+    (!gated.is_empty()).then(|| loudness(gated.iter().sum::<f64>() / gated.len() as f64))
+}
+
+fn gate(blocks: &[f64], relative_gate: f64) -> Vec<f64> {
+    let audible: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|power| loudness(*power) > ABSOLUTE_GATE)
+        .collect();
+    if audible.is_empty() {
+        return audible;
+    }
+    let threshold = loudness(audible.iter().sum::<f64>() / audible.len() as f64) + relative_gate;
+    audible
+        .into_iter()
+        .filter(|power| loudness(*power) > threshold)
+        .collect()
+}
+
+/// Diferença entre os percentis 95 e 10 do volume de curto prazo (EBU Tech 3342)
+fn loudness_range(short_term: &[f64]) -> Option<f64> {
+    let mut levels: Vec<f64> = gate(short_term, RANGE_GATE)
+        .into_iter()
+        .map(loudness)
+        .collect();
+    if levels.is_empty() {
+        return None;
+    }
+    levels.sort_by(f64::total_cmp);
+    let percentile = |p: f64| levels[((levels.len() - 1) as f64 * p).round() as usize];
+    Some(percentile(0.95) - percentile(0.10))
+}
+
+fn loudness(power: f64) -> f64 {
+    -0.691 + 10.0 * power.max(1e-20).log10()
+}
+
+fn decibels(amplitude: f64) -> f64 {
+    20.0 * amplitude.max(1e-10).log10()
+}
+
+/// Filtro de ponderação K: prateleira de agudos seguida de um passa-altas
+///
+/// Os coeficientes vêm dos filtros analógicos da BS.1770, então servem para qualquer taxa
+/// de amostragem e não só para os 48 kHz da tabela da norma.
+struct KWeighting {
+    stages: [Biquad; 2],
+}
+
+impl KWeighting {
+    fn new(rate: u32) -> Self {
+        let rate = rate as f64;
+
+        let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = (PI * f0 / rate).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+        let k = (PI * f0 / rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        Self {
+            stages: [shelf, high_pass],
+        }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.stages
+            .iter_mut()
+            .fold(sample, |sample, stage| stage.process(sample))
+    }
+}
+
+/// Filtro biquad na forma direta II transposta
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            state: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// Pico real, interpolando 4x entre as amostras
+///
+/// A interpolação só é calculada perto das amostras a menos de 6 dB do maior pico até agora,
+/// as outras não chegam a mudar o resultado.
+struct TruePeak {
+    taps: [[f32; 2 * TRUE_PEAK_HALF]; TRUE_PEAK_PHASES],
+    /// Amostras de cada canal, `buffers[c][next]` é a próxima a ser avaliada
+    buffers: Vec<Vec<f32>>,
+    next: usize,
+    peak: f32,
+}
+
+impl TruePeak {
+    fn new(channels: usize) -> Self {
+        let mut taps = [[0.0; 2 * TRUE_PEAK_HALF]; TRUE_PEAK_PHASES];
+        for (phase, taps) in taps.iter_mut().enumerate() {
+            let frac = (phase + 1) as f64 / (TRUE_PEAK_PHASES + 1) as f64;
+            for (k, tap) in taps.iter_mut().enumerate() {
+                let distance = k as f64 - (TRUE_PEAK_HALF - 1) as f64 - frac;
+                *tap = preprocess::kernel(distance, 0.5, TRUE_PEAK_HALF as f64) as f32;
+            }
+        }
+        Self {
+            taps,
+            // O que vem antes do início é silêncio
+            buffers: vec![vec![0.0; TRUE_PEAK_HALF - 1]; channels],
+            next: TRUE_PEAK_HALF - 1,
+            peak: 0.0,
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        let channels = self.buffers.len();
+        for (channel, buffer) in self.buffers.iter_mut().enumerate() {
+            buffer.extend(samples.iter().skip(channel).step_by(channels));
+        }
+        self.scan();
+    }
+
+    fn finish(mut self) -> f32 {
+        for buffer in &mut self.buffers {
+            buffer.extend([0.0; TRUE_PEAK_HALF]);
+        }
+        self.scan();
+        self.peak
+    }
+
+    fn scan(&mut self) {
+        let len = self.buffers.first().map_or(0, Vec::len);
+        while self.next + TRUE_PEAK_HALF < len {
+            let i = self.next;
+            for buffer in &self.buffers {
+                self.peak = self.peak.max(buffer[i].abs());
+                if buffer[i].abs().max(buffer[i + 1].abs()) < self.peak * 0.5 {
+                    continue;
+                }
+                let window = &buffer[i + 1 - TRUE_PEAK_HALF..=i + TRUE_PEAK_HALF];
+                for taps in &self.taps {
+                    let value: f32 = window.iter().zip(taps).map(|(x, tap)| x * tap).sum();
+                    self.peak = self.peak.max(value.abs());
+                }
+            }
+            self.next += 1;
+        }
+
+        // Guarda só o que a interpolação ainda vai usar
+        let keep_from = self.next + 1 - TRUE_PEAK_HALF;
+        for buffer in &mut self.buffers {
+            buffer.drain(..keep_from);
+        }
+        self.next -= keep_from;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::WavWriter;
+    use crate::test_support::temp_dir;
+    use std::f32::consts::PI;
+    use tempfile::TempDir;
+
+    /// WAV de 16 bits com as amostras intercaladas
+    fn write_wav(
+        dir: &TempDir,
+        name: &str,
+        rate: u32,
+        channels: u16,
+        samples: &[f32],
+    ) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        let mut writer = WavWriter::with_channels(&path, rate, channels).unwrap();
+        writer.write(samples).unwrap();
+        writer.finish().unwrap();
+        path
+    }
+
+    /// Seno de 1 kHz com pico em `dbfs`, repetido em cada canal
+    fn sine(rate: u32, channels: usize, secs: f32, dbfs: f32) -> Vec<f32> {
+        let amplitude = 10f32.powf(dbfs / 20.0);
+        (0..(rate as f32 * secs) as usize)
+            .flat_map(|i| {
+                let value = amplitude * (2.0 * PI * 1000.0 * i as f32 / rate as f32).sin();
+                std::iter::repeat_n(value, channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_integrated() {
+        let dir = temp_dir("loudness-integrated");
+        // EBU Tech 3341, caso 1: seno estéreo de 1 kHz a -23 dBFS mede -23 LUFS
+        let path = write_wav(&dir, "stereo.wav", 48000, 2, &sine(48000, 2, 10.0, -23.0));
+        let report = analyze(&path).unwrap();
+        let integrated = report.integrated.unwrap();
+        assert!((integrated + 23.0).abs() < 0.1, "{:?}", report);
+        assert!((report.sample_peak + 23.0).abs() < 0.1, "{:?}", report);
+        assert!(report.warnings.is_empty());
+        // A mistura em mono tem a potência de um canal só, e o ganho compensa isso
+        let mono = report.mono_integrated.unwrap();
+        assert!((mono + 26.01).abs() < 0.1, "{:?}", report);
+        let gain = report.normalization_gain(TARGET_LUFS).unwrap();
+        assert!((gain - 3.01).abs() < 0.1, "{}", gain);
+
+        // Em mono a mesma onda soma metade da potência
+        let path = write_wav(&dir, "mono.wav", 16000, 1, &sine(16000, 1, 10.0, -23.0));
+        let report = analyze(&path).unwrap();
+        assert!(
+            (report.integrated.unwrap() + 26.01).abs() < 0.1,
+            "{:?}",
+            report
+        );
+        assert_eq!(report.mono_integrated, report.integrated);
+    }
+
+    #[test]
+    fn test_normalize_stereo() {
+        let dir = temp_dir("loudness-normalize");
+        // Estéreo a -33 LUFS, com o canal direito defasado
+        let rate = 44100;
+        let samples: Vec<f32> = sine(rate, 1, 10.0, -33.0)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, left)| {
+                let t = i as f32 / rate as f32;
+                let right = 10f32.powf(-33.0 / 20.0) * (2.0 * PI * 1000.0 * t + 1.0).sin();
+                [left, right]
+            })
+            .collect();
+        let path = write_wav(&dir, "estereo.wav", rate, 2, &samples);
+        let report = analyze(&path).unwrap();
+        assert!(
+            (report.integrated.unwrap() + 33.0).abs() < 0.1,
+            "{:?}",
+            report
+        );
+
+        // O arquivo enviado, em mono de 16 kHz, chega ao alvo
+        let gain = report.normalization_gain(TARGET_LUFS).unwrap();
+        let folder = dir.path().to_str().unwrap();
+        let prepared =
+            preprocess::prepare(&path, folder, preprocess::UploadFormat::Flac, gain).unwrap();
+        let uploaded = analyze(&prepared).unwrap();
+        let integrated = uploaded.integrated.unwrap();
+        assert!((integrated - TARGET_LUFS).abs() < 0.2, "{:?}", uploaded);
+    }
+
+    #[test]
+    fn test_loudness_range() {
+        let dir = temp_dir("loudness-range");
+        // EBU Tech 3342, caso 1: 20 s a -20 dBFS e 20 s a -30 dBFS têm LRA de 10 LU
+        let mut samples = sine(16000, 2, 20.0, -20.0);
+        samples.extend(sine(16000, 2, 20.0, -30.0));
+        let path = write_wav(&dir, "range.wav", 16000, 2, &samples);
+        let report = analyze(&path).unwrap();
+        let range = report.loudness_range.unwrap();
+        assert!((range - 10.0).abs() < 1.0, "{:?}", report);
+    }
+
+    #[test]
+    fn test_true_peak() {
+        let dir = temp_dir("loudness-true-peak");
+        // Um quarto da taxa de amostragem, defasado 45°: as amostras ficam 3 dB abaixo do pico
+        let rate = 48000;
+        let samples: Vec<f32> = (0..rate)
+            .map(|i| 0.5 * (2.0 * PI * i as f32 / 4.0 + PI / 4.0).sin())
+            .collect();
+        let path = write_wav(&dir, "peak.wav", rate, 1, &samples);
+        let report = analyze(&path).unwrap();
+        assert!((report.sample_peak + 9.03).abs() < 0.1, "{:?}", report);
+        assert!((report.true_peak + 6.02).abs() < 0.5, "{:?}", report);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let dir = temp_dir("loudness-warnings");
+        let quiet = write_wav(&dir, "quiet.wav", 16000, 1, &sine(16000, 1, 5.0, -45.0));
+        let report = analyze(&quiet).unwrap();
+        assert_eq!(report.warnings, [LoudnessWarning::TooQuiet]);
+        let gain = report.normalization_gain(TARGET_LUFS).unwrap();
+        assert!((gain - 25.0).abs() < 0.1, "{}", gain);
+
+        // O ganho respeita o limite de pico
+        let peaky = LoudnessReport {
+            integrated: Some(-40.0),
+            mono_integrated: Some(-40.0),
+            true_peak: -10.0,
+            ..report.clone()
+        };
+        assert_eq!(peaky.normalization_gain(TARGET_LUFS), Some(9.0));
+
+        let clipped: Vec<f32> = sine(16000, 1, 5.0, 6.0)
+            .into_iter()
+            .map(|s| s.clamp(-1.0, 1.0))
+            .collect();
+        let clipped = write_wav(&dir, "clipped.wav", 16000, 1, &clipped);
+        let report = analyze(&clipped).unwrap();
+        assert_eq!(report.warnings, [LoudnessWarning::Clipping]);
+        assert!(report.clipping_ratio > 0.3, "{:?}", report);
+
+        let silent = dir.path().join("silent.wav");
+        fs::write(&silent, audio::silent_wav(16000, 1, 2)).unwrap();
+        let report = analyze(&silent).unwrap();
+        assert_eq!(report.integrated, None);
+        assert_eq!(report.warnings, [LoudnessWarning::Silent]);
+        assert_eq!(report.normalization_gain(TARGET_LUFS), None);
+    }
+}
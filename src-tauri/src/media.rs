@@ -0,0 +1,56 @@
+use youtube_dl::SingleVideo;
+
+/// `extractor_key` do yt-dlp para vídeos do YouTube
+const YOUTUBE_EXTRACTOR: &str = "Youtube";
+
+/// Chave estável da mídia, usada como nome dos arquivos na pasta de saída
+///
+/// Vídeos do YouTube continuam usando só o ID, para os áudios já baixados seguirem no cache.
+/// Os outros sites ganham o extrator como prefixo, porque IDs numéricos como os do Vimeo e
+/// da Twitch podem se repetir entre sites.
+pub fn media_key(video: &SingleVideo) -> String {
+    key(video.extractor_key.as_deref(), &video.id)
+}
+
+fn key(extractor_key: Option<&str>, id: &str) -> String {
+    match extractor_key {
+        Some(YOUTUBE_EXTRACTOR) => sanitize(id),
+        Some(extractor) => format!("{}_{}", sanitize(&extractor.to_lowercase()), sanitize(id)),
+        None => format!("generic_{}", sanitize(id)),
+    }
+}
+
+/// Troca o que não pode aparecer num nome de arquivo (`/`, `:`, espaços...) por `_`
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        let cases = [
+            (Some("Youtube"), "dQw4w9WgXcQ", "dQw4w9WgXcQ"),
+            (Some("Vimeo"), "76979871", "vimeo_76979871"),
+            (Some("Soundcloud"), "1234567", "soundcloud_1234567"),
+            (Some("TwitchVod"), "v123456789", "twitchvod_v123456789"),
+            (Some("Generic"), "my episode/01", "generic_my_episode_01"),
+            (None, "abc:def", "generic_abc_def"),
+        ];
+
+        for (extractor_key, id, expected) in cases {
+            assert_eq!(key(extractor_key, id), expected);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use youtube_dl::{SingleVideo, TimeRange};
+
+use crate::podcast::PODCAST_EXTRACTOR;
+
+/// Versão do formato do `{id}.meta.json`, incrementar ao mudar os campos
+///
+/// Na versão 1 o `id` era o ID do extrator, que não bate com o nome do arquivo em outros
+/// sites nem em trechos baixados.
+pub const METADATA_VERSION: u32 = 2;
+
+/// Valor de `extractor` para arquivos importados do computador
+pub const LOCAL_EXTRACTOR: &str = "Local";
+
+/// Caminho de um arquivo auxiliar (`{id}.{kind}.json`) salvo ao lado do áudio
+///
+/// Aceita tanto o ID quanto o nome ou caminho do arquivo de áudio.
+pub fn sidecar_path(folder: &str, media: &str, kind: &str) -> PathBuf {
+    Path::new(folder).join(format!("{}.{}.json", audio_stem(media), kind))
+}
+
+/// Chave da mídia a partir do ID ou do nome ou caminho do arquivo de áudio
+fn audio_stem(media: &str) -> &str {
+    Path::new(media)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(media)
+}
+
+/// Metadados do vídeo salvos ao lado do áudio, para a biblioteca não depender do yt-dlp
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MediaMetadata {
+    pub version: u32,
+    /// Chave da mídia (nome do áudio sem extensão), a mesma dos outros arquivos auxiliares
+    /// e dos comandos
+    pub id: String,
+    /// ID do vídeo no site de origem, como o yt-dlp informa
+    #[serde(default)]
+    pub video_id: Option<String>,
+    /// Nome do arquivo de áudio, relativo à pasta de saída
+    pub audio_file: String,
+    pub title: Option<String>,
+    pub channel: Option<String>,
+    pub channel_url: Option<String>,
+    /// Duração do vídeo original, em segundos
+    pub duration: Option<f64>,
+    /// Data de publicação no formato `YYYYMMDD` do yt-dlp
+    pub upload_date: Option<String>,
+    pub webpage_url: Option<String>,
+    pub extractor: Option<String>,
+    pub thumbnail: Option<String>,
+    /// Trecho baixado, quando não é o vídeo inteiro
+    pub range: Option<TimeRange>,
+    /// Momento do download ou da importação, em segundos desde 1970
+    pub downloaded_at: u64,
+}
+
+impl MediaMetadata {
+    pub fn from_video(video: &SingleVideo, audio_file: &str, range: Option<TimeRange>) -> Self {
+        Self {
+            version: METADATA_VERSION,
+            id: audio_stem(audio_file).to_string(),
+            video_id: Some(video.id.clone()),
+            audio_file: audio_file.to_string(),
+            title: video.title.clone(),
+            channel: video.channel.clone().or_else(|| video.uploader.clone()),
+            channel_url: video
+                .channel_url
+                .clone()
+                .or_else(|| video.uploader_url.clone()),
+            duration: video.duration.as_ref().and_then(|d| d.as_f64()),
+            upload_date: video.upload_date.clone(),
+            webpage_url: video.webpage_url.clone(),
+            extractor: video.extractor_key.clone(),
+            thumbnail: video.thumbnail.clone(),
+            range,
+            downloaded_at: now(),
+        }
+    }
+
+    /// Metadados de um arquivo local importado, sem nada vindo do yt-dlp
+    pub fn from_file(id: &str, audio_file: &str, title: &str, duration: Option<f64>) -> Self {
+        Self {
+            version: METADATA_VERSION,
+            id: id.to_string(),
+            video_id: None,
+            audio_file: audio_file.to_string(),
+            title: Some(title.to_string()),
+            channel: None,
+            channel_url: None,
+            duration,
+            upload_date: None,
+            webpage_url: None,
+            extractor: Some(LOCAL_EXTRACTOR.to_string()),
+            thumbnail: None,
+            range: None,
+            downloaded_at: now(),
+        }
+    }
+
+    /// Caminho do áudio dentro da pasta de saída
+    pub fn audio_path(&self, folder: &str) -> PathBuf {
+        Path::new(folder).join(&self.audio_file)
+    }
+
+    /// Salva os metadados como `{id}.meta.json`
+    pub fn save(&self, folder: &str) -> Result<(), String> {
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+        fs::write(sidecar_path(folder, &self.audio_file, "meta"), content)
+            .map_err(|e| format!("Failed to write metadata: {}", e))
+    }
+
+    /// Lê os metadados salvos para o áudio `media` (ID ou nome do arquivo)
+    pub fn load(folder: &str, media: &str) -> Result<Self, String> {
+        Self::load_path(&sidecar_path(folder, media, "meta"))
+    }
+
+    fn load_path(path: &Path) -> Result<Self, String> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+        let mut metadata: Self = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse metadata: {}", e))?;
+        if metadata.version > METADATA_VERSION {
+            return Err(format!(
+                "Metadata version {} is newer than supported version {}",
+                metadata.version, METADATA_VERSION
+            ));
+        }
+        if metadata.version < 2 {
+            // Importados e episódios já usavam a chave como `id`
+            if metadata.extractor.as_deref() != Some(LOCAL_EXTRACTOR)
+                && metadata.extractor.as_deref() != Some(PODCAST_EXTRACTOR)
+            {
+                metadata.video_id = Some(metadata.id.clone());
+            }
+            metadata.id = audio_stem(&metadata.audio_file).to_string();
+            metadata.version = METADATA_VERSION;
+        }
+        Ok(metadata)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Caminho do áudio de uma mídia da pasta
+///
+/// Áudios baixados antes dos metadados existirem são sempre `{id}.mp3`.
+pub fn audio_path(folder: &str, media: &str) -> Result<PathBuf, String> {
+    match MediaMetadata::load(folder, media) {
+        Ok(metadata) => Ok(metadata.audio_path(folder)),
+        Err(e) => {
+            let legacy = Path::new(folder).join(format!("{}.mp3", media));
+            legacy.exists().then_some(legacy).ok_or(e)
+        }
+    }
+}
+
+/// Lista os metadados de todos os áudios da pasta, os downloads mais recentes primeiro
+pub fn list(folder: &str) -> Result<Vec<MediaMetadata>, String> {
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read output directory: {}", e)),
+    };
+
+    let mut library: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.to_string_lossy().ends_with(".meta.json"))
+        .filter_map(|path| match MediaMetadata::load_path(&path) {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                println!("Ignorando {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+    library.sort_by_key(|metadata| std::cmp::Reverse(metadata.downloaded_at));
+    Ok(library)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            sidecar_path("../output", "dQw4w9WgXcQ", "meta"),
+            Path::new("../output/dQw4w9WgXcQ.meta.json")
+        );
+        assert_eq!(
+            sidecar_path("../output", "../output/dQw4w9WgXcQ.mp3", "timeline"),
+            Path::new("../output/dQw4w9WgXcQ.timeline.json")
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp = temp_dir("metadata");
+        let folder = temp.path().to_str().unwrap();
+
+        let video: SingleVideo = serde_json::from_str(
+            r#"{"id": "abc", "title": "Título", "uploader": "Canal", "duration": 212,
+                "upload_date": "20091025", "extractor_key": "Youtube"}"#,
+        )
+        .unwrap();
+        let metadata = MediaMetadata::from_video(&video, "abc.mp3", None);
+        assert_eq!(metadata.channel.as_deref(), Some("Canal"));
+        assert_eq!(metadata.duration, Some(212.0));
+
+        // O `id` é a chave dos outros arquivos auxiliares, não o ID do site
+        let ranged = MediaMetadata::from_video(
+            &video,
+            "abc_90000-end.mp3",
+            Some(TimeRange::from_start(90.0)),
+        );
+        assert_eq!(ranged.id, "abc_90000-end");
+        assert_eq!(ranged.video_id.as_deref(), Some("abc"));
+
+        metadata.save(folder).unwrap();
+        assert_eq!(MediaMetadata::load(folder, "abc").unwrap(), metadata);
+        assert!(list(folder).unwrap().contains(&metadata));
+
+        let newer = serde_json::to_string(&MediaMetadata {
+            version: METADATA_VERSION + 1,
+            ..metadata
+        })
+        .unwrap();
+        fs::write(sidecar_path(folder, "abc", "meta"), newer).unwrap();
+        assert!(MediaMetadata::load(folder, "abc").is_err());
+    }
+
+    #[test]
+    fn test_load_version_1() {
+        let temp = temp_dir("metadata-v1");
+        let folder = temp.path().to_str().unwrap();
+
+        fs::write(
+            sidecar_path(folder, "vimeo_123", "meta"),
+            r#"{"version": 1, "id": "123", "audio_file": "vimeo_123.mp3", "title": null,
+                "channel": null, "channel_url": null, "duration": null, "upload_date": null,
+                "webpage_url": null, "extractor": "Vimeo", "thumbnail": null, "range": null,
+                "downloaded_at": 0}"#,
+        )
+        .unwrap();
+        let metadata = MediaMetadata::load(folder, "vimeo_123").unwrap();
+        assert_eq!(metadata.version, METADATA_VERSION);
+        assert_eq!(metadata.id, "vimeo_123");
+        assert_eq!(metadata.video_id.as_deref(), Some("123"));
+    }
+}
@@ -0,0 +1,664 @@
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use crate::metadata::{sidecar_path, MediaMetadata};
+
+const ITUNES_NS: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+const ATOM_NS: &str = "http://www.w3.org/2005/Atom";
+/// O Podcasting 2.0 mudou a URL do namespace, feeds antigos ainda usam a do GitHub
+const PODCAST_NS: [&str; 2] = [
+    "https://podcastindex.org/namespace/1.0",
+    "https://github.com/Podcastindex-org/podcast-namespace/blob/main/docs/1.0.md",
+];
+
+/// Valor de `extractor` nos metadados dos episódios baixados
+pub const PODCAST_EXTRACTOR: &str = "Podcast";
+
+/// Quantos caracteres do hash do `guid` entram no ID do episódio
+const ID_HASH_LEN: usize = 16;
+
+/// Extensões aceitas para o arquivo do episódio, pelo `type` do enclosure
+const ENCLOSURE_TYPES: [(&str, &str); 10] = [
+    ("audio/mpeg", "mp3"),
+    ("audio/mp3", "mp3"),
+    ("audio/mp4", "m4a"),
+    ("audio/x-m4a", "m4a"),
+    ("audio/aac", "aac"),
+    ("audio/ogg", "ogg"),
+    ("audio/opus", "opus"),
+    ("audio/wav", "wav"),
+    ("audio/flac", "flac"),
+    ("video/mp4", "mp4"),
+];
+
+/// Podcast com os episódios que podem ser baixados
+#[derive(Clone, Debug, Serialize)]
+pub struct Feed {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub link: Option<String>,
+    pub image: Option<String>,
+    pub episodes: Vec<Episode>,
+}
+
+/// Episódio do feed, a interface devolve ele inteiro para `download_episode`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Episode {
+    pub guid: String,
+    pub title: String,
+    /// Nome do podcast
+    pub show: Option<String>,
+    pub description: Option<String>,
+    /// Data de publicação como veio no feed (RFC 2822 no RSS, RFC 3339 no Atom)
+    pub published: Option<String>,
+    pub link: Option<String>,
+    pub image: Option<String>,
+    pub enclosure_url: String,
+    pub enclosure_type: Option<String>,
+    /// Tamanho do arquivo em bytes, quando o feed informa
+    pub enclosure_length: Option<u64>,
+    /// Duração em segundos (`itunes:duration`)
+    pub duration: Option<f64>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    /// `podcast:chapters`, capítulos já publicados pelo autor
+    pub chapters_url: Option<String>,
+}
+
+/// Capítulo importado de um `podcast:chapters`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PodcastChapter {
+    pub start_time: f64,
+    pub end_time: Option<f64>,
+    pub title: String,
+    pub url: Option<String>,
+    pub image: Option<String>,
+}
+
+/// Formato JSON Chapters do Podcasting 2.0
+#[derive(Deserialize)]
+struct ChaptersDocument {
+    chapters: Vec<JsonChapter>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonChapter {
+    start_time: f64,
+    end_time: Option<f64>,
+    title: Option<String>,
+    url: Option<String>,
+    img: Option<String>,
+    /// `false` marca pontos que não devem aparecer no índice
+    toc: Option<bool>,
+}
+
+/// Baixa e interpreta o feed
+pub async fn fetch_feed(url: &str) -> Result<Feed, String> {
+    let content = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch feed: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read feed: {}", e))?;
+    parse_feed(&content)
+}
+
+/// Interpreta um feed RSS ou Atom
+pub fn parse_feed(content: &str) -> Result<Feed, String> {
+    let document = Document::parse(content).map_err(|e| format!("Failed to parse feed: {}", e))?;
+    let root = document.root_element();
+
+    if is(root, None, "rss") {
+        let channel =
+            child(root, None, "channel").ok_or_else(|| "Feed RSS sem <channel>".to_string())?;
+        Ok(parse_rss(channel))
+    } else if is(root, Some(ATOM_NS), "feed") {
+        Ok(parse_atom(root))
+    } else {
+        Err(format!(
+            "Formato de feed não suportado: <{}>",
+            root.tag_name().name()
+        ))
+    }
+}
+
+fn parse_rss(channel: Node) -> Feed {
+    let title = text(channel, None, "title");
+    let image = itunes_image(channel)
+        .or_else(|| child(channel, None, "image").and_then(|image| text(image, None, "url")));
+
+    let episodes = children(channel, None, "item")
+        .filter_map(|item| {
+            let enclosure = child(item, None, "enclosure")?;
+            let enclosure_url = attribute(enclosure, "url")?;
+            let tags = Tags::parse(item);
+            Some(Episode {
+                guid: text(item, None, "guid").unwrap_or_else(|| enclosure_url.clone()),
+                title: text(item, None, "title").unwrap_or_else(|| enclosure_url.clone()),
+                show: title.clone(),
+                description: text(item, None, "description")
+                    .or_else(|| text(item, Some(ITUNES_NS), "summary")),
+                published: text(item, None, "pubDate"),
+                link: text(item, None, "link"),
+                image: itunes_image(item).or_else(|| image.clone()),
+                enclosure_type: attribute(enclosure, "type"),
+                enclosure_length: attribute(enclosure, "length").and_then(|l| l.parse().ok()),
+                enclosure_url,
+                duration: tags.duration,
+                season: tags.season,
+                episode: tags.episode,
+                chapters_url: tags.chapters_url,
+            })
+        })
+        .collect();
+
+    Feed {
+        description: text(channel, None, "description")
+            .or_else(|| text(channel, Some(ITUNES_NS), "summary")),
+        author: text(channel, Some(ITUNES_NS), "author")
+            .or_else(|| text(channel, None, "managingEditor")),
+        link: text(channel, None, "link"),
+        title,
+        image,
+        episodes,
+    }
+}
+
+fn parse_atom(feed: Node) -> Feed {
+    let title = text(feed, Some(ATOM_NS), "title");
+    let image = itunes_image(feed)
+        .or_else(|| text(feed, Some(ATOM_NS), "logo"))
+        .or_else(|| text(feed, Some(ATOM_NS), "icon"));
+
+    let episodes = children(feed, Some(ATOM_NS), "entry")
+        .filter_map(|entry| {
+            let enclosure = atom_link(entry, "enclosure")?;
+            let enclosure_url = attribute(enclosure, "href")?;
+            let tags = Tags::parse(entry);
+            Some(Episode {
+                guid: text(entry, Some(ATOM_NS), "id").unwrap_or_else(|| enclosure_url.clone()),
+                title: text(entry, Some(ATOM_NS), "title").unwrap_or_else(|| enclosure_url.clone()),
+                show: title.clone(),
+                description: text(entry, Some(ATOM_NS), "summary")
+                    .or_else(|| text(entry, Some(ATOM_NS), "content")),
+                published: text(entry, Some(ATOM_NS), "published")
+                    .or_else(|| text(entry, Some(ATOM_NS), "updated")),
+                link: atom_link(entry, "alternate").and_then(|link| attribute(link, "href")),
+                image: itunes_image(entry).or_else(|| image.clone()),
+                enclosure_type: attribute(enclosure, "type"),
+                enclosure_length: attribute(enclosure, "length").and_then(|l| l.parse().ok()),
+                enclosure_url,
+                duration: tags.duration,
+                season: tags.season,
+                episode: tags.episode,
+                chapters_url: tags.chapters_url,
+            })
+        })
+        .collect();
+
+    Feed {
+        description: text(feed, Some(ATOM_NS), "subtitle"),
+        author: child(feed, Some(ATOM_NS), "author")
+            .and_then(|author| text(author, Some(ATOM_NS), "name"))
+            .or_else(|| text(feed, Some(ITUNES_NS), "author")),
+        link: atom_link(feed, "alternate").and_then(|link| attribute(link, "href")),
+        title,
+        image,
+        episodes,
+    }
+}
+
+/// Tags do iTunes e do Podcasting 2.0, que são as mesmas no RSS e no Atom
+struct Tags {
+    duration: Option<f64>,
+    season: Option<u32>,
+    episode: Option<u32>,
+    chapters_url: Option<String>,
+}
+
+impl Tags {
+    fn parse(item: Node) -> Self {
+        let number = |name: &str| {
+            text(item, Some(ITUNES_NS), name)
+                .or_else(|| podcast_child(item, name).and_then(node_text))
+                .and_then(|value| value.parse().ok())
+        };
+        Self {
+            duration: text(item, Some(ITUNES_NS), "duration").and_then(|d| parse_duration(&d)),
+            season: number("season"),
+            episode: number("episode"),
+            chapters_url: podcast_child(item, "chapters").and_then(|node| attribute(node, "url")),
+        }
+    }
+}
+
+/// Aceita `3723`, `3723.5`, `62:03` e `1:02:03`
+pub fn parse_duration(value: &str) -> Option<f64> {
+    value.trim().split(':').try_fold(0.0, |total, part| {
+        let part: f64 = part.trim().parse().ok()?;
+        (part >= 0.0).then_some(total * 60.0 + part)
+    })
+}
+
+/// Data no formato `YYYYMMDD` usado nos metadados, a partir da data do feed
+pub fn upload_date(published: &str) -> Option<String> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+
+    let published = published.trim();
+    // RFC 3339: 2024-03-05T10:00:00Z
+    if published.get(4..5) == Some("-") {
+        let date = published.get(..10)?.replace('-', "");
+        return (date.len() == 8 && date.chars().all(|c| c.is_ascii_digit())).then_some(date);
+    }
+
+    // RFC 2822: Tue, 05 Mar 2024 10:00:00 GMT (o dia da semana é opcional)
+    let mut parts = published
+        .split_whitespace()
+        .skip_while(|part| part.ends_with(','));
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month = parts.next()?.to_lowercase();
+    let month = MONTHS.iter().position(|m| month.starts_with(m))? + 1;
+    let year: u32 = parts.next()?.parse().ok()?;
+    Some(format!("{:04}{:02}{:02}", year, month, day))
+}
+
+/// Interpreta um arquivo JSON Chapters, ignorando os pontos marcados com `toc: false`
+pub fn parse_chapters(content: &str) -> Result<Vec<PodcastChapter>, String> {
+    let document: ChaptersDocument =
+        serde_json::from_str(content).map_err(|e| format!("Failed to parse chapters: {}", e))?;
+
+    let mut chapters: Vec<_> = document
+        .chapters
+        .into_iter()
+        .filter(|chapter| chapter.toc != Some(false))
+        .map(|chapter| PodcastChapter {
+            start_time: chapter.start_time,
+            end_time: chapter.end_time,
+            title: chapter.title.unwrap_or_default(),
+            url: chapter.url,
+            image: chapter.img,
+        })
+        .collect();
+    chapters.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+
+    // Sem `endTime`, o capítulo vai até o começo do próximo
+    let starts: Vec<f64> = chapters.iter().map(|c| c.start_time).skip(1).collect();
+    for (chapter, next_start) in chapters.iter_mut().zip(starts) {
+        chapter.end_time.get_or_insert(next_start);
+    }
+    Ok(chapters)
+}
+
+pub async fn fetch_chapters(url: &str) -> Result<Vec<PodcastChapter>, String> {
+    let content = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch chapters: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read chapters: {}", e))?;
+    parse_chapters(&content)
+}
+
+/// Salva os capítulos do autor como `{id}.chapters.json`, ao lado do áudio
+pub fn save_chapters(folder: &str, media: &str, chapters: &[PodcastChapter]) -> Result<(), String> {
+    let content = serde_json::to_string(chapters)
+        .map_err(|e| format!("Failed to serialize chapters: {}", e))?;
+    fs::write(sidecar_path(folder, media, "chapters"), content)
+        .map_err(|e| format!("Failed to write chapters: {}", e))
+}
+
+/// Capítulos importados do feed, se o episódio tinha
+pub fn load_chapters(folder: &str, media: &str) -> Option<Vec<PodcastChapter>> {
+    let content = fs::read_to_string(sidecar_path(folder, media, "chapters")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+impl Episode {
+    /// ID estável do episódio, a partir do `guid`
+    pub fn media_id(&self) -> String {
+        let hash: String = Sha256::digest(self.guid.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("podcast_{}", &hash[..ID_HASH_LEN])
+    }
+
+    /// Extensão do arquivo, pelo `type` do enclosure ou pela URL
+    pub fn extension(&self) -> &'static str {
+        let by_type = self.enclosure_type.as_deref().and_then(|enclosure_type| {
+            ENCLOSURE_TYPES
+                .iter()
+                .find(|(mime, _)| enclosure_type.eq_ignore_ascii_case(mime))
+                .map(|(_, ext)| *ext)
+        });
+        let path = self
+            .enclosure_url
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        by_type
+            .or_else(|| {
+                ENCLOSURE_TYPES
+                    .iter()
+                    .map(|(_, ext)| *ext)
+                    .find(|ext| path.ends_with(&format!(".{}", ext)))
+            })
+            .unwrap_or("mp3")
+    }
+
+    /// Metadados do episódio para a biblioteca
+    pub fn metadata(&self, audio_file: &str, duration: Option<f64>) -> MediaMetadata {
+        let mut metadata = MediaMetadata::from_file(
+            &self.media_id(),
+            audio_file,
+            &self.title,
+            duration.or(self.duration),
+        );
+        metadata.extractor = Some(PODCAST_EXTRACTOR.to_string());
+        metadata.channel = self.show.clone();
+        metadata.upload_date = self.published.as_deref().and_then(upload_date);
+        metadata.webpage_url = self.link.clone();
+        metadata.thumbnail = self.image.clone();
+        metadata
+    }
+}
+
+/// Baixa o episódio para `path`
+///
+/// O arquivo é escrito primeiro com outro nome, assim um download interrompido nunca é
+/// tratado como episódio já baixado.
+pub async fn download(episode: &Episode, path: &Path) -> Result<(), String> {
+    let mut response = reqwest::get(&episode.enclosure_url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download episode: {}", e))?;
+
+    let partial = path.with_extension("part");
+    let mut file =
+        File::create(&partial).map_err(|e| format!("Failed to create episode file: {}", e))?;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to download episode: {}", e))?
+    {
+        file.write_all(&chunk)
+            .map_err(|e| format!("Failed to write episode: {}", e))?;
+    }
+    fs::rename(&partial, path).map_err(|e| format!("Failed to write episode: {}", e))
+}
+
+fn is(node: Node, namespace: Option<&str>, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name && node.tag_name().namespace() == namespace
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    namespace: Option<&'a str>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| is(*child, namespace, name))
+}
+
+fn child<'a, 'input>(
+    node: Node<'a, 'input>,
+    namespace: Option<&str>,
+    name: &str,
+) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| is(*child, namespace, name))
+}
+
+fn podcast_child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    PODCAST_NS
+        .iter()
+        .find_map(|namespace| child(node, Some(namespace), name))
+}
+
+fn text(node: Node, namespace: Option<&str>, name: &str) -> Option<String> {
+    child(node, namespace, name).and_then(node_text)
+}
+
+/// Texto do elemento, juntando os blocos CDATA
+fn node_text(node: Node) -> Option<String> {
+    let text: String = node
+        .children()
+        .filter(|child| child.is_text())
+        .filter_map(|child| child.text())
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn attribute(node: Node, name: &str) -> Option<String> {
+    node.attribute(name)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+}
+
+fn itunes_image(node: Node) -> Option<String> {
+    child(node, Some(ITUNES_NS), "image").and_then(|image| attribute(image, "href"))
+}
+
+fn atom_link<'a, 'input>(node: Node<'a, 'input>, rel: &str) -> Option<Node<'a, 'input>> {
+    children(node, Some(ATOM_NS), "link")
+        .find(|link| link.attribute("rel").unwrap_or("alternate") == rel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0"
+     xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd"
+     xmlns:podcast="https://podcastindex.org/namespace/1.0">
+  <channel>
+    <title>Capitu Cast</title>
+    <link>https://capitu.example/cast</link>
+    <description><![CDATA[Conversas sobre <b>vídeo</b>]]></description>
+    <itunes:author>Equipe Capitu</itunes:author>
+    <itunes:image href="https://capitu.example/cover.jpg"/>
+    <item>
+      <title>Episódio 2: Capítulos</title>
+      <itunes:title>Capítulos</itunes:title>
+      <guid isPermaLink="false">capitu-cast-2</guid>
+      <pubDate>Tue, 05 Mar 2024 10:00:00 GMT</pubDate>
+      <enclosure url="https://cdn.example/ep2.m4a?token=1" type="audio/x-m4a" length="1234567"/>
+      <itunes:duration>1:02:03</itunes:duration>
+      <itunes:season>1</itunes:season>
+      <itunes:episode>2</itunes:episode>
+      <itunes:image href="https://capitu.example/ep2.jpg"/>
+      <podcast:chapters url="https://capitu.example/ep2.chapters.json" type="application/json+chapters"/>
+    </item>
+    <item>
+      <title>Trailer sem áudio</title>
+      <guid>trailer</guid>
+    </item>
+    <item>
+      <title>Episódio 1</title>
+      <pubDate>1 Feb 2024 08:00:00 -0300</pubDate>
+      <enclosure url="https://cdn.example/ep1.mp3" length="" type="audio/mpeg"/>
+      <itunes:duration>754</itunes:duration>
+    </item>
+  </channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:podcast="https://github.com/Podcastindex-org/podcast-namespace/blob/main/docs/1.0.md">
+  <title>Capitu Atom</title>
+  <subtitle>Feed em Atom</subtitle>
+  <author><name>Equipe Capitu</name></author>
+  <link href="https://capitu.example/atom"/>
+  <logo>https://capitu.example/logo.png</logo>
+  <entry>
+    <id>urn:uuid:1225c695</id>
+    <title>Primeiro</title>
+    <published>2024-03-05T10:00:00Z</published>
+    <summary>Resumo</summary>
+    <link rel="alternate" href="https://capitu.example/atom/1"/>
+    <link rel="enclosure" href="https://cdn.example/atom1.ogg" type="audio/ogg" length="42"/>
+    <podcast:episode>7</podcast:episode>
+    <podcast:chapters url="https://capitu.example/atom1.json" type="application/json+chapters"/>
+  </entry>
+  <entry>
+    <id>sem-enclosure</id>
+    <title>Só texto</title>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn test_parse_rss() {
+        let feed = parse_feed(RSS).unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Capitu Cast"));
+        assert_eq!(feed.author.as_deref(), Some("Equipe Capitu"));
+        assert_eq!(
+            feed.description.as_deref(),
+            Some("Conversas sobre <b>vídeo</b>")
+        );
+        assert_eq!(
+            feed.image.as_deref(),
+            Some("https://capitu.example/cover.jpg")
+        );
+        assert_eq!(feed.episodes.len(), 2);
+
+        let episode = &feed.episodes[0];
+        assert_eq!(episode.guid, "capitu-cast-2");
+        assert_eq!(episode.title, "Episódio 2: Capítulos");
+        assert_eq!(episode.show.as_deref(), Some("Capitu Cast"));
+        assert_eq!(episode.enclosure_url, "https://cdn.example/ep2.m4a?token=1");
+        assert_eq!(episode.enclosure_length, Some(1234567));
+        assert_eq!(episode.duration, Some(3723.0));
+        assert_eq!((episode.season, episode.episode), (Some(1), Some(2)));
+        assert_eq!(
+            episode.image.as_deref(),
+            Some("https://capitu.example/ep2.jpg")
+        );
+        assert_eq!(
+            episode.chapters_url.as_deref(),
+            Some("https://capitu.example/ep2.chapters.json")
+        );
+        assert_eq!(episode.extension(), "m4a");
+
+        // Sem guid, o enclosure identifica o episódio
+        let episode = &feed.episodes[1];
+        assert_eq!(episode.guid, "https://cdn.example/ep1.mp3");
+        assert_eq!(episode.enclosure_length, None);
+        assert_eq!(episode.duration, Some(754.0));
+        assert_eq!(
+            episode.image.as_deref(),
+            Some("https://capitu.example/cover.jpg")
+        );
+        assert_eq!(episode.chapters_url, None);
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let feed = parse_feed(ATOM).unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Capitu Atom"));
+        assert_eq!(feed.author.as_deref(), Some("Equipe Capitu"));
+        assert_eq!(feed.link.as_deref(), Some("https://capitu.example/atom"));
+        assert_eq!(
+            feed.image.as_deref(),
+            Some("https://capitu.example/logo.png")
+        );
+        assert_eq!(feed.episodes.len(), 1);
+
+        let episode = &feed.episodes[0];
+        assert_eq!(episode.guid, "urn:uuid:1225c695");
+        assert_eq!(
+            episode.link.as_deref(),
+            Some("https://capitu.example/atom/1")
+        );
+        assert_eq!(episode.published.as_deref(), Some("2024-03-05T10:00:00Z"));
+        assert_eq!(episode.episode, Some(7));
+        assert_eq!(
+            episode.chapters_url.as_deref(),
+            Some("https://capitu.example/atom1.json")
+        );
+        assert_eq!(episode.extension(), "ogg");
+    }
+
+    #[test]
+    fn test_parse_feed_rejects() {
+        assert!(parse_feed("não é xml").is_err());
+        assert!(parse_feed("<html><body/></html>").is_err());
+        assert!(parse_feed("<rss version=\"2.0\"/>").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("754"), Some(754.0));
+        assert_eq!(parse_duration("754.5"), Some(754.5));
+        assert_eq!(parse_duration("12:34"), Some(754.0));
+        assert_eq!(parse_duration("01:02:03"), Some(3723.0));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("1:xx"), None);
+        assert_eq!(parse_duration("-5"), None);
+    }
+
+    #[test]
+    fn test_upload_date() {
+        assert_eq!(
+            upload_date("Tue, 05 Mar 2024 10:00:00 GMT").as_deref(),
+            Some("20240305")
+        );
+        assert_eq!(
+            upload_date("1 Feb 2024 08:00:00 -0300").as_deref(),
+            Some("20240201")
+        );
+        assert_eq!(
+            upload_date("2024-03-05T10:00:00Z").as_deref(),
+            Some("20240305")
+        );
+        assert_eq!(upload_date("ontem"), None);
+    }
+
+    #[test]
+    fn test_parse_chapters() {
+        let chapters = parse_chapters(
+            r#"{"version": "1.2.0", "chapters": [
+                {"startTime": 95.5, "title": "Entrevista", "url": "https://capitu.example"},
+                {"startTime": 0, "title": "Abertura", "img": "https://capitu.example/a.jpg"},
+                {"startTime": 60, "title": "Marcador", "toc": false},
+                {"startTime": 1800, "endTime": 1900, "title": "Encerramento"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let titles: Vec<_> = chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Abertura", "Entrevista", "Encerramento"]);
+        let ends: Vec<_> = chapters.iter().map(|c| c.end_time).collect();
+        assert_eq!(ends, [Some(95.5), Some(1800.0), Some(1900.0)]);
+        assert_eq!(
+            chapters[0].image.as_deref(),
+            Some("https://capitu.example/a.jpg")
+        );
+
+        assert!(parse_chapters("{}").is_err());
+    }
+
+    #[test]
+    fn test_episode_media_id() {
+        let episode = parse_feed(RSS).unwrap().episodes.remove(0);
+        assert!(episode.media_id().starts_with("podcast_"));
+        assert_eq!(episode.media_id(), episode.clone().media_id());
+
+        let metadata = episode.metadata("x.m4a", None);
+        assert_eq!(metadata.extractor.as_deref(), Some(PODCAST_EXTRACTOR));
+        assert_eq!(metadata.channel.as_deref(), Some("Capitu Cast"));
+        assert_eq!(metadata.upload_date.as_deref(), Some("20240305"));
+        assert_eq!(metadata.duration, Some(3723.0));
+    }
+}
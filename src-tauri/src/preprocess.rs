@@ -0,0 +1,408 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio::{self, WavWriter};
+use crate::flac::FlacWriter;
+
+/// Taxa de amostragem que o modelo de transcrição usa
+pub const SPEECH_RATE: u32 = 16_000;
+
+/// Pasta, dentro da pasta de saída, dos arquivos preparados para o envio
+const UPLOAD_FOLDER: &str = "upload";
+/// Passagens por zero do sinc de cada lado, mais passagens cortam melhor e custam mais
+const ZERO_CROSSINGS: f64 = 16.0;
+/// Fração da frequência de Nyquist mantida pelo filtro, o resto é a faixa de transição
+const CUTOFF: f64 = 0.95;
+/// Posições fracionárias pré-calculadas da tabela de coeficientes
+const PHASES: usize = 256;
+
+/// Formato do áudio enviado para transcrição
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadFormat {
+    /// FLAC mono de 16 kHz, sem perdas e com cerca de metade do tamanho do WAV
+    #[default]
+    Flac,
+    /// WAV mono de 16 kHz, para servidores que não aceitam FLAC
+    Wav,
+    /// Envia o arquivo baixado sem mexer
+    Original,
+}
+
+impl UploadFormat {
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::Flac => Some("flac"),
+            Self::Wav => Some("wav"),
+            Self::Original => None,
+        }
+    }
+}
+
+/// Grava amostras mono no formato escolhido
+pub enum Encoder {
+    Flac(FlacWriter),
+    Wav(WavWriter),
+}
+
+impl Encoder {
+    /// Cria o arquivo no formato indicado pela extensão de `path` (WAV se não for `.flac`)
+    pub fn create(path: &Path, sample_rate: u32) -> Result<Self, String> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("flac") => FlacWriter::create(path, sample_rate).map(Self::Flac),
+            _ => WavWriter::create(path, sample_rate).map(Self::Wav),
+        }
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        match self {
+            Self::Flac(writer) => writer.write(samples),
+            Self::Wav(writer) => writer.write(samples),
+        }
+    }
+
+    pub fn finish(self) -> Result<(), String> {
+        match self {
+            Self::Flac(writer) => writer.finish(),
+            Self::Wav(writer) => writer.finish(),
+        }
+    }
+}
+
+/// Converte o áudio para mono de 16 kHz, pronto para o envio
+///
+/// O arquivo fica em `{folder}/upload/` com o mesmo nome do original, para o servidor devolver
+/// o mesmo `filename_id`. O original continua na pasta de saída para ser ouvido.
+/// `gain_db` é aplicado antes da conversão para 16 bits, para não perder resolução em
+/// gravações baixas.
+pub fn prepare(
+    path: &Path,
+    folder: &str,
+    format: UploadFormat,
+    gain_db: f64,
+) -> Result<PathBuf, String> {
+    let Some(extension) = format.extension() else {
+        return Ok(path.to_path_buf());
+    };
+    let stem = path
+        .file_stem()
+        .ok_or("Invalid audio file name")?
+        .to_string_lossy();
+    let target_folder = Path::new(folder).join(UPLOAD_FOLDER);
+    fs::create_dir_all(&target_folder)
+        .map_err(|e| format!("Failed to create upload directory: {}", e))?;
+    let target = target_folder.join(format!("{}.{}", stem, extension));
+
+    println!("Convertendo {} para {}", path.display(), target.display());
+    let gain = 10f32.powf(gain_db as f32 / 20.0);
+    let mut encoder = Encoder::create(&target, SPEECH_RATE)?;
+    let mut resampler: Option<Resampler> = None;
+    let mut output = Vec::new();
+    let mut result = Ok(());
+    audio::decode_mono(path, |block, rate| {
+        if result.is_err() {
+            return;
+        }
+        let resampler = resampler.get_or_insert_with(|| Resampler::new(rate, SPEECH_RATE));
+        output.clear();
+        resampler.process(block, &mut output);
+        if gain != 1.0 {
+            output.iter_mut().for_each(|sample| *sample *= gain);
+        }
+        result = encoder.write(&output);
+    })?;
+    result?;
+    if let Some(mut resampler) = resampler {
+        output.clear();
+        resampler.finish(&mut output);
+        output.iter_mut().for_each(|sample| *sample *= gain);
+        encoder.write(&output)?;
+    }
+    encoder.finish()?;
+
+    Ok(target)
+}
+
+/// Conversor de taxa de amostragem com sinc janelado, processando o áudio em blocos
+///
+/// Os coeficientes do filtro são calculados uma vez para `PHASES` posições entre duas
+/// amostras e interpolados, em vez de calcular um seno por coeficiente.
+///
+/// Só converte um canal em blocos de tamanho qualquer, o que uma crate como a `rubato` faria
+/// com uma API de canais e blocos fixos. A resposta em frequência é conferida nos testes:
+/// plano até 6 kHz vindo de 44,1 kHz, sem aliasing (-60 dB) acima de 9 kHz, e sem imagens
+/// vindo de 8 kHz.
+pub struct Resampler {
+    /// Amostras de entrada por amostra de saída
+    step: f64,
+    /// Coeficientes de cada lado da posição de saída
+    half: usize,
+    /// `(PHASES + 1) * 2 * half` coeficientes
+    table: Vec<f32>,
+    /// Entrada ainda necessária, `input[0]` é a amostra de índice `offset`
+    input: Vec<f32>,
+    offset: i64,
+    /// Quantas saídas já foram geradas
+    produced: u64,
+    /// Quantas entradas já foram recebidas
+    received: u64,
+    /// Quando as taxas são iguais o áudio passa direto
+    passthrough: bool,
+}
+
+impl Resampler {
+    pub fn new(from: u32, to: u32) -> Self {
+        let step = from as f64 / to as f64;
+        // Ao reduzir a taxa, o filtro corta abaixo da nova frequência de Nyquist
+        let cutoff = CUTOFF * 0.5 / step.max(1.0);
+        let half = (ZERO_CROSSINGS / (2.0 * cutoff)).ceil() as usize;
+
+        let mut table = Vec::with_capacity((PHASES + 1) * 2 * half);
+        for phase in 0..=PHASES {
+            let frac = phase as f64 / PHASES as f64;
+            for tap in 0..2 * half {
+                let distance = tap as f64 - half as f64 + 1.0 - frac;
+                table.push(kernel(distance, cutoff, half as f64) as f32);
+            }
+        }
+
+        Self {
+            step,
+            half,
+            table,
+            // A entrada antes do início é silêncio
+            input: vec![0.0; half],
+            offset: -(half as i64),
+            produced: 0,
+            received: 0,
+            passthrough: from == to,
+        }
+    }
+
+    /// Converte um bloco, acrescentando em `output` as saídas que já podem ser calculadas
+    pub fn process(&mut self, block: &[f32], output: &mut Vec<f32>) {
+        if self.passthrough {
+            output.extend_from_slice(block);
+            return;
+        }
+        self.input.extend_from_slice(block);
+        self.received += block.len() as u64;
+        self.drain(output, u64::MAX);
+    }
+
+    /// Calcula as últimas saídas, completando o fim com silêncio
+    pub fn finish(&mut self, output: &mut Vec<f32>) {
+        if self.passthrough {
+            return;
+        }
+        let total = (self.received as f64 / self.step).ceil() as u64;
+        self.input.extend(std::iter::repeat_n(0.0, 2 * self.half));
+        self.drain(output, total);
+    }
+
+    fn drain(&mut self, output: &mut Vec<f32>, limit: u64) {
+        let taps = 2 * self.half;
+        while self.produced < limit {
+            let position = self.produced as f64 * self.step;
+            let base = position.floor() as i64;
+            // Primeira amostra usada pelo filtro, relativa ao buffer
+            let first = (base - self.half as i64 + 1 - self.offset) as usize;
+            if first + taps > self.input.len() {
+                break;
+            }
+
+            let phase = (position - base as f64) * PHASES as f64;
+            let index = (phase as usize).min(PHASES - 1);
+            let weight = (phase - index as f64) as f32;
+            let low = &self.table[index * taps..(index + 1) * taps];
+            let high = &self.table[(index + 1) * taps..(index + 2) * taps];
+            let sample = self.input[first..first + taps]
+                .iter()
+                .zip(low.iter().zip(high))
+                .map(|(x, (a, b))| x * (a + (b - a) * weight))
+                .sum();
+            output.push(sample);
+            self.produced += 1;
+        }
+
+        // Descarta a entrada que nenhuma saída futura vai usar
+        let next = (self.produced as f64 * self.step).floor() as i64 - self.half as i64 + 1;
+        let consumed = (next - self.offset).clamp(0, self.input.len() as i64) as usize;
+        self.input.drain(..consumed);
+        self.offset += consumed as i64;
+    }
+}
+
+/// Sinc passa-baixa com janela de Blackman, `cutoff` em ciclos por amostra de entrada
+pub fn kernel(distance: f64, cutoff: f64, half: f64) -> f64 {
+    let x = distance / half;
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    let window = 0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos();
+    let t = 2.0 * cutoff * distance;
+    let sinc = if t == 0.0 {
+        1.0
+    } else {
+        (PI * t).sin() / (PI * t)
+    };
+    2.0 * cutoff * sinc * window
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::f32::consts::PI;
+
+    fn tone(frequency: f32, rate: u32, secs: f32) -> Vec<f32> {
+        (0..(rate as f32 * secs) as usize)
+            .map(|i| 0.5 * (2.0 * PI * frequency * i as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    fn amplitude(samples: &[f32]) -> f32 {
+        // Ignora as bordas, onde o filtro ainda está entrando
+        let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+        middle.iter().fold(0.0, |max, s| max.max(s.abs()))
+    }
+
+    fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+        let mut resampler = Resampler::new(from, to);
+        let mut output = Vec::new();
+        // Blocos de tamanhos variados, como os pacotes de um mp3
+        for block in samples.chunks(1152) {
+            resampler.process(block, &mut output);
+        }
+        resampler.finish(&mut output);
+        output
+    }
+
+    #[test]
+    fn test_resampler() {
+        // Um tom de 1 kHz passa inteiro e mantém a frequência
+        let output = resample(&tone(1000.0, 44100, 1.0), 44100, SPEECH_RATE);
+        assert_eq!(output.len(), 16000);
+        assert!(
+            (amplitude(&output) - 0.5).abs() < 0.01,
+            "{}",
+            amplitude(&output)
+        );
+        let expected = tone(1000.0, SPEECH_RATE, 1.0);
+        let error = output[4000..12000]
+            .iter()
+            .zip(&expected[4000..12000])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(error < 0.01, "{}", error);
+
+        // Acima da nova frequência de Nyquist o tom é removido, sem aliasing
+        let output = resample(&tone(12000.0, 48000, 1.0), 48000, SPEECH_RATE);
+        assert!(amplitude(&output) < 0.005, "{}", amplitude(&output));
+
+        // Aumentar a taxa também funciona
+        let output = resample(&tone(440.0, 8000, 1.0), 8000, SPEECH_RATE);
+        assert_eq!(output.len(), 16000);
+        assert!(
+            (amplitude(&output) - 0.5).abs() < 0.01,
+            "{}",
+            amplitude(&output)
+        );
+
+        let same = resample(&tone(440.0, 16000, 0.1), 16000, 16000);
+        assert_eq!(same, tone(440.0, 16000, 0.1));
+    }
+
+    /// Amplitude da componente em `frequency`, medida no meio do sinal
+    fn level(samples: &[f32], frequency: f32, rate: u32) -> f32 {
+        let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+        let (re, im) = middle
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (i, s)| {
+                let phase = 2.0 * PI * frequency * i as f32 / rate as f32;
+                (re + s * phase.cos(), im - s * phase.sin())
+            });
+        2.0 * (re * re + im * im).sqrt() / middle.len() as f32
+    }
+
+    /// Resposta em frequência nas conversões mais comuns, de telefone e de música
+    #[test]
+    fn test_resampler_response() {
+        // Ganho do tom `frequency` medido em `measured` depois da conversão
+        let gain = |from: u32, frequency: f32, measured: f32| {
+            let output = resample(&tone(frequency, from, 2.0), from, SPEECH_RATE);
+            level(&output, measured, SPEECH_RATE) / 0.5
+        };
+
+        // 44,1 kHz: plano na faixa da voz e -6 dB no corte, em 95% da nova Nyquist
+        for frequency in [100.0, 1000.0, 4000.0, 6000.0] {
+            let passed = gain(44100, frequency, frequency);
+            assert!((passed - 1.0).abs() < 0.001, "{} Hz: {}", frequency, passed);
+        }
+        let cutoff = gain(44100, 7600.0, 7600.0);
+        assert!((cutoff - 0.5).abs() < 0.01, "{}", cutoff);
+        // Tons acima de 9 kHz não voltam como aliasing, ficam abaixo de -60 dB
+        for frequency in [9000.0f32, 10000.0, 12000.0, 15000.0, 20000.0] {
+            let alias = (frequency % 16000.0).min(16000.0 - frequency % 16000.0);
+            let aliased = gain(44100, frequency, alias);
+            assert!(
+                aliased < 0.001,
+                "{} Hz em {} Hz: {}",
+                frequency,
+                alias,
+                aliased
+            );
+        }
+
+        // 8 kHz: plano até 3 kHz e sem a imagem espelhada acima de 4 kHz
+        for frequency in [100.0, 1000.0, 2000.0, 3000.0] {
+            let passed = gain(8000, frequency, frequency);
+            assert!((passed - 1.0).abs() < 0.001, "{} Hz: {}", frequency, passed);
+            let image = gain(8000, frequency, 8000.0 - frequency);
+            assert!(image < 0.001, "imagem de {} Hz: {}", frequency, image);
+        }
+    }
+
+    #[test]
+    fn test_prepare() {
+        let temp = temp_dir("preprocess");
+        let dir = temp.path();
+        let folder = dir.to_str().unwrap();
+        let source = dir.join("episodio.wav");
+        fs::write(&source, audio::silent_wav(44100, 2, 2)).unwrap();
+
+        for (format, extension) in [(UploadFormat::Flac, "flac"), (UploadFormat::Wav, "wav")] {
+            let prepared = prepare(&source, folder, format, 0.0).unwrap();
+            assert_eq!(
+                prepared,
+                dir.join("upload").join(format!("episodio.{}", extension))
+            );
+            let info = audio::probe(&prepared).unwrap();
+            assert_eq!(info.sample_rate, Some(SPEECH_RATE));
+            assert_eq!(info.channels, Some(1));
+            assert_eq!(info.duration, Some(2.0));
+        }
+        assert_eq!(
+            prepare(&source, folder, UploadFormat::Original, 0.0).unwrap(),
+            source
+        );
+        assert!(source.exists());
+
+        // O ganho é aplicado antes da conversão
+        let quiet = dir.join("baixo.wav");
+        let mut writer = WavWriter::create(&quiet, SPEECH_RATE).unwrap();
+        writer.write(&tone(440.0, SPEECH_RATE, 1.0)).unwrap();
+        writer.finish().unwrap();
+        let prepared = prepare(&quiet, folder, UploadFormat::Wav, -6.0206).unwrap();
+        let mut samples = Vec::new();
+        audio::decode_mono(&prepared, |block, _| samples.extend_from_slice(block)).unwrap();
+        assert!(
+            (amplitude(&samples) - 0.25).abs() < 0.01,
+            "{}",
+            amplitude(&samples)
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use youtube_dl::TimeRange;
+
+use crate::metadata::sidecar_path;
+use crate::sponsorblock::SponsorSegment;
+
+/// Campos numéricos da transcrição que representam posições no tempo
+const TIME_FIELDS: [&str; 5] = ["start", "end", "start_time", "end_time", "timestamp"];
+
+/// Converte posições do áudio baixado para a linha do tempo do vídeo original
+///
+/// O áudio pode começar depois do início do vídeo (`--download-sections`) e ter trechos
+/// cortados (`--sponsorblock-remove`). É salvo ao lado do áudio como `{id}.timeline.json`
+/// para que `take_transcription` consiga desfazer essas diferenças.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timeline {
+    /// Início do trecho baixado, em segundos do vídeo original
+    pub offset: f64,
+    /// Trechos removidos do áudio, em segundos do vídeo original
+    pub removed: Vec<SponsorSegment>,
+    /// Trechos mantidos no áudio, mas que devem ser identificados nos capítulos
+    pub labeled: Vec<SponsorSegment>,
+}
+
+impl Timeline {
+    /// Linha do tempo de um áudio que começa `offset` segundos depois do vídeo original
+    pub fn with_offset(offset: f64) -> Self {
+        Self {
+            offset,
+            ..Default::default()
+        }
+    }
+
+    /// Se o áudio baixado já está na mesma linha do tempo do vídeo original
+    pub fn is_identity(&self) -> bool {
+        self.offset <= 0.0 && self.removed.is_empty()
+    }
+
+    /// Converte uma posição do áudio baixado para a posição no vídeo original
+    pub fn to_original(&self, seconds: f64) -> f64 {
+        let mut original = self.offset + seconds;
+        for (start, end) in self.removed_ranges() {
+            if start > original {
+                break;
+            }
+            original += end - start;
+        }
+        original
+    }
+
+    /// Converte uma posição do vídeo original para o áudio baixado
+    ///
+    /// Posições dentro de um trecho removido caem no ponto do corte.
+    pub fn to_audio(&self, original: f64) -> f64 {
+        let mut seconds = original - self.offset;
+        for (start, end) in self.removed_ranges() {
+            if start >= original {
+                break;
+            }
+            seconds -= end.min(original) - start;
+        }
+        seconds.max(0.0)
+    }
+
+    /// Trechos removidos depois do início do trecho baixado, em ordem e sem sobreposição
+    ///
+    /// Segmentos do SponsorBlock podem se sobrepor (um patrocínio dentro de uma
+    /// autopromoção) e o yt-dlp corta a união deles, então os que se tocam viram um só.
+    fn removed_ranges(&self) -> Vec<(f64, f64)> {
+        let mut removed: Vec<_> = self
+            .removed
+            .iter()
+            // Só conta o que foi cortado depois do início do trecho
+            .filter(|segment| segment.end > self.offset)
+            .map(|segment| (segment.start.max(self.offset), segment.end))
+            .collect();
+        removed.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut merged: Vec<(f64, f64)> = Vec::with_capacity(removed.len());
+        for (start, end) in removed {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// Lê a linha do tempo salva ao lado do áudio, se existir
+    pub fn load(folder: &str, file_stem: &str) -> Option<Self> {
+        let content = fs::read_to_string(Self::path(folder, file_stem)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Salva a linha do tempo ao lado do áudio
+    pub fn save(&self, folder: &str, file_stem: &str) -> Result<(), String> {
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize timeline: {}", e))?;
+        fs::write(Self::path(folder, file_stem), content)
+            .map_err(|e| format!("Failed to write timeline: {}", e))
+    }
+
+    fn path(folder: &str, file_stem: &str) -> PathBuf {
+        sidecar_path(folder, file_stem, "timeline")
+    }
+}
+
+/// Nome do arquivo (sem extensão) para o vídeo inteiro ou apenas um trecho dele
+///
+/// Trechos diferentes do mesmo vídeo precisam de arquivos diferentes, senão o cache
+/// de `download_audio` devolveria o trecho errado.
+pub fn file_stem(video_id: &str, range: Option<&TimeRange>) -> String {
+    match range {
+        None => video_id.to_string(),
+        Some(range) => format!(
+            "{}_{}-{}",
+            video_id,
+            millis(range.start),
+            range
+                .end
+                .map(|end| millis(end).to_string())
+                .unwrap_or_else(|| "end".to_string())
+        ),
+    }
+}
+
+fn millis(seconds: f64) -> u64 {
+    (seconds.max(0.0) * 1000.0).round() as u64
+}
+
+/// Converte os timestamps (`MM:SS` ou `H:MM:SS`) de um texto para a linha do tempo original
+pub fn remap_timestamps(text: &str, timeline: &Timeline) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        result.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+        let len = candidate
+            .find(|c: char| !(c.is_ascii_digit() || c == ':'))
+            .unwrap_or(candidate.len());
+        // "00:00: Introdução" não deve levar o último ':' junto
+        let token = candidate[..len].trim_end_matches(':');
+
+        match parse_timestamp(token) {
+            Some(seconds) => result.push_str(&format_timestamp(timeline.to_original(seconds))),
+            None => result.push_str(token),
+        }
+        rest = &candidate[token.len()..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Converte a transcrição para a linha do tempo original, seja ela texto com timestamps
+/// ou segmentos com campos `start`/`end`
+pub fn remap_transcript(transcript: &mut Value, timeline: &Timeline) {
+    match transcript {
+        Value::String(text) => *text = remap_timestamps(text, timeline),
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| remap_transcript(item, timeline)),
+        Value::Object(fields) => {
+            for (key, value) in fields.iter_mut() {
+                match value {
+                    Value::Number(number) if TIME_FIELDS.contains(&key.as_str()) => {
+                        if let Some(shifted) = number
+                            .as_f64()
+                            .and_then(|n| serde_json::Number::from_f64(timeline.to_original(n)))
+                        {
+                            *number = shifted;
+                        }
+                    }
+                    Value::Array(_) | Value::Object(_) => remap_transcript(value, timeline),
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Converte `MM:SS` ou `H:MM:SS` em segundos
+pub fn parse_timestamp(token: &str) -> Option<f64> {
+    let parts: Vec<&str> = token.split(':').collect();
+    if !(2..=3).contains(&parts.len()) || parts.iter().any(|p| p.is_empty()) {
+        return None;
+    }
+
+    let mut seconds = 0u64;
+    for (i, part) in parts.iter().enumerate() {
+        let value: u64 = part.parse().ok()?;
+        // Minutos e segundos sempre têm dois dígitos e são menores que 60
+        if i > 0 && (part.len() != 2 || value >= 60) {
+            return None;
+        }
+        seconds = seconds * 60 + value;
+    }
+    Some(seconds as f64)
+}
+
+fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use youtube_dl::SponsorBlockCategory;
+
+    fn segment(start: f64, end: f64) -> SponsorSegment {
+        SponsorSegment {
+            start,
+            end,
+            category: SponsorBlockCategory::Sponsor,
+            label: "Patrocínio".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_file_stem() {
+        assert_eq!(file_stem("dQw4w9WgXcQ", None), "dQw4w9WgXcQ");
+        assert_eq!(
+            file_stem("dQw4w9WgXcQ", Some(&TimeRange::new(90.0, 312.5))),
+            "dQw4w9WgXcQ_90000-312500"
+        );
+        assert_eq!(
+            file_stem("dQw4w9WgXcQ", Some(&TimeRange::from_start(60.0))),
+            "dQw4w9WgXcQ_60000-end"
+        );
+    }
+
+    #[test]
+    fn test_remap_timestamps() {
+        let chapters = "00:00 Introdução\n05:30: Tema principal\n1:02:03 Encerramento";
+        assert_eq!(
+            remap_timestamps(chapters, &Timeline::with_offset(3600.0)),
+            "1:00:00 Introdução\n1:05:30: Tema principal\n2:02:03 Encerramento"
+        );
+        // Números que não são timestamps ficam como estão
+        assert_eq!(
+            remap_timestamps("Top 10 de 2024 às 12:75", &Timeline::with_offset(60.0)),
+            "Top 10 de 2024 às 12:75"
+        );
+    }
+
+    #[test]
+    fn test_removed_segments() {
+        let timeline = Timeline {
+            removed: vec![segment(60.0, 90.0), segment(10.0, 20.0)],
+            ..Default::default()
+        };
+        assert_eq!(timeline.to_original(5.0), 5.0);
+        assert_eq!(timeline.to_original(15.0), 25.0);
+        assert_eq!(timeline.to_original(55.0), 95.0);
+        assert_eq!(timeline.to_audio(25.0), 15.0);
+        assert_eq!(timeline.to_audio(95.0), 55.0);
+        // Dentro de um trecho removido cai no corte
+        assert_eq!(timeline.to_audio(70.0), 50.0);
+
+        // Patrocínio que começa antes do trecho baixado só conta a partir do início dele
+        let timeline = Timeline {
+            offset: 100.0,
+            removed: vec![segment(90.0, 130.0), segment(10.0, 20.0)],
+            ..Default::default()
+        };
+        assert_eq!(timeline.to_original(0.0), 130.0);
+        assert_eq!(timeline.to_original(10.0), 140.0);
+        assert_eq!(timeline.to_audio(140.0), 10.0);
+        assert_eq!(timeline.to_audio(50.0), 0.0);
+    }
+
+    #[test]
+    fn test_overlapping_segments() {
+        // O yt-dlp corta a união de 10–40, não 20 + 20 segundos
+        let timeline = Timeline {
+            removed: vec![segment(20.0, 40.0), segment(10.0, 30.0)],
+            ..Default::default()
+        };
+        assert_eq!(timeline.to_original(5.0), 5.0);
+        assert_eq!(timeline.to_original(15.0), 45.0);
+        assert_eq!(timeline.to_audio(45.0), 15.0);
+        assert_eq!(timeline.to_audio(25.0), 10.0);
+
+        // Um trecho dentro do outro e trechos que só se tocam
+        let timeline = Timeline {
+            removed: vec![
+                segment(10.0, 50.0),
+                segment(20.0, 30.0),
+                segment(50.0, 60.0),
+                segment(100.0, 110.0),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(timeline.to_original(15.0), 65.0);
+        assert_eq!(timeline.to_original(60.0), 120.0);
+        assert_eq!(timeline.to_audio(65.0), 15.0);
+        assert_eq!(timeline.to_audio(120.0), 60.0);
+        assert_eq!(timeline.to_audio(55.0), 10.0);
+    }
+
+    #[test]
+    fn test_remap_transcript_segments() {
+        let mut transcript = json!({
+            "segments": [
+                {"start": 0.0, "end": 4.5, "text": "Olá 00:10"},
+                {"start": 4.5, "end": 9.0, "text": "pessoal"}
+            ]
+        });
+        remap_transcript(&mut transcript, &Timeline::with_offset(100.0));
+        assert_eq!(
+            transcript,
+            json!({
+                "segments": [
+                    {"start": 100.0, "end": 104.5, "text": "Olá 00:10"},
+                    {"start": 104.5, "end": 109.0, "text": "pessoal"}
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use youtube_dl::{SingleVideo, SponsorBlockCategory, YoutubeDl};
+
+/// Categorias usadas quando o frontend não escolhe nenhuma
+pub const DEFAULT_CATEGORIES: [SponsorBlockCategory; 5] = [
+    SponsorBlockCategory::Sponsor,
+    SponsorBlockCategory::SelfPromo,
+    SponsorBlockCategory::Interaction,
+    SponsorBlockCategory::Intro,
+    SponsorBlockCategory::Outro,
+];
+
+/// O que fazer com os trechos do SponsorBlock antes da transcrição
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SponsorMode {
+    /// Ignora o SponsorBlock
+    #[default]
+    Keep,
+    /// Corta os trechos do áudio (`--sponsorblock-remove`)
+    Exclude,
+    /// Mantém o áudio e envia os trechos identificados junto com a transcrição
+    Label,
+}
+
+impl SponsorMode {
+    /// Aplica as opções do SponsorBlock ao builder do yt-dlp
+    pub fn apply(self, ytdl: &mut YoutubeDl, categories: &[SponsorBlockCategory]) {
+        match self {
+            SponsorMode::Keep => {}
+            SponsorMode::Exclude => {
+                // O yt-dlp recusa `poi_highlight` e `chapter` no `--sponsorblock-remove`
+                ytdl.sponsorblock_remove(categories.iter().copied().filter(|c| removable(*c)));
+            }
+            SponsorMode::Label => {
+                ytdl.sponsorblock_mark(categories.iter().copied());
+            }
+        }
+    }
+}
+
+/// Se a categoria marca trechos que podem ser cortados, e não só pontos do vídeo
+pub fn removable(category: SponsorBlockCategory) -> bool {
+    !matches!(
+        category,
+        SponsorBlockCategory::PoiHighlight
+            | SponsorBlockCategory::Chapter
+            | SponsorBlockCategory::Unknown
+    )
+}
+
+/// Um trecho do SponsorBlock, em segundos do vídeo original
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SponsorSegment {
+    pub start: f64,
+    pub end: f64,
+    pub category: SponsorBlockCategory,
+    /// Nome do trecho para mostrar nos capítulos
+    pub label: String,
+}
+
+/// Extrai os trechos que podem ser pulados (`type == "skip"`) das categorias escolhidas
+pub fn segments(video: &SingleVideo, categories: &[SponsorBlockCategory]) -> Vec<SponsorSegment> {
+    let mut segments: Vec<_> = video
+        .sponsorblock_chapters
+        .iter()
+        .flatten()
+        .filter(|chapter| chapter.segment_type.as_deref().unwrap_or("skip") == "skip")
+        .filter_map(|chapter| {
+            let category = chapter.category?;
+            let (start, end) = (chapter.start_time?, chapter.end_time?);
+            (categories.contains(&category) && end > start).then(|| SponsorSegment {
+                start,
+                end,
+                category,
+                label: label(category).to_string(),
+            })
+        })
+        .collect();
+    segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+    segments
+}
+
+fn label(category: SponsorBlockCategory) -> &'static str {
+    match category {
+        SponsorBlockCategory::Sponsor => "Patrocínio",
+        SponsorBlockCategory::Intro => "Vinheta de abertura",
+        SponsorBlockCategory::Outro => "Encerramento",
+        SponsorBlockCategory::SelfPromo => "Autopromoção",
+        SponsorBlockCategory::Preview => "Prévia",
+        SponsorBlockCategory::Filler => "Conteúdo fora do tema",
+        SponsorBlockCategory::Interaction => "Pedido de interação",
+        SponsorBlockCategory::MusicOfftopic => "Música",
+        SponsorBlockCategory::PoiHighlight => "Destaque",
+        SponsorBlockCategory::Chapter | SponsorBlockCategory::Unknown => "Outros",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments_filter_categories_and_types() {
+        let video: SingleVideo = serde_json::from_str(
+            r#"{"id": "abc", "sponsorblock_chapters": [
+                {"start_time": 300.0, "end_time": 320.0, "category": "selfpromo", "type": "skip"},
+                {"start_time": 10.0, "end_time": 40.0, "category": "sponsor", "type": "skip"},
+                {"start_time": 50.0, "end_time": 60.0, "category": "filler", "type": "skip"},
+                {"start_time": 90.0, "end_time": 90.0, "category": "poi_highlight", "type": "poi"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let segments = segments(&video, &DEFAULT_CATEGORIES);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].category, SponsorBlockCategory::Sponsor);
+        assert_eq!(segments[0].label, "Patrocínio");
+        assert_eq!((segments[1].start, segments[1].end), (300.0, 320.0));
+    }
+
+    #[test]
+    fn test_removable() {
+        assert!(DEFAULT_CATEGORIES.iter().all(|c| removable(*c)));
+        assert!(removable(SponsorBlockCategory::MusicOfftopic));
+        assert!(!removable(SponsorBlockCategory::PoiHighlight));
+        assert!(!removable(SponsorBlockCategory::Chapter));
+    }
+}
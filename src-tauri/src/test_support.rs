@@ -0,0 +1,12 @@
+use tempfile::TempDir;
+
+/// Pasta temporária só do teste, apagada quando sai de escopo
+///
+/// Cada chamada cria uma pasta nova, então os testes rodando em paralelo, ou duas execuções
+/// ao mesmo tempo, não apagam os arquivos uns dos outros.
+pub fn temp_dir(name: &str) -> TempDir {
+    tempfile::Builder::new()
+        .prefix(&format!("capituai-{}-", name))
+        .tempdir()
+        .unwrap()
+}
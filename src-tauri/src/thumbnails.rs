@@ -0,0 +1,125 @@
+use base64::Engine;
+use std::fs;
+use std::path::{Path, PathBuf};
+use youtube_dl::Thumbnail;
+
+/// Tamanho usado pelos cards da interface quando o frontend não informa outro
+pub const DEFAULT_WIDTH: u32 = 480;
+pub const DEFAULT_HEIGHT: u32 = 270;
+
+/// Extensões que o YouTube e outros sites usam para as miniaturas
+const EXTENSIONS: [&str; 3] = ["jpg", "webp", "png"];
+
+/// Procura a miniatura já baixada para o vídeo e o tamanho pedido
+pub fn find_cached(folder: &str, media_id: &str, width: u32, height: u32) -> Option<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|ext| cache_path(folder, media_id, width, height, ext))
+        .find(|path| path.is_file())
+}
+
+/// Caminho da miniatura no cache, ao lado do áudio do vídeo
+pub fn cache_path(folder: &str, media_id: &str, width: u32, height: u32, ext: &str) -> PathBuf {
+    Path::new(folder).join(format!(
+        "{}.thumbnail_{}x{}.{}",
+        media_id, width, height, ext
+    ))
+}
+
+/// Extensão da miniatura a partir da URL, `jpg` quando não dá para saber
+pub fn extension(thumbnail: &Thumbnail) -> &'static str {
+    let path = thumbnail
+        .url
+        .as_deref()
+        .unwrap_or_default()
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|ext| path.ends_with(&format!(".{}", ext)))
+        .copied()
+        .unwrap_or("jpg")
+}
+
+/// Baixa a miniatura para `path`
+///
+/// O arquivo é escrito primeiro com outro nome, assim um download interrompido nunca é
+/// encontrado pelo `find_cached`.
+pub async fn download(thumbnail: &Thumbnail, path: &Path) -> Result<(), String> {
+    let url = thumbnail
+        .url
+        .as_deref()
+        .ok_or_else(|| "Thumbnail has no URL".to_string())?;
+
+    let bytes = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download thumbnail: {}", e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read thumbnail: {}", e))?;
+
+    let partial = path.with_extension("part");
+    fs::write(&partial, &bytes).map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+    fs::rename(&partial, path).map_err(|e| format!("Failed to write thumbnail: {}", e))
+}
+
+/// Lê a miniatura do cache como `data:` URL, para a interface não depender do caminho local
+pub fn data_url(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read thumbnail: {}", e))?;
+    let mime = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("webp") => "image/webp",
+        Some("png") => "image/png",
+        _ => "image/jpeg",
+    };
+    Ok(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn thumbnail(url: &str) -> Thumbnail {
+        Thumbnail {
+            url: Some(url.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(
+            extension(&thumbnail(
+                "https://i.ytimg.com/vi_webp/abc/maxresdefault.webp"
+            )),
+            "webp"
+        );
+        assert_eq!(
+            extension(&thumbnail(
+                "https://i.ytimg.com/vi/abc/hqdefault.jpg?sqp=-oaymwE"
+            )),
+            "jpg"
+        );
+        assert_eq!(extension(&thumbnail("https://example.com/thumb")), "jpg");
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let temp = temp_dir("thumbnail");
+        let folder = temp.path().to_str().unwrap();
+
+        let path = cache_path(folder, "abc", 480, 270, "png");
+        fs::write(&path, [1u8, 2, 3]).unwrap();
+
+        assert_eq!(find_cached(folder, "abc", 480, 270), Some(path.clone()));
+        assert_eq!(find_cached(folder, "abc", 1280, 720), None);
+        assert_eq!(data_url(&path).unwrap(), "data:image/png;base64,AQID");
+    }
+}
@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio;
+use crate::capitu_client::{CapituClient, CapituError};
+use crate::chunking::{self, ChunkManifest};
+use crate::metadata::{self, sidecar_path};
+use crate::preprocess::{Resampler, SPEECH_RATE};
+
+/// Versão do formato do `{id}.transcript.json`, incrementar ao mudar os campos
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+/// Motor usado para transcrever, escolhido pelo usuário
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "engine", rename_all = "snake_case")]
+pub enum TranscriberConfig {
+    /// Serviço de transcrição, a partir do áudio enviado por `upload_audio`
+    #[default]
+    Remote,
+    /// whisper.cpp no próprio computador, com um modelo `ggml` baixado pelo usuário
+    Local {
+        model_path: PathBuf,
+        /// Código do idioma (`pt`, `en`...), detectado pelo modelo se ausente
+        #[serde(default)]
+        language: Option<String>,
+        #[serde(default)]
+        threads: Option<u32>,
+    },
+}
+
+impl TranscriberConfig {
+    /// O `client` só é usado pelo serviço de transcrição
+    pub fn build(self, folder: &str, client: CapituClient) -> Engine {
+        match self {
+            Self::Remote => Engine::Remote(RemoteTranscriber {
+                client,
+                folder: folder.to_string(),
+            }),
+            Self::Local {
+                model_path,
+                language,
+                threads,
+            } => Engine::Local(LocalTranscriber {
+                folder: folder.to_string(),
+                model_path,
+                language,
+                threads,
+            }),
+        }
+    }
+}
+
+/// Transcreve os áudios da pasta de saída
+///
+/// A transcrição sai como uma lista de segmentos com `start` e `end` em segundos do áudio
+/// baixado e o `text`, o formato que `take_transcription` ajusta e envia para gerar os
+/// capítulos.
+pub trait Transcriber {
+    /// Começa a transcrever a mídia, devolvendo a resposta repassada à interface
+    ///
+    /// O serviço responde com o `task_id` da transcrição (e `task_ids`, uma por pedaço, em
+    /// áudios enviados divididos), o motor local só responde quando termina.
+    async fn start(&self, media_id: &str) -> Result<Value, String>;
+
+    /// Transcrição pronta da mídia
+    async fn transcript(&self, media_id: &str) -> Result<Value, String>;
+}
+
+/// O motor escolhido em `TranscriberConfig`
+pub enum Engine {
+    Remote(RemoteTranscriber),
+    Local(LocalTranscriber),
+}
+
+impl Transcriber for Engine {
+    async fn start(&self, media_id: &str) -> Result<Value, String> {
+        match self {
+            Self::Remote(remote) => remote.start(media_id).await,
+            Self::Local(local) => local.start(media_id).await,
+        }
+    }
+
+    async fn transcript(&self, media_id: &str) -> Result<Value, String> {
+        match self {
+            Self::Remote(remote) => remote.transcript(media_id).await,
+            Self::Local(local) => local.transcript(media_id).await,
+        }
+    }
+}
+
+/// Serviço de transcrição, para áudios enviados por `upload_audio`
+pub struct RemoteTranscriber {
+    client: CapituClient,
+    folder: String,
+}
+
+impl Transcriber for RemoteTranscriber {
+    /// Áudios enviados em pedaços têm uma tarefa por pedaço, todas começam de uma vez
+    ///
+    /// A resposta traz as tarefas de todos os pedaços em `task_ids` para a interface esperar
+    /// cada uma, e o `task_id` da última, como a de um áudio inteiro.
+    async fn start(&self, media_id: &str) -> Result<Value, String> {
+        let task = match ChunkManifest::load(&self.folder, media_id) {
+            None => self.client.start_transcription(media_id).await?,
+            Some(mut manifest) => {
+                let mut last = None;
+                for chunk in &mut manifest.chunks {
+                    let task = self.client.start_transcription(&chunk.filename_id).await?;
+                    chunk.task_id = task.task_id.clone();
+                    last = Some(task);
+                }
+                manifest.save(&self.folder, media_id)?;
+
+                // Pedaços já transcritos voltam sem tarefa
+                let task_ids: Vec<String> = manifest
+                    .chunks
+                    .into_iter()
+                    .filter_map(|chunk| chunk.task_id)
+                    .collect();
+                let mut task = last.ok_or("Chunk list is empty")?;
+                task.task_id = task_ids.last().cloned();
+                task.extra.insert("task_ids".to_string(), task_ids.into());
+                task
+            }
+        };
+
+        serde_json::to_value(task).map_err(|e| format!("Failed to serialize task: {}", e))
+    }
+
+    /// Áudios enviados em pedaços têm uma transcrição por pedaço, que voltam a ser uma só
+    async fn transcript(&self, media_id: &str) -> Result<Value, String> {
+        match ChunkManifest::load(&self.folder, media_id) {
+            Some(manifest) => {
+                let mut parts = Vec::with_capacity(manifest.chunks.len());
+                for chunk in &manifest.chunks {
+                    // Tarefas que a interface já esperou respondem na primeira consulta
+                    if let Some(task_id) = &chunk.task_id {
+                        let result = self.client.wait_task(task_id).await?;
+                        if !result.is_success() {
+                            return Err(CapituError::Task {
+                                task_id: task_id.clone(),
+                                result: result.result,
+                            }
+                            .into());
+                        }
+                    }
+                    parts.push((
+                        self.client.transcript(&chunk.filename_id).await?,
+                        chunk.start,
+                    ));
+                }
+                Ok(chunking::stitch(parts))
+            }
+            None => Ok(self.client.transcript(media_id).await?),
+        }
+    }
+}
+
+/// Trecho transcrito, com os tempos em segundos
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// Transcrição feita no computador, salva como `{id}.transcript.json`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LocalTranscript {
+    pub version: u32,
+    /// Tamanho do áudio transcrito, para perceber se ele mudou
+    pub source_len: u64,
+    /// Nome do arquivo do modelo, outro modelo transcreve de novo
+    pub model: String,
+    pub segments: Vec<Segment>,
+}
+
+impl LocalTranscript {
+    pub fn load(folder: &str, media: &str) -> Option<Self> {
+        let content = fs::read_to_string(sidecar_path(folder, media, "transcript")).ok()?;
+        serde_json::from_str::<Self>(&content)
+            .ok()
+            .filter(|transcript| transcript.version == TRANSCRIPT_VERSION)
+    }
+
+    pub fn save(&self, folder: &str, media: &str) -> Result<(), String> {
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize transcript: {}", e))?;
+        fs::write(sidecar_path(folder, media, "transcript"), content)
+            .map_err(|e| format!("Failed to write transcript: {}", e))
+    }
+}
+
+/// whisper.cpp rodando na CPU, funciona sem internet e sem créditos
+pub struct LocalTranscriber {
+    folder: String,
+    model_path: PathBuf,
+    language: Option<String>,
+    threads: Option<u32>,
+}
+
+impl Transcriber for LocalTranscriber {
+    async fn start(&self, media_id: &str) -> Result<Value, String> {
+        let path = metadata::audio_path(&self.folder, media_id)?;
+        let source_len = fs::metadata(&path)
+            .map_err(|e| format!("Failed to read audio file: {}", e))?
+            .len();
+        let model = self
+            .model_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or("Modelo de transcrição não informado")?;
+
+        let cached = LocalTranscript::load(&self.folder, media_id)
+            .filter(|saved| saved.source_len == source_len && saved.model == model);
+        let transcript = match cached {
+            Some(transcript) => transcript,
+            None => {
+                println!("Transcrevendo {} com {}", path.display(), model);
+                let model_path = self.model_path.clone();
+                let language = self.language.clone();
+                let threads = self.threads.unwrap_or_else(default_threads);
+                let segments = tokio::task::spawn_blocking(move || {
+                    let samples = speech_samples(&path)?;
+                    whisper::transcribe(&model_path, language.as_deref(), threads, &samples)
+                })
+                .await
+                .map_err(|e| format!("Failed to run local transcription: {}", e))??;
+
+                let transcript = LocalTranscript {
+                    version: TRANSCRIPT_VERSION,
+                    source_len,
+                    model,
+                    segments,
+                };
+                transcript.save(&self.folder, media_id)?;
+                transcript
+            }
+        };
+
+        Ok(serde_json::json!({
+            "filename_id": media_id,
+            "status": "success",
+            "segments": transcript.segments.len(),
+        }))
+    }
+
+    async fn transcript(&self, media_id: &str) -> Result<Value, String> {
+        let transcript = LocalTranscript::load(&self.folder, media_id)
+            .ok_or("Transcrição local não encontrada, rode process_transcription antes")?;
+        serde_json::to_value(transcript.segments)
+            .map_err(|e| format!("Failed to serialize transcript: {}", e))
+    }
+}
+
+/// Metade dos núcleos, até 8, para o computador continuar usável durante a transcrição
+fn default_threads() -> u32 {
+    std::thread::available_parallelism()
+        .map(|n| (n.get() as u32 / 2).clamp(1, 8))
+        .unwrap_or(4)
+}
+
+/// O áudio inteiro em mono de 16 kHz, o formato que o whisper espera
+fn speech_samples(path: &Path) -> Result<Vec<f32>, String> {
+    let mut resampler: Option<Resampler> = None;
+    let mut samples = Vec::new();
+    audio::decode_mono(path, |block, rate| {
+        resampler
+            .get_or_insert_with(|| Resampler::new(rate, SPEECH_RATE))
+            .process(block, &mut samples);
+    })?;
+    let mut resampler = resampler.ok_or("O arquivo não tem áudio")?;
+    resampler.finish(&mut samples);
+    Ok(samples)
+}
+
+#[cfg(feature = "whisper")]
+mod whisper {
+    use std::path::Path;
+    use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+    use super::Segment;
+
+    /// Os tempos do whisper.cpp vêm em centésimos de segundo
+    const TICKS_PER_SEC: f64 = 100.0;
+
+    pub fn transcribe(
+        model_path: &Path,
+        language: Option<&str>,
+        threads: u32,
+        samples: &[f32],
+    ) -> Result<Vec<Segment>, String> {
+        let model = model_path.to_str().ok_or("Caminho do modelo inválido")?;
+        let context = WhisperContext::new_with_params(model, WhisperContextParameters::default())
+            .map_err(|e| format!("Failed to load whisper model: {}", e))?;
+        let mut state = context
+            .create_state()
+            .map_err(|e| format!("Failed to create whisper state: {}", e))?;
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Some(language.unwrap_or("auto")));
+        params.set_n_threads(threads as i32);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+        params.set_progress_callback_safe(|progress: i32| {
+            println!("Transcrição local: {}%", progress);
+        });
+        state
+            .full(params, samples)
+            .map_err(|e| format!("Failed to transcribe audio: {}", e))?;
+
+        let count = state
+            .full_n_segments()
+            .map_err(|e| format!("Failed to read transcription: {}", e))?;
+        let mut segments = Vec::with_capacity(count.max(0) as usize);
+        for i in 0..count {
+            let read = |e| format!("Failed to read transcription: {}", e);
+            let text = state.full_get_segment_text_lossy(i).map_err(read)?;
+            segments.push(Segment {
+                start: state.full_get_segment_t0(i).map_err(read)? as f64 / TICKS_PER_SEC,
+                end: state.full_get_segment_t1(i).map_err(read)? as f64 / TICKS_PER_SEC,
+                text: text.trim().to_string(),
+            });
+        }
+        Ok(segments)
+    }
+}
+
+#[cfg(not(feature = "whisper"))]
+mod whisper {
+    use std::path::Path;
+
+    use super::Segment;
+
+    pub fn transcribe(
+        _model_path: &Path,
+        _language: Option<&str>,
+        _threads: u32,
+        _samples: &[f32],
+    ) -> Result<Vec<Segment>, String> {
+        Err("Esta versão foi compilada sem a transcrição local (feature `whisper`)".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::WavWriter;
+    use crate::capitu_client::Endpoints;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_config() {
+        let remote: TranscriberConfig = serde_json::from_str(r#"{"engine":"remote"}"#).unwrap();
+        assert_eq!(remote, TranscriberConfig::Remote);
+
+        let local: TranscriberConfig =
+            serde_json::from_str(r#"{"engine":"local","model_path":"/modelos/ggml-base.bin"}"#)
+                .unwrap();
+        assert_eq!(
+            local,
+            TranscriberConfig::Local {
+                model_path: PathBuf::from("/modelos/ggml-base.bin"),
+                language: None,
+                threads: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_local_transcript_cache() {
+        let temp = temp_dir("transcriber");
+        let dir = temp.path();
+        let folder = dir.to_str().unwrap();
+
+        let path = dir.join("fala.wav");
+        let mut writer = WavWriter::create(&path, 44100).unwrap();
+        writer.write(&vec![0.1; 44100 * 2]).unwrap();
+        writer.finish().unwrap();
+        metadata::MediaMetadata::from_file("fala", "fala.wav", "Fala", Some(2.0))
+            .save(folder)
+            .unwrap();
+        let samples = speech_samples(&path).unwrap();
+        assert!(
+            (samples.len() as i64 - 32000).abs() < 16,
+            "{}",
+            samples.len()
+        );
+
+        // Com uma transcrição salva do mesmo áudio e modelo o whisper nem é carregado
+        let segments = vec![Segment {
+            start: 0.0,
+            end: 2.0,
+            text: "Olá".to_string(),
+        }];
+        LocalTranscript {
+            version: TRANSCRIPT_VERSION,
+            source_len: fs::metadata(&path).unwrap().len(),
+            model: "ggml-base.bin".to_string(),
+            segments: segments.clone(),
+        }
+        .save(folder, "fala")
+        .unwrap();
+
+        let config = TranscriberConfig::Local {
+            model_path: PathBuf::from("/não/existe/ggml-base.bin"),
+            language: None,
+            threads: None,
+        };
+        let endpoints = Endpoints {
+            transcribe_url: String::new(),
+            chapters_url: String::new(),
+        };
+        let engine = config.build(folder, CapituClient::new(endpoints, None));
+        let started = engine.start("fala").await.unwrap();
+        assert_eq!(started["segments"], 1);
+        assert_eq!(
+            engine.transcript("fala").await.unwrap(),
+            serde_json::to_value(&segments).unwrap()
+        );
+    }
+}
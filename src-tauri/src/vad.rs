@@ -0,0 +1,440 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use crate::audio;
+use crate::metadata::sidecar_path;
+
+/// Versão do formato do `{id}.vad.json`, incrementar ao mudar os campos
+pub const VAD_VERSION: u32 = 1;
+/// Energia de uma janela totalmente silenciosa, evita `-inf` no cálculo em dB
+const FLOOR_DB: f32 = -100.0;
+/// Silêncio mínimo para contar como pausa entre um assunto e outro
+pub const PAUSE_SECS: f64 = 1.5;
+/// Percentil das janelas usado como ruído de fundo da gravação
+const NOISE_PERCENTILE: f64 = 0.1;
+
+/// Parâmetros da detecção de fala
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VadConfig {
+    /// Tamanho da janela analisada, em segundos
+    pub frame_secs: f64,
+    /// Quantos dB acima do ruído de fundo uma janela precisa estar para contar como fala
+    pub threshold_db: f32,
+    /// Energia mínima da fala em dBFS, para gravações que são só ruído não virarem fala
+    pub min_db: f32,
+    /// Planicidade espectral máxima da fala, de 0 (tom puro) a 1 (ruído branco)
+    ///
+    /// Chiado e ventilador têm espectro plano e a voz não, então o limite separa os dois mesmo
+    /// quando têm a mesma energia. `None` usa só a energia.
+    pub max_flatness: Option<f32>,
+    /// Falas mais curtas que isso são tratadas como ruído (cliques, estalos)
+    pub min_speech_secs: f64,
+    /// Silêncios mais curtos que isso são tratados como parte da fala (pausas entre palavras)
+    pub min_silence_secs: f64,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            frame_secs: 0.03,
+            threshold_db: 12.0,
+            min_db: -50.0,
+            max_flatness: None,
+            min_speech_secs: 0.25,
+            min_silence_secs: 0.3,
+        }
+    }
+}
+
+/// Trecho contínuo de fala ou de silêncio, em segundos do áudio
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VoiceSegment {
+    pub start: f64,
+    pub end: f64,
+    pub speech: bool,
+}
+
+impl VoiceSegment {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Medidas de uma janela do áudio
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    /// Energia em dBFS
+    pub db: f32,
+    /// Planicidade espectral, só calculada quando pedida
+    pub flatness: Option<f32>,
+}
+
+/// Janelas medidas de um arquivo
+#[derive(Clone, Debug, Default)]
+pub struct Analysis {
+    pub frames: Vec<Frame>,
+    pub sample_rate: u32,
+    /// Duração real de cada janela, `frame_secs` arredondado para amostras inteiras
+    pub frame_secs: f64,
+}
+
+/// Resultado da detecção, salvo ao lado do áudio como `{id}.vad.json`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct VoiceActivity {
+    pub version: u32,
+    /// Tamanho do áudio analisado, para perceber se ele mudou
+    pub source_len: u64,
+    pub config: VadConfig,
+    pub segments: Vec<VoiceSegment>,
+}
+
+impl VoiceActivity {
+    pub fn load(folder: &str, media: &str) -> Option<Self> {
+        let content = fs::read_to_string(sidecar_path(folder, media, "vad")).ok()?;
+        serde_json::from_str::<Self>(&content)
+            .ok()
+            .filter(|activity| activity.version == VAD_VERSION)
+    }
+
+    pub fn save(&self, folder: &str, media: &str) -> Result<(), String> {
+        let content = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize voice activity: {}", e))?;
+        fs::write(sidecar_path(folder, media, "vad"), content)
+            .map_err(|e| format!("Failed to write voice activity: {}", e))
+    }
+
+    /// Silêncios com pelo menos `min_secs`, bons pontos para começar um capítulo
+    pub fn pauses(&self, min_secs: f64) -> Vec<VoiceSegment> {
+        self.segments
+            .iter()
+            .filter(|segment| !segment.speech && segment.duration() >= min_secs)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Trechos de fala e de silêncio do áudio, usando os salvos ao lado dele se nem o arquivo
+/// nem `config` mudaram
+pub fn voice_activity(
+    path: &Path,
+    folder: &str,
+    media: &str,
+    config: &VadConfig,
+) -> Result<VoiceActivity, String> {
+    let source_len = fs::metadata(path)
+        .map_err(|e| format!("Failed to read audio file: {}", e))?
+        .len();
+    if let Some(activity) = VoiceActivity::load(folder, media)
+        .filter(|activity| activity.source_len == source_len && activity.config == *config)
+    {
+        return Ok(activity);
+    }
+
+    println!("Detectando fala em {}", path.display());
+    let mut activity = detect(path, config)?;
+    activity.source_len = source_len;
+    activity.save(folder, media)?;
+    Ok(activity)
+}
+
+/// Detecta os trechos de fala e de silêncio de um arquivo de áudio
+pub fn detect(path: &Path, config: &VadConfig) -> Result<VoiceActivity, String> {
+    let analysis = analyze(path, config.frame_secs, config.max_flatness.is_some())?;
+    let speech = smooth(
+        &classify(&analysis.frames, config),
+        config,
+        analysis.frame_secs,
+    );
+    Ok(VoiceActivity {
+        version: VAD_VERSION,
+        source_len: 0,
+        config: config.clone(),
+        segments: segments(&speech, analysis.frame_secs),
+    })
+}
+
+/// Decodifica o arquivo e mede cada janela de `frame_secs`
+///
+/// As janelas têm um número inteiro de amostras, então a duração delas em `Analysis` pode
+/// ser um pouco diferente de `frame_secs` (30 ms a 22,05 kHz são 661 amostras, 29,98 ms).
+pub fn analyze(path: &Path, frame_secs: f64, spectral: bool) -> Result<Analysis, String> {
+    let mut frames = Vec::new();
+    let mut buffer = Vec::new();
+    let mut sample_rate = 0;
+    let mut frame_len = 1;
+    audio::decode_mono(path, |block, rate| {
+        sample_rate = rate;
+        frame_len = frame_samples(rate, frame_secs);
+        for sample in block {
+            buffer.push(*sample);
+            if buffer.len() == frame_len {
+                frames.push(measure(&buffer, spectral));
+                buffer.clear();
+            }
+        }
+    })?;
+    if !buffer.is_empty() {
+        frames.push(measure(&buffer, spectral));
+    }
+    if sample_rate == 0 {
+        return Err("O arquivo não tem áudio".to_string());
+    }
+    Ok(Analysis {
+        frames,
+        sample_rate,
+        frame_secs: frame_len as f64 / sample_rate as f64,
+    })
+}
+
+fn frame_samples(rate: u32, frame_secs: f64) -> usize {
+    ((rate as f64 * frame_secs) as usize).max(1)
+}
+
+/// Marca as janelas com fala, comparando a energia com o ruído de fundo da própria gravação
+pub fn classify(frames: &[Frame], config: &VadConfig) -> Vec<bool> {
+    let threshold = (noise_floor(frames) + config.threshold_db).max(config.min_db);
+    frames
+        .iter()
+        .map(|frame| {
+            frame.db >= threshold
+                && config
+                    .max_flatness
+                    .is_none_or(|max| frame.flatness.is_none_or(|flatness| flatness <= max))
+        })
+        .collect()
+}
+
+/// Preenche as pausas curtas dentro da fala e descarta as falas curtas demais
+///
+/// `frame_secs` é a duração real das janelas, de `Analysis`.
+pub fn smooth(speech: &[bool], config: &VadConfig, frame_secs: f64) -> Vec<bool> {
+    let frames = |secs: f64| (secs / frame_secs).round() as usize;
+    let mut speech = speech.to_vec();
+    // Pausas primeiro, senão as sílabas separadas por elas seriam descartadas como curtas
+    fill_runs(&mut speech, false, frames(config.min_silence_secs), true);
+    fill_runs(&mut speech, true, frames(config.min_speech_secs), false);
+    speech
+}
+
+/// Junta as janelas em trechos contínuos de fala e de silêncio
+pub fn segments(speech: &[bool], frame_secs: f64) -> Vec<VoiceSegment> {
+    runs(speech)
+        .into_iter()
+        .map(|(start, end, speech)| VoiceSegment {
+            start: start as f64 * frame_secs,
+            end: end as f64 * frame_secs,
+            speech,
+        })
+        .collect()
+}
+
+/// Sequências de valores iguais, como `(início, fim, valor)`
+fn runs(values: &[bool]) -> Vec<(usize, usize, bool)> {
+    let mut runs: Vec<(usize, usize, bool)> = Vec::new();
+    for (i, value) in values.iter().enumerate() {
+        match runs.last_mut() {
+            Some(run) if run.2 == *value => run.1 = i + 1,
+            _ => runs.push((i, i + 1, *value)),
+        }
+    }
+    runs
+}
+
+/// Troca por `replacement` as sequências internas de `value` menores que `min_len`
+///
+/// Silêncios no começo e no fim ficam como estão, um silêncio curto antes da primeira fala
+/// não deve virar fala.
+fn fill_runs(values: &mut [bool], value: bool, min_len: usize, replacement: bool) {
+    let len = values.len();
+    for (start, end, run_value) in runs(values) {
+        let inner = start > 0 && end < len;
+        if run_value == value && end - start < min_len && (inner || value) {
+            values[start..end].fill(replacement);
+        }
+    }
+}
+
+fn noise_floor(frames: &[Frame]) -> f32 {
+    let mut levels: Vec<f32> = frames.iter().map(|frame| frame.db).collect();
+    if levels.is_empty() {
+        return FLOOR_DB;
+    }
+    levels.sort_by(f32::total_cmp);
+    levels[((levels.len() - 1) as f64 * NOISE_PERCENTILE) as usize]
+}
+
+fn measure(samples: &[f32], spectral: bool) -> Frame {
+    let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+    Frame {
+        db: (10.0 * power.log10()).max(FLOOR_DB),
+        flatness: spectral.then(|| flatness(samples)),
+    }
+}
+
+/// Média geométrica dividida pela média aritmética do espectro de potência
+fn flatness(samples: &[f32]) -> f32 {
+    let size = samples.len().next_power_of_two().max(2);
+    let mut re: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        // Janela de Hann, reduz o vazamento entre as frequências
+        .map(|(i, s)| s * 0.5 * (1.0 - (2.0 * PI * i as f32 / samples.len() as f32).cos()))
+        .chain(std::iter::repeat(0.0))
+        .take(size)
+        .collect();
+    let mut im = vec![0.0; size];
+    audio::fft(&mut re, &mut im);
+
+    // Ignora o nível DC, que não diz nada sobre o timbre
+    let power: Vec<f64> = (1..size / 2)
+        .map(|k| (re[k] as f64).powi(2) + (im[k] as f64).powi(2) + 1e-12)
+        .collect();
+    if power.is_empty() {
+        return 1.0;
+    }
+    let count = power.len() as f64;
+    let geometric = (power.iter().map(|p| p.ln()).sum::<f64>() / count).exp();
+    let arithmetic = power.iter().sum::<f64>() / count;
+    (geometric / arithmetic) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::WavWriter;
+    use crate::test_support::temp_dir;
+
+    /// Gerador simples de ruído, para os testes não dependerem de `rand`
+    fn noise(seed: &mut u32) -> f32 {
+        *seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (*seed >> 8) as f32 / (1 << 23) as f32 - 1.0
+    }
+
+    #[test]
+    fn test_smooth() {
+        let config = VadConfig {
+            frame_secs: 0.1,
+            min_speech_secs: 0.3,
+            min_silence_secs: 0.3,
+            ..Default::default()
+        };
+        // Pausa curta dentro da fala, estalo isolado e silêncio curto no começo
+        let speech = [
+            false, true, true, false, true, true, false, false, false, false, true, false, false,
+        ];
+        assert_eq!(
+            smooth(&speech, &config, 0.1),
+            [
+                false, true, true, true, true, true, false, false, false, false, false, false,
+                false
+            ]
+        );
+
+        let segments = segments(&smooth(&speech, &config, 0.1), 0.1);
+        assert_eq!(segments.len(), 3);
+        assert!(segments[1].speech);
+        assert!((segments[1].start - 0.1).abs() < 1e-9);
+        assert!((segments[1].end - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_frame_duration() {
+        let temp = temp_dir("vad-rate");
+        let dir = temp.path();
+        let path = dir.join("fala.wav");
+
+        // 30 ms a 22,05 kHz não são um número inteiro de amostras, o tom começa aos 60 s
+        let rate = 22050;
+        let samples: Vec<f32> = (0..rate * 65)
+            .map(|i| {
+                let t = i as f32 / rate as f32;
+                if t >= 60.0 {
+                    0.5 * (2.0 * PI * 220.0 * t).sin()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let mut writer = WavWriter::create(&path, rate).unwrap();
+        writer.write(&samples).unwrap();
+        writer.finish().unwrap();
+
+        let analysis = analyze(&path, 0.03, false).unwrap();
+        assert_eq!(analysis.frame_secs, 661.0 / 22050.0);
+        let activity = detect(&path, &VadConfig::default()).unwrap();
+        let speech: Vec<_> = activity.segments.iter().filter(|s| s.speech).collect();
+        assert_eq!(speech.len(), 1, "{:?}", activity.segments);
+        assert!((speech[0].start - 60.0).abs() < 0.05, "{:?}", speech);
+        assert!((speech[0].end - 65.0).abs() < 0.05, "{:?}", speech);
+    }
+
+    #[test]
+    fn test_flatness() {
+        let tone: Vec<f32> = (0..512)
+            .map(|i| (2.0 * PI * 440.0 * i as f32 / 16000.0).sin())
+            .collect();
+        let mut seed = 7;
+        let hiss: Vec<f32> = (0..512).map(|_| noise(&mut seed)).collect();
+        assert!(flatness(&tone) < 0.1, "{}", flatness(&tone));
+        assert!(flatness(&hiss) > 0.3, "{}", flatness(&hiss));
+    }
+
+    #[test]
+    fn test_detect() {
+        let temp = temp_dir("vad");
+        let dir = temp.path();
+        let path = dir.join("fala.wav");
+
+        // 1s de chiado baixo, 2s de "voz" (tom), 1s de chiado e 1s de chiado alto
+        let rate = 16000;
+        let mut seed = 1;
+        let samples: Vec<f32> = (0..rate * 5)
+            .map(|i| {
+                let t = i as f32 / rate as f32;
+                match t {
+                    t if (1.0..3.0).contains(&t) => 0.5 * (2.0 * PI * 220.0 * t).sin(),
+                    t if t >= 4.0 => 0.3 * noise(&mut seed),
+                    _ => 0.005 * noise(&mut seed),
+                }
+            })
+            .collect();
+        let mut writer = WavWriter::create(&path, rate).unwrap();
+        writer.write(&samples).unwrap();
+        writer.finish().unwrap();
+
+        // Só pela energia, o chiado alto também é fala
+        let activity = detect(&path, &VadConfig::default()).unwrap();
+        let speech: Vec<_> = activity.segments.iter().filter(|s| s.speech).collect();
+        assert_eq!(speech.len(), 2, "{:?}", activity.segments);
+        assert!((speech[0].start - 1.0).abs() < 0.05, "{:?}", speech);
+        assert!((speech[0].end - 3.0).abs() < 0.05, "{:?}", speech);
+
+        // Com o limite espectral, só o tom sobra
+        let config = VadConfig {
+            max_flatness: Some(0.3),
+            ..Default::default()
+        };
+        let activity = detect(&path, &config).unwrap();
+        let speech: Vec<_> = activity.segments.iter().filter(|s| s.speech).collect();
+        assert_eq!(speech.len(), 1, "{:?}", activity.segments);
+        assert_eq!(activity.pauses(0.5).len(), 2);
+
+        let folder = dir.to_str().unwrap();
+        let cached = voice_activity(&path, folder, "fala", &config).unwrap();
+        assert_eq!(cached.segments, activity.segments);
+        let loaded = VoiceActivity::load(folder, "fala").unwrap();
+        assert_eq!(loaded.segments, activity.segments);
+        assert_eq!(loaded.config, config);
+        assert_eq!(loaded.source_len, fs::metadata(&path).unwrap().len());
+
+        // Outro áudio com o mesmo nome é analisado de novo
+        let mut writer = WavWriter::create(&path, rate).unwrap();
+        writer.write(&samples[..rate as usize * 2]).unwrap();
+        writer.finish().unwrap();
+        let shorter = voice_activity(&path, folder, "fala", &config).unwrap();
+        assert!((shorter.segments.last().unwrap().end - 2.0).abs() < 0.05);
+    }
+}
@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::audio;
+use crate::metadata::sidecar_path;
+
+/// Versão do formato do `{id}.peaks.json`, incrementar ao mudar os campos
+pub const PEAKS_VERSION: u32 = 2;
+
+/// Duração de cada pico do nível mais detalhado, arredondada para amostras inteiras
+const BASE_BUCKET_SECS: f64 = 0.05;
+/// Os níveis dobram de tamanho até o mais grosso ter no máximo essa quantidade de picos
+const MIN_LEVEL_BUCKETS: usize = 512;
+/// Limite de picos por requisição, o suficiente para qualquer tela
+const MAX_BUCKETS: usize = 16384;
+
+/// Um nível de resolução, com os valores em `i16` para o cache ficar pequeno
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeakLevel {
+    pub bucket_secs: f64,
+    pub min: Vec<i16>,
+    pub max: Vec<i16>,
+    pub rms: Vec<i16>,
+}
+
+/// Picos do áudio inteiro em várias resoluções, salvos como `{id}.peaks.json`
+///
+/// Cada nível tem a metade dos picos do anterior, então qualquer zoom é montado a partir de
+/// um nível com pouco mais picos do que a tela precisa, sem decodificar o áudio de novo.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeakCache {
+    pub version: u32,
+    /// Tamanho do áudio quando os picos foram calculados, para perceber se ele mudou
+    pub source_len: u64,
+    pub duration: f64,
+    pub levels: Vec<PeakLevel>,
+}
+
+/// Picos de um trecho, prontos para desenhar
+#[derive(Clone, Debug, Serialize)]
+pub struct Peaks {
+    pub start: f64,
+    pub end: f64,
+    pub bucket_secs: f64,
+    pub min: Vec<f32>,
+    pub max: Vec<f32>,
+    pub rms: Vec<f32>,
+}
+
+/// Picos de `buckets` pedaços do trecho entre `start` e `end` (o áudio inteiro por padrão)
+///
+/// Usa o cache salvo ao lado do áudio, calculando e salvando se ainda não existir.
+pub fn peaks(
+    path: &Path,
+    folder: &str,
+    media: &str,
+    buckets: usize,
+    start: Option<f64>,
+    end: Option<f64>,
+) -> Result<Peaks, String> {
+    let source_len = fs::metadata(path)
+        .map_err(|e| format!("Failed to read audio file: {}", e))?
+        .len();
+    let cache = match PeakCache::load(folder, media).filter(|cache| cache.source_len == source_len)
+    {
+        Some(cache) => cache,
+        None => {
+            println!("Calculando a forma de onda de {}", path.display());
+            let cache = PeakCache::build(path, source_len)?;
+            cache.save(folder, media)?;
+            cache
+        }
+    };
+
+    let start = start.unwrap_or(0.0).clamp(0.0, cache.duration);
+    let end = end.unwrap_or(cache.duration).clamp(start, cache.duration);
+    Ok(cache.peaks(buckets, start, end))
+}
+
+impl PeakCache {
+    /// Decodifica o áudio e calcula todos os níveis
+    pub fn build(path: &Path, source_len: u64) -> Result<Self, String> {
+        let mut base = LevelBuilder::default();
+        let mut bucket = Bucket::default();
+        let mut sample_rate = 0;
+        let mut samples = 0u64;
+        let mut bucket_len = 1;
+        audio::decode_mono(path, |block, rate| {
+            sample_rate = rate;
+            samples += block.len() as u64;
+            bucket_len = ((rate as f64 * BASE_BUCKET_SECS) as usize).max(1);
+            for sample in block {
+                bucket.add(*sample);
+                if bucket.count == bucket_len {
+                    base.push(&bucket);
+                    bucket = Bucket::default();
+                }
+            }
+        })?;
+        if sample_rate == 0 {
+            return Err("O arquivo não tem áudio".to_string());
+        }
+        if bucket.count > 0 {
+            base.push(&bucket);
+        }
+
+        // A 22,05 kHz são 1102 amostras, 49,98 ms, e não os 50 ms nominais
+        let mut levels = vec![base.finish(bucket_len as f64 / sample_rate as f64)];
+        while let Some(level) = levels.last().filter(|l| l.min.len() > MIN_LEVEL_BUCKETS) {
+            let coarser = level.halve();
+            levels.push(coarser);
+        }
+
+        Ok(Self {
+            version: PEAKS_VERSION,
+            source_len,
+            duration: samples as f64 / sample_rate as f64,
+            levels,
+        })
+    }
+
+    pub fn load(folder: &str, media: &str) -> Option<Self> {
+        let content = fs::read_to_string(sidecar_path(folder, media, "peaks")).ok()?;
+        serde_json::from_str::<Self>(&content)
+            .ok()
+            .filter(|cache| cache.version == PEAKS_VERSION)
+    }
+
+    pub fn save(&self, folder: &str, media: &str) -> Result<(), String> {
+        let content =
+            serde_json::to_string(self).map_err(|e| format!("Failed to serialize peaks: {}", e))?;
+        fs::write(sidecar_path(folder, media, "peaks"), content)
+            .map_err(|e| format!("Failed to write peaks: {}", e))
+    }
+
+    /// Monta `buckets` picos do trecho a partir do nível mais grosso que ainda tem detalhe
+    /// suficiente
+    pub fn peaks(&self, buckets: usize, start: f64, end: f64) -> Peaks {
+        let buckets = buckets.clamp(1, MAX_BUCKETS);
+        let span = (end - start).max(0.0);
+        let level = self
+            .levels
+            .iter()
+            .rev()
+            .find(|level| span / level.bucket_secs >= buckets as f64)
+            .or(self.levels.first());
+        let bucket_secs = span / buckets as f64;
+
+        let mut peaks = Peaks {
+            start,
+            end,
+            bucket_secs,
+            min: Vec::with_capacity(buckets),
+            max: Vec::with_capacity(buckets),
+            rms: Vec::with_capacity(buckets),
+        };
+        let Some(level) = level.filter(|level| !level.min.is_empty()) else {
+            return peaks;
+        };
+
+        let len = level.min.len();
+        for i in 0..buckets {
+            let from = start + i as f64 * bucket_secs;
+            let first = ((from / level.bucket_secs) as usize).min(len - 1);
+            let last =
+                (((from + bucket_secs) / level.bucket_secs).ceil() as usize).clamp(first + 1, len);
+
+            let range = first..last;
+            let min = level.min[range.clone()].iter().min().copied().unwrap_or(0);
+            let max = level.max[range.clone()].iter().max().copied().unwrap_or(0);
+            let power = level.rms[range.clone()]
+                .iter()
+                .map(|rms| (from_i16(*rms) as f64).powi(2))
+                .sum::<f64>()
+                / range.len() as f64;
+            peaks.min.push(from_i16(min));
+            peaks.max.push(from_i16(max));
+            peaks.rms.push(power.sqrt() as f32);
+        }
+        peaks
+    }
+}
+
+impl PeakLevel {
+    /// Nível com metade dos picos, juntando cada par
+    fn halve(&self) -> Self {
+        let mut level = LevelBuilder::default();
+        for i in (0..self.min.len()).step_by(2) {
+            let pair = i..(i + 2).min(self.min.len());
+            let power = self.rms[pair.clone()]
+                .iter()
+                .map(|rms| (*rms as f64).powi(2))
+                .sum::<f64>()
+                / pair.len() as f64;
+            level
+                .min
+                .push(*self.min[pair.clone()].iter().min().unwrap_or(&0));
+            level
+                .max
+                .push(*self.max[pair.clone()].iter().max().unwrap_or(&0));
+            level.rms.push(power.sqrt().round() as i16);
+        }
+        level.finish(self.bucket_secs * 2.0)
+    }
+}
+
+#[derive(Default)]
+struct LevelBuilder {
+    min: Vec<i16>,
+    max: Vec<i16>,
+    rms: Vec<i16>,
+}
+
+impl LevelBuilder {
+    fn push(&mut self, bucket: &Bucket) {
+        self.min.push(to_i16(bucket.min));
+        self.max.push(to_i16(bucket.max));
+        self.rms.push(to_i16(
+            (bucket.sum_squares / bucket.count.max(1) as f64).sqrt() as f32,
+        ));
+    }
+
+    fn finish(self, bucket_secs: f64) -> PeakLevel {
+        PeakLevel {
+            bucket_secs,
+            min: self.min,
+            max: self.max,
+            rms: self.rms,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Bucket {
+    min: f32,
+    max: f32,
+    sum_squares: f64,
+    count: usize,
+}
+
+impl Bucket {
+    fn add(&mut self, sample: f32) {
+        if self.count == 0 {
+            (self.min, self.max) = (sample, sample);
+        }
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.sum_squares += (sample as f64).powi(2);
+        self.count += 1;
+    }
+}
+
+fn to_i16(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+fn from_i16(value: i16) -> f32 {
+    value as f32 / i16::MAX as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::WavWriter;
+    use crate::test_support::temp_dir;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_peaks() {
+        let temp = temp_dir("waveform");
+        let dir = temp.path();
+        let folder = dir.to_str().unwrap();
+        let path = dir.join("onda.wav");
+
+        // 30s de silêncio seguidos de 30s de um tom com amplitude 0.5
+        let rate = 8000;
+        let samples: Vec<f32> = (0..rate * 60)
+            .map(|i| match i / rate {
+                0..=29 => 0.0,
+                _ => 0.5 * (2.0 * PI * 100.0 * i as f32 / rate as f32).sin(),
+            })
+            .collect();
+        let mut writer = WavWriter::create(&path, rate).unwrap();
+        writer.write(&samples).unwrap();
+        writer.finish().unwrap();
+
+        let whole = peaks(&path, folder, "onda", 2, None, None).unwrap();
+        assert_eq!(whole.max.len(), 2);
+        assert_eq!((whole.start, whole.end), (0.0, 60.0));
+        assert_eq!(whole.max[0], 0.0);
+        assert!((whole.max[1] - 0.5).abs() < 0.01, "{:?}", whole);
+        assert!((whole.min[1] + 0.5).abs() < 0.01, "{:?}", whole);
+        assert!(
+            (whole.rms[1] - 0.5 / 2f32.sqrt()).abs() < 0.01,
+            "{:?}",
+            whole
+        );
+
+        let cache = PeakCache::load(folder, "onda").unwrap();
+        assert_eq!(cache.levels[0].min.len(), 1200);
+        assert!(cache.levels.last().unwrap().min.len() <= MIN_LEVEL_BUCKETS);
+        assert!(cache
+            .levels
+            .windows(2)
+            .all(|pair| pair[1].bucket_secs == pair[0].bucket_secs * 2.0));
+
+        // Zoom na transição, montado do cache
+        let zoom = peaks(&path, folder, "onda", 100, Some(29.0), Some(31.0)).unwrap();
+        assert_eq!(zoom.max.len(), 100);
+        assert!((zoom.bucket_secs - 0.02).abs() < 1e-9);
+        assert_eq!(zoom.max[..45].iter().sum::<f32>(), 0.0);
+        assert!(zoom.max[55..].iter().all(|max| *max > 0.4), "{:?}", zoom);
+
+        // Um áudio diferente com o mesmo nome invalida o cache
+        let mut writer = WavWriter::create(&path, rate).unwrap();
+        writer.write(&samples[..rate as usize * 10]).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            peaks(&path, folder, "onda", 10, None, None).unwrap().end,
+            10.0
+        );
+
+        // A 22,05 kHz os picos têm um número inteiro de amostras e acompanham a duração
+        let rate = 22050;
+        let path = dir.join("podcast.wav");
+        let mut writer = WavWriter::create(&path, rate).unwrap();
+        writer.write(&vec![0.1; rate as usize * 600]).unwrap();
+        writer.finish().unwrap();
+        peaks(&path, folder, "podcast", 10, None, None).unwrap();
+        let cache = PeakCache::load(folder, "podcast").unwrap();
+        let base = &cache.levels[0];
+        assert_eq!(base.bucket_secs, 1102.0 / 22050.0);
+        assert!(
+            (cache.duration - base.min.len() as f64 * base.bucket_secs).abs() < base.bucket_secs,
+            "{} {}",
+            cache.duration,
+            base.min.len()
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use serde::Serialize;
+use url::Url;
+
+/// IDs de vídeo do YouTube sempre têm 11 caracteres
+const VIDEO_ID_LEN: usize = 11;
+
+/// Domínios que servem as mesmas páginas do youtube.com
+const YOUTUBE_HOSTS: [&str; 6] = [
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+    "youtube-nocookie.com",
+    "www.youtube-nocookie.com",
+];
+
+/// Formato do link, ajuda a interface a explicar o que vai ser baixado
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlKind {
+    /// `youtube.com/watch?v=`
+    Watch,
+    /// `youtu.be/`
+    Share,
+    /// `youtube.com/shorts/`
+    Shorts,
+    /// `youtube.com/live/`
+    Live,
+    /// `youtube.com/embed/` e o antigo `youtube.com/v/`
+    Embed,
+    /// `youtube.com/playlist?list=`, sem vídeo
+    Playlist,
+}
+
+/// Link do YouTube já interpretado
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct YoutubeUrl {
+    pub kind: UrlKind,
+    pub video_id: Option<String>,
+    pub playlist_id: Option<String>,
+    /// Posição inicial pedida no link (`t=` ou `start=`), em segundos
+    pub start_time: Option<u64>,
+}
+
+impl YoutubeUrl {
+    /// Interpreta um link do YouTube, com ou sem `https://`
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let url = Url::parse(input)
+            .or_else(|_| Url::parse(&format!("https://{}", input)))
+            .map_err(|_| format!("URL inválida: {}", input))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("URL inválida: {}", input));
+        }
+
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+        let segments: Vec<&str> = url
+            .path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        let query = |key: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.into_owned())
+                .filter(|v| !v.is_empty())
+        };
+
+        let (kind, video_id) = if host == "youtu.be" {
+            (UrlKind::Share, segments.first().map(|s| s.to_string()))
+        } else if YOUTUBE_HOSTS.contains(&host.as_str()) {
+            match segments.as_slice() {
+                ["watch"] => (UrlKind::Watch, query("v")),
+                ["playlist"] => (UrlKind::Playlist, None),
+                ["shorts", id] => (UrlKind::Shorts, Some(id.to_string())),
+                ["live", id] => (UrlKind::Live, Some(id.to_string())),
+                ["embed" | "v" | "e", id] => (UrlKind::Embed, Some(id.to_string())),
+                _ => return Err(format!("Link do YouTube não suportado: {}", input)),
+            }
+        } else {
+            return Err(format!("Não é um link do YouTube: {}", input));
+        };
+
+        if let Some(id) = &video_id {
+            if !is_video_id(id) {
+                return Err(format!("ID de vídeo inválido: {}", id));
+            }
+        }
+        let playlist_id = query("list").filter(|id| is_playlist_id(id));
+
+        let (kind, video_id) = match (kind, video_id) {
+            (UrlKind::Playlist, _) | (UrlKind::Watch, None) if playlist_id.is_some() => {
+                (UrlKind::Playlist, None)
+            }
+            (_, None) => return Err(format!("Link sem ID de vídeo: {}", input)),
+            (kind, video_id) => (kind, video_id),
+        };
+
+        // O `t=` também aparece no fragmento em links antigos (`#t=1m30s`)
+        let start_time = query("t")
+            .or_else(|| query("start"))
+            .or_else(|| {
+                url.fragment()
+                    .and_then(|f| f.strip_prefix("t="))
+                    .map(String::from)
+            })
+            .and_then(|t| parse_start_time(&t));
+
+        Ok(Self {
+            kind,
+            video_id,
+            playlist_id,
+            start_time,
+        })
+    }
+
+    /// ID do vídeo, erro quando o link é de uma playlist
+    pub fn require_video_id(&self) -> Result<&str, String> {
+        self.video_id
+            .as_deref()
+            .ok_or_else(|| "O link aponta para uma playlist, não para um vídeo".to_string())
+    }
+
+    /// Link canônico apenas do vídeo, sem playlist nem posição inicial
+    ///
+    /// É o que deve ser passado ao yt-dlp, senão um `list=` faz ele baixar a playlist inteira.
+    pub fn watch_url(&self) -> Result<String, String> {
+        self.require_video_id()
+            .map(|id| format!("https://www.youtube.com/watch?v={}", id))
+    }
+
+    /// Link canônico preservando playlist e posição inicial
+    pub fn canonical_url(&self) -> String {
+        let mut url = match (&self.video_id, &self.playlist_id) {
+            (Some(id), _) => format!("https://www.youtube.com/watch?v={}", id),
+            (None, Some(list)) => return format!("https://www.youtube.com/playlist?list={}", list),
+            (None, None) => return "https://www.youtube.com/".to_string(),
+        };
+        if let Some(list) = &self.playlist_id {
+            url.push_str(&format!("&list={}", list));
+        }
+        if let Some(t) = self.start_time.filter(|t| *t > 0) {
+            url.push_str(&format!("&t={}s", t));
+        }
+        url
+    }
+}
+
+/// Normaliza qualquer link do YouTube para o formato canônico `watch?v=`
+pub fn normalize(input: &str) -> Result<String, String> {
+    YoutubeUrl::parse(input).map(|url| url.canonical_url())
+}
+
+fn is_video_id(id: &str) -> bool {
+    id.len() == VIDEO_ID_LEN && id.chars().all(is_id_char)
+}
+
+fn is_playlist_id(id: &str) -> bool {
+    id.chars().all(is_id_char)
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_')
+}
+
+/// Aceita `90`, `90s`, `1m30s` e `1h2m3s`
+fn parse_start_time(value: &str) -> Option<u64> {
+    if let Ok(secs) = value.parse() {
+        return Some(secs);
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' => {
+                let unit = match c {
+                    'h' => 3600,
+                    'm' => 60,
+                    _ => 1,
+                };
+                // Valores absurdos (`99999999999999999h`) não cabem em um u64
+                let secs = number.parse::<u64>().ok()?.checked_mul(unit)?;
+                total = total.checked_add(secs)?;
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+    number.is_empty().then_some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "dQw4w9WgXcQ";
+    const LIST: &str = "PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI";
+
+    /// (entrada, tipo, vídeo, playlist, início)
+    type Case = (
+        &'static str,
+        UrlKind,
+        Option<&'static str>,
+        Option<&'static str>,
+        Option<u64>,
+    );
+
+    #[test]
+    fn test_parse() {
+        let cases: Vec<Case> = vec![
+            // watch?v=
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ", UrlKind::Watch, Some(ID), None, None),
+            ("http://youtube.com/watch?v=dQw4w9WgXcQ", UrlKind::Watch, Some(ID), None, None),
+            ("www.youtube.com/watch?v=dQw4w9WgXcQ", UrlKind::Watch, Some(ID), None, None),
+            ("youtube.com/watch?v=dQw4w9WgXcQ", UrlKind::Watch, Some(ID), None, None),
+            ("  https://www.youtube.com/watch?v=dQw4w9WgXcQ\n", UrlKind::Watch, Some(ID), None, None),
+            ("https://WWW.YOUTUBE.COM/watch?v=dQw4w9WgXcQ", UrlKind::Watch, Some(ID), None, None),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&feature=share", UrlKind::Watch, Some(ID), None, None),
+            ("https://www.youtube.com/watch?feature=share&v=dQw4w9WgXcQ", UrlKind::Watch, Some(ID), None, None),
+            ("https://www.youtube.com/watch/?v=dQw4w9WgXcQ", UrlKind::Watch, Some(ID), None, None),
+            ("https://m.youtube.com/watch?v=dQw4w9WgXcQ", UrlKind::Watch, Some(ID), None, None),
+            ("https://music.youtube.com/watch?v=dQw4w9WgXcQ&si=abc", UrlKind::Watch, Some(ID), None, None),
+            // Posição inicial
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42", UrlKind::Watch, Some(ID), None, Some(42)),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42s", UrlKind::Watch, Some(ID), None, Some(42)),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1m30s", UrlKind::Watch, Some(ID), None, Some(90)),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1h2m3s", UrlKind::Watch, Some(ID), None, Some(3723)),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ#t=2m", UrlKind::Watch, Some(ID), None, Some(120)),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=abc", UrlKind::Watch, Some(ID), None, None),
+            // Playlists
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI&index=3", UrlKind::Watch, Some(ID), Some(LIST), None),
+            ("https://www.youtube.com/playlist?list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI", UrlKind::Playlist, None, Some(LIST), None),
+            ("https://music.youtube.com/playlist?list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI", UrlKind::Playlist, None, Some(LIST), None),
+            ("https://www.youtube.com/watch?list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI", UrlKind::Playlist, None, Some(LIST), None),
+            // youtu.be
+            ("https://youtu.be/dQw4w9WgXcQ", UrlKind::Share, Some(ID), None, None),
+            ("youtu.be/dQw4w9WgXcQ", UrlKind::Share, Some(ID), None, None),
+            ("https://youtu.be/dQw4w9WgXcQ?t=123", UrlKind::Share, Some(ID), None, Some(123)),
+            ("https://youtu.be/dQw4w9WgXcQ?si=Xb2Q8q&list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI", UrlKind::Share, Some(ID), Some(LIST), None),
+            // Shorts, lives e embeds
+            ("https://youtube.com/shorts/dQw4w9WgXcQ", UrlKind::Shorts, Some(ID), None, None),
+            ("https://www.youtube.com/shorts/dQw4w9WgXcQ?feature=share", UrlKind::Shorts, Some(ID), None, None),
+            ("https://www.youtube.com/live/dQw4w9WgXcQ?si=abc", UrlKind::Live, Some(ID), None, None),
+            ("https://m.youtube.com/live/dQw4w9WgXcQ", UrlKind::Live, Some(ID), None, None),
+            ("https://www.youtube.com/embed/dQw4w9WgXcQ", UrlKind::Embed, Some(ID), None, None),
+            ("https://www.youtube.com/embed/dQw4w9WgXcQ?start=30", UrlKind::Embed, Some(ID), None, Some(30)),
+            ("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ", UrlKind::Embed, Some(ID), None, None),
+            ("https://www.youtube.com/v/dQw4w9WgXcQ", UrlKind::Embed, Some(ID), None, None),
+        ];
+
+        for (input, kind, video_id, playlist_id, start_time) in cases {
+            let url = YoutubeUrl::parse(input)
+                .unwrap_or_else(|e| panic!("Falha ao interpretar '{}': {}", input, e));
+            assert_eq!(url.kind, kind, "tipo de '{}'", input);
+            assert_eq!(url.video_id.as_deref(), video_id, "vídeo de '{}'", input);
+            assert_eq!(
+                url.playlist_id.as_deref(),
+                playlist_id,
+                "playlist de '{}'",
+                input
+            );
+            assert_eq!(url.start_time, start_time, "início de '{}'", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects() {
+        let cases = [
+            "",
+            "dQw4w9WgXcQ",
+            "https://youtube.com/invalid",
+            "https://www.youtube.com/",
+            "https://www.youtube.com/watch",
+            "https://www.youtube.com/watch?v=",
+            "https://youtu.be/",
+            "https://youtu.be/abc123",
+            "https://youtu.be/abc123456789",
+            "https://www.youtube.com/watch?v=dQw4w9WgXc!",
+            "https://www.youtube.com/playlist",
+            "https://www.youtube.com/channel/UCuAXFkgsw1L7xaCfnd5JJOw",
+            "https://www.youtube.com/@RickAstleyYT",
+            "https://vimeo.com/76979871",
+            "https://notyoutube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtube.com.evil.com/watch?v=dQw4w9WgXcQ",
+            "ftp://youtube.com/watch?v=dQw4w9WgXcQ",
+            // O ID antigo casava qualquer trecho de 11 caracteres
+            "https://example.com/abcdefghijk",
+        ];
+
+        for input in cases {
+            assert!(
+                YoutubeUrl::parse(input).is_err(),
+                "'{}' deveria ser rejeitada",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let cases = [
+            ("https://youtu.be/dQw4w9WgXcQ?si=abc", "https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            ("https://m.youtube.com/shorts/dQw4w9WgXcQ", "https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            ("https://music.youtube.com/watch?v=dQw4w9WgXcQ&feature=share", "https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            ("https://www.youtube.com/live/dQw4w9WgXcQ", "https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            ("https://www.youtube.com/embed/dQw4w9WgXcQ?start=0", "https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            ("https://youtu.be/dQw4w9WgXcQ?t=1m30s", "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=90s"),
+            (
+                "https://www.youtube.com/watch?index=3&list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI&v=dQw4w9WgXcQ&t=5",
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI&t=5s",
+            ),
+            (
+                "music.youtube.com/playlist?list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI",
+                "https://www.youtube.com/playlist?list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI",
+            ),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                normalize(input).unwrap(),
+                expected,
+                "normalizando '{}'",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_watch_url() {
+        let url = YoutubeUrl::parse(
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI",
+        )
+        .unwrap();
+        assert_eq!(
+            url.watch_url().unwrap(),
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ"
+        );
+        assert!(YoutubeUrl::parse(
+            "https://www.youtube.com/playlist?list=PLFgquLnL59alCl_2TQvOiD5Vgm1hCaGSI"
+        )
+        .unwrap()
+        .watch_url()
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_start_time() {
+        assert_eq!(parse_start_time("0"), Some(0));
+        assert_eq!(parse_start_time("75"), Some(75));
+        assert_eq!(parse_start_time("2h"), Some(7200));
+        assert_eq!(parse_start_time("1m5s"), Some(65));
+        assert_eq!(parse_start_time("1m5"), None);
+        assert_eq!(parse_start_time("-5"), None);
+        assert_eq!(parse_start_time("1.5"), None);
+        assert_eq!(parse_start_time("99999999999999999h"), None);
+        assert_eq!(parse_start_time("18446744073709551615s1s"), None);
+        assert_eq!(parse_start_time("99999999999999999999"), None);
+    }
+}
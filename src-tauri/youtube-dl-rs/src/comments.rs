@@ -0,0 +1,155 @@
+//! Helpers to turn the flat comment list of yt-dlp into reply threads.
+
+use crate::model::{Comment, SingleVideo};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// The `parent` value yt-dlp uses for top-level comments.
+const ROOT_PARENT: &str = "root";
+
+/// How comments and their replies are ordered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentOrder {
+    /// Oldest first
+    Timestamp,
+    /// Most liked first
+    Likes,
+}
+
+/// A comment together with its (recursively threaded) replies.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommentThread {
+    /// The comment itself
+    pub comment: Comment,
+    /// Replies to this comment, ordered like their parent level
+    pub replies: Vec<CommentThread>,
+}
+
+impl CommentThread {
+    /// Number of comments in this thread, including the comment itself.
+    pub fn count(&self) -> usize {
+        1 + self.replies.iter().map(CommentThread::count).sum::<usize>()
+    }
+}
+
+/// Builds the reply tree from `Comment::parent`. Comments whose parent is missing from the
+/// list are treated as top-level comments. Pinned comments always come first at the top level.
+pub fn build_comment_tree(comments: &[Comment], order: CommentOrder) -> Vec<CommentThread> {
+    let ids: HashSet<&str> = comments.iter().filter_map(|c| c.id.as_deref()).collect();
+    let mut children: HashMap<&str, Vec<&Comment>> = HashMap::new();
+    let mut roots = Vec::new();
+
+    for comment in comments {
+        match comment.parent.as_deref() {
+            Some(parent) if parent != ROOT_PARENT && ids.contains(&parent) => {
+                children.entry(parent).or_default().push(comment);
+            }
+            _ => roots.push(comment),
+        }
+    }
+
+    let mut threads: Vec<_> = roots
+        .into_iter()
+        .map(|comment| thread(comment, &children, order))
+        .collect();
+    threads.sort_by(|a, b| {
+        pinned(&b.comment)
+            .cmp(&pinned(&a.comment))
+            .then_with(|| compare(&a.comment, &b.comment, order))
+    });
+    threads
+}
+
+fn thread(
+    comment: &Comment,
+    children: &HashMap<&str, Vec<&Comment>>,
+    order: CommentOrder,
+) -> CommentThread {
+    let mut replies: Vec<_> = comment
+        .id
+        .as_deref()
+        .and_then(|id| children.get(id))
+        .into_iter()
+        .flatten()
+        .map(|reply| thread(reply, children, order))
+        .collect();
+    replies.sort_by(|a, b| compare(&a.comment, &b.comment, order));
+
+    CommentThread {
+        comment: comment.clone(),
+        replies,
+    }
+}
+
+fn pinned(comment: &Comment) -> bool {
+    comment.is_pinned.unwrap_or(false)
+}
+
+fn compare(a: &Comment, b: &Comment, order: CommentOrder) -> Ordering {
+    match order {
+        CommentOrder::Timestamp => a
+            .timestamp
+            .unwrap_or(f64::MAX)
+            .total_cmp(&b.timestamp.unwrap_or(f64::MAX)),
+        CommentOrder::Likes => b.like_count.unwrap_or(0).cmp(&a.like_count.unwrap_or(0)),
+    }
+}
+
+impl SingleVideo {
+    /// The comments of this video as reply threads, empty if comments were not fetched
+    /// (see `YoutubeDl::write_comments`).
+    pub fn comment_threads(&self, order: CommentOrder) -> Vec<CommentThread> {
+        self.comments
+            .as_deref()
+            .map(|comments| build_comment_tree(comments, order))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_comment_tree, CommentOrder};
+    use crate::model::Comment;
+
+    fn comment(id: &str, parent: &str, timestamp: f64, likes: i64) -> Comment {
+        Comment {
+            id: Some(id.to_string()),
+            parent: Some(parent.to_string()),
+            timestamp: Some(timestamp),
+            like_count: Some(likes),
+            ..Default::default()
+        }
+    }
+
+    fn ids(threads: &[super::CommentThread]) -> Vec<&str> {
+        threads
+            .iter()
+            .map(|t| t.comment.id.as_deref().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_comment_tree() {
+        let mut pinned = comment("pinned", "root", 50.0, 0);
+        pinned.is_pinned = Some(true);
+        let comments = vec![
+            comment("a", "root", 10.0, 5),
+            comment("a.1", "a", 20.0, 1),
+            comment("b", "root", 5.0, 30),
+            comment("a.2", "a", 15.0, 7),
+            comment("orphan", "missing", 1.0, 2),
+            pinned,
+        ];
+
+        let by_time = build_comment_tree(&comments, CommentOrder::Timestamp);
+        assert_eq!(ids(&by_time), ["pinned", "orphan", "b", "a"]);
+        assert_eq!(ids(&by_time[3].replies), ["a.2", "a.1"]);
+        assert_eq!(by_time[3].count(), 3);
+
+        let by_likes = build_comment_tree(&comments, CommentOrder::Likes);
+        assert_eq!(ids(&by_likes), ["pinned", "b", "a", "orphan"]);
+        assert_eq!(ids(&by_likes[2].replies), ["a.2", "a.1"]);
+    }
+}
@@ -0,0 +1,200 @@
+//! Analysis of the "most replayed" heatmap (`SingleVideo::heatmap`).
+
+use crate::model::{HeatmapSample, SingleVideo};
+use serde::{Deserialize, Serialize};
+
+/// Whether an extremum is a local maximum or minimum of the heatmap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtremumKind {
+    /// Viewers rewatch this part more than its surroundings
+    Peak,
+    /// Viewers skip this part more than its surroundings
+    Valley,
+}
+
+/// A local peak or valley of the (smoothed) heatmap.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Extremum {
+    /// Peak or valley
+    pub kind: ExtremumKind,
+    /// Middle of the sample, in seconds
+    pub time: f64,
+    /// Smoothed heatmap value
+    pub value: f64,
+}
+
+/// A contiguous part of the video that viewers rewatch.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplayRange {
+    /// Start of the range, in seconds
+    pub start_time: f64,
+    /// End of the range, in seconds
+    pub end_time: f64,
+    /// Time of the highest point inside the range, in seconds
+    pub peak_time: f64,
+    /// Mean of the normalized (0 to 1) heatmap values inside the range
+    pub score: f64,
+}
+
+/// Options for `most_replayed`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayOptions {
+    /// Number of samples in the moving average, 1 disables smoothing
+    pub smoothing_window: usize,
+    /// Minimum normalized value (0 to 1) for a sample to be part of a range
+    pub threshold: f64,
+    /// Maximum number of ranges to return
+    pub max_ranges: usize,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            smoothing_window: 5,
+            threshold: 0.5,
+            max_ranges: 5,
+        }
+    }
+}
+
+/// Smooths the samples with a centered moving average of `window` samples.
+pub fn smooth(samples: &[HeatmapSample], window: usize) -> Vec<HeatmapSample> {
+    let radius = window.max(1) / 2;
+    (0..samples.len())
+        .map(|i| {
+            let neighbours =
+                &samples[i.saturating_sub(radius)..(i + radius + 1).min(samples.len())];
+            HeatmapSample {
+                value: neighbours.iter().map(|s| s.value).sum::<f64>() / neighbours.len() as f64,
+                ..samples[i]
+            }
+        })
+        .collect()
+}
+
+/// Finds the local peaks and valleys of the samples, in chronological order. The first
+/// and last samples are never reported.
+pub fn extrema(samples: &[HeatmapSample]) -> Vec<Extremum> {
+    samples
+        .windows(3)
+        .filter_map(|w| {
+            let (previous, current, next) = (w[0].value, w[1].value, w[2].value);
+            let kind = if current > previous && current >= next {
+                ExtremumKind::Peak
+            } else if current < previous && current <= next {
+                ExtremumKind::Valley
+            } else {
+                return None;
+            };
+            Some(Extremum {
+                kind,
+                time: (w[1].start_time + w[1].end_time) / 2.0,
+                value: current,
+            })
+        })
+        .collect()
+}
+
+/// Returns the ranges where the smoothed, normalized heatmap stays above
+/// `options.threshold`, the highest scores first.
+pub fn most_replayed(samples: &[HeatmapSample], options: &ReplayOptions) -> Vec<ReplayRange> {
+    let smoothed = smooth(samples, options.smoothing_window);
+    let (min, max) = smoothed.iter().fold((f64::MAX, f64::MIN), |(min, max), s| {
+        (min.min(s.value), max.max(s.value))
+    });
+    // A flat heatmap has no part that stands out
+    if smoothed.is_empty() || max - min <= f64::EPSILON {
+        return Vec::new();
+    }
+
+    let normalized = |sample: &HeatmapSample| (sample.value - min) / (max - min);
+    let mut ranges: Vec<ReplayRange> = smoothed
+        .split(|sample| normalized(sample) < options.threshold)
+        .filter(|run| !run.is_empty())
+        .map(|run| {
+            let peak = run
+                .iter()
+                .max_by(|a, b| a.value.total_cmp(&b.value))
+                .unwrap_or(&run[0]);
+            ReplayRange {
+                start_time: run[0].start_time,
+                end_time: run[run.len() - 1].end_time,
+                peak_time: (peak.start_time + peak.end_time) / 2.0,
+                score: run.iter().map(normalized).sum::<f64>() / run.len() as f64,
+            }
+        })
+        .collect();
+
+    ranges.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranges.truncate(options.max_ranges);
+    ranges
+}
+
+impl SingleVideo {
+    /// The most replayed ranges of this video, empty if yt-dlp returned no heatmap.
+    pub fn most_replayed(&self, options: &ReplayOptions) -> Vec<ReplayRange> {
+        self.heatmap
+            .as_deref()
+            .map(|samples| most_replayed(samples, options))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extrema, most_replayed, smooth, ExtremumKind, ReplayOptions};
+    use crate::model::HeatmapSample;
+
+    fn samples(values: &[f64]) -> Vec<HeatmapSample> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| HeatmapSample {
+                start_time: i as f64 * 10.0,
+                end_time: (i + 1) as f64 * 10.0,
+                value,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_smooth() {
+        let smoothed = smooth(&samples(&[0.0, 3.0, 0.0, 3.0]), 3);
+        let values: Vec<_> = smoothed.iter().map(|s| s.value).collect();
+        assert_eq!(values, [1.5, 1.0, 2.0, 1.5]);
+        assert_eq!(smoothed[2].start_time, 20.0);
+    }
+
+    #[test]
+    fn test_extrema() {
+        let found = extrema(&samples(&[0.1, 0.5, 0.2, 0.1, 0.4, 0.4, 0.3]));
+        let kinds: Vec<_> = found.iter().map(|e| (e.kind, e.time)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (ExtremumKind::Peak, 15.0),
+                (ExtremumKind::Valley, 35.0),
+                (ExtremumKind::Peak, 45.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_most_replayed() {
+        let heatmap = samples(&[0.1, 0.2, 0.9, 1.0, 0.8, 0.1, 0.1, 0.6, 0.1, 0.0]);
+        let options = ReplayOptions {
+            smoothing_window: 1,
+            ..Default::default()
+        };
+        let ranges = most_replayed(&heatmap, &options);
+        assert_eq!(ranges.len(), 2);
+        assert_eq!((ranges[0].start_time, ranges[0].end_time), (20.0, 50.0));
+        assert_eq!(ranges[0].peak_time, 35.0);
+        assert!((ranges[0].score - 0.9).abs() < 1e-9);
+        assert_eq!((ranges[1].start_time, ranges[1].end_time), (70.0, 80.0));
+
+        assert!(most_replayed(&samples(&[0.5, 0.5, 0.5]), &options).is_empty());
+    }
+}
@@ -35,12 +35,18 @@ use std::time::Duration;
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
+pub mod comments;
 /// Exposes a function to download the latest version of youtube-dl/yt-dlp.
 #[cfg(any(feature = "downloader-rustls-tls", feature = "downloader-native-tls"))]
 pub mod downloader;
+pub mod heatmap;
 pub mod model;
+pub mod thumbnails;
 
+pub use crate::comments::{build_comment_tree, CommentOrder, CommentThread};
+pub use crate::heatmap::{ReplayOptions, ReplayRange};
 pub use crate::model::*;
+pub use crate::thumbnails::best_thumbnail;
 
 #[cfg(any(feature = "downloader-rustls-tls", feature = "downloader-native-tls"))]
 pub use crate::downloader::download_yt_dlp;
@@ -246,6 +252,125 @@ impl fmt::Display for SearchOptions {
     }
 }
 
+/// A time range of a video, in seconds. A missing `end` means "until the end of the video".
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeRange {
+    /// Start of the range
+    pub start: f64,
+    /// End of the range, or `None` to download until the end
+    pub end: Option<f64>,
+}
+
+impl TimeRange {
+    /// Create a range from `start` to `end` seconds.
+    pub fn new(start: f64, end: f64) -> Self {
+        Self {
+            start,
+            end: Some(end),
+        }
+    }
+
+    /// Create a range from `start` seconds until the end of the video.
+    pub fn from_start(start: f64) -> Self {
+        Self { start, end: None }
+    }
+
+    /// Length of the range in seconds, if it has an end.
+    pub fn duration(&self) -> Option<f64> {
+        self.end.map(|end| end - self.start)
+    }
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "*{}-{}", self.start, end),
+            None => write!(f, "*{}-inf", self.start),
+        }
+    }
+}
+
+/// A section to download with the `--download-sections` option.
+#[derive(Clone, Debug)]
+pub enum DownloadSection {
+    /// Download only the given time range
+    Range(TimeRange),
+    /// Download the chapters whose title matches the regular expression
+    Chapters(String),
+}
+
+impl fmt::Display for DownloadSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadSection::Range(range) => write!(f, "{}", range),
+            DownloadSection::Chapters(regex) => write!(f, "{}", regex),
+        }
+    }
+}
+
+impl From<TimeRange> for DownloadSection {
+    fn from(range: TimeRange) -> Self {
+        DownloadSection::Range(range)
+    }
+}
+
+/// Limits for the YouTube comment extraction, passed as the `max_comments` extractor argument.
+/// `None` means no limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MaxComments {
+    /// Maximum number of comments, including replies
+    pub total: Option<u32>,
+    /// Maximum number of top-level comments
+    pub parents: Option<u32>,
+    /// Maximum number of replies, over all threads
+    pub replies: Option<u32>,
+    /// Maximum number of replies per thread
+    pub replies_per_thread: Option<u32>,
+}
+
+impl MaxComments {
+    /// Only limit the total number of comments.
+    pub fn total(total: u32) -> Self {
+        Self {
+            total: Some(total),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for MaxComments {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limits: Vec<String> = [
+            self.total,
+            self.parents,
+            self.replies,
+            self.replies_per_thread,
+        ]
+        .iter()
+        .map(|limit| limit.map_or_else(|| "all".to_string(), |n| n.to_string()))
+        .collect();
+        write!(f, "{}", limits.join(","))
+    }
+}
+
+/// The order YouTube returns comments in, passed as the `comment_sort` extractor argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommentSort {
+    /// Top comments first
+    Top,
+    /// Newest comments first
+    New,
+}
+
+impl fmt::Display for CommentSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommentSort::Top => write!(f, "top"),
+            CommentSort::New => write!(f, "new"),
+        }
+    }
+}
+
 /// A builder to create a `youtube-dl` command to execute.
 #[derive(Clone, Debug)]
 pub struct YoutubeDl {
@@ -271,6 +396,13 @@ pub struct YoutubeDl {
     extra_args: Vec<String>,
     output_template: Option<String>,
     output_directory: Option<String>,
+    wait_for_video: Option<String>,
+    live_from_start: bool,
+    download_sections: Vec<String>,
+    sponsorblock_mark: Option<String>,
+    sponsorblock_remove: Option<String>,
+    write_comments: bool,
+    youtube_extractor_args: Option<String>,
     #[cfg(test)]
     debug: bool,
     ignore_errors: bool,
@@ -302,6 +434,13 @@ impl YoutubeDl {
             extra_args: Vec::new(),
             output_template: None,
             output_directory: None,
+            wait_for_video: None,
+            live_from_start: false,
+            download_sections: Vec::new(),
+            sponsorblock_mark: None,
+            sponsorblock_remove: None,
+            write_comments: false,
+            youtube_extractor_args: None,
             #[cfg(test)]
             debug: false,
             ignore_errors: false,
@@ -470,6 +609,79 @@ impl YoutubeDl {
         self
     }
 
+    /// Set the `--wait-for-video` command line flag. Scheduled streams and premieres are
+    /// re-checked every `min` (or a random interval between `min` and `max`) until they start.
+    pub fn wait_for_video(&mut self, min: Duration, max: Option<Duration>) -> &mut Self {
+        self.wait_for_video = Some(match max {
+            Some(max) => format!("{}-{}", min.as_secs(), max.as_secs()),
+            None => min.as_secs().to_string(),
+        });
+        self
+    }
+
+    /// Set the `--live-from-start` command line flag, downloading livestreams from their
+    /// beginning instead of the current position.
+    pub fn live_from_start(&mut self, live_from_start: bool) -> &mut Self {
+        self.live_from_start = live_from_start;
+        self
+    }
+
+    /// Add a `--download-sections` option. Can be called multiple times to download
+    /// several sections.
+    pub fn download_section<S: Into<DownloadSection>>(&mut self, section: S) -> &mut Self {
+        self.download_sections.push(section.into().to_string());
+        self
+    }
+
+    /// Set the `--sponsorblock-mark` command line option, creating chapters for the
+    /// SponsorBlock segments of the given categories.
+    pub fn sponsorblock_mark<I>(&mut self, categories: I) -> &mut Self
+    where
+        I: IntoIterator<Item = SponsorBlockCategory>,
+    {
+        self.sponsorblock_mark = sponsorblock_categories(categories);
+        self
+    }
+
+    /// Set the `--sponsorblock-remove` command line option, cutting the SponsorBlock
+    /// segments of the given categories out of the downloaded file.
+    pub fn sponsorblock_remove<I>(&mut self, categories: I) -> &mut Self
+    where
+        I: IntoIterator<Item = SponsorBlockCategory>,
+    {
+        self.sponsorblock_remove = sponsorblock_categories(categories);
+        self
+    }
+
+    /// Set the `--write-comments` command line flag. The comments are then included in
+    /// `SingleVideo::comments`.
+    pub fn write_comments(&mut self, write_comments: bool) -> &mut Self {
+        self.write_comments = write_comments;
+        self
+    }
+
+    /// Limit the number of comments fetched from YouTube
+    /// (`--extractor-args youtube:max_comments=...`).
+    pub fn max_comments(&mut self, max_comments: MaxComments) -> &mut Self {
+        self.youtube_extractor_arg(format!("max_comments={}", max_comments))
+    }
+
+    /// Set the order comments are fetched from YouTube in
+    /// (`--extractor-args youtube:comment_sort=...`).
+    pub fn comment_sort(&mut self, comment_sort: CommentSort) -> &mut Self {
+        self.youtube_extractor_arg(format!("comment_sort={}", comment_sort))
+    }
+
+    // yt-dlp only keeps the last `--extractor-args` per extractor, so all YouTube arguments
+    // are joined into a single one.
+    fn youtube_extractor_arg(&mut self, arg: String) -> &mut Self {
+        self.youtube_extractor_args = Some(match self.youtube_extractor_args.take() {
+            Some(args) => format!("{};{}", args, arg),
+            None => format!("youtube:{}", arg),
+        });
+        self
+    }
+
     #[cfg(test)]
     pub fn debug(&mut self, arg: bool) -> &mut Self {
         self.debug = arg;
@@ -575,6 +787,39 @@ impl YoutubeDl {
             args.push(date_before);
         }
 
+        if let Some(wait_for_video) = &self.wait_for_video {
+            args.push("--wait-for-video");
+            args.push(wait_for_video);
+        }
+
+        if self.live_from_start {
+            args.push("--live-from-start");
+        }
+
+        for section in &self.download_sections {
+            args.push("--download-sections");
+            args.push(section);
+        }
+
+        if let Some(categories) = &self.sponsorblock_mark {
+            args.push("--sponsorblock-mark");
+            args.push(categories);
+        }
+
+        if let Some(categories) = &self.sponsorblock_remove {
+            args.push("--sponsorblock-remove");
+            args.push(categories);
+        }
+
+        if self.write_comments {
+            args.push("--write-comments");
+        }
+
+        if let Some(extractor_args) = &self.youtube_extractor_args {
+            args.push("--extractor-args");
+            args.push(extractor_args);
+        }
+
         if self.ignore_errors {
             args.push("--ignore-errors");
         }
@@ -931,6 +1176,22 @@ impl YoutubeDl {
     }
 }
 
+// Joins the categories into the comma separated list yt-dlp expects, skipping `Unknown`.
+fn sponsorblock_categories<I>(categories: I) -> Option<String>
+where
+    I: IntoIterator<Item = SponsorBlockCategory>,
+{
+    let categories: Vec<_> = categories
+        .into_iter()
+        .filter_map(SponsorBlockCategory::as_arg)
+        .collect();
+    if categories.is_empty() {
+        None
+    } else {
+        Some(categories.join(","))
+    }
+}
+
 struct ProcessResult {
     stdout: Vec<u8>,
     stderr: Vec<u8>,
@@ -939,7 +1200,11 @@ struct ProcessResult {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Protocol, SearchOptions, YoutubeDl};
+    use crate::{
+        Availability, CommentSort, CompositeProtocol, DownloadSection, LiveStatus, MaxComments,
+        MediaType, Protocol, SearchOptions, SingleVideo, SponsorBlockCategory, TimeRange,
+        YoutubeDl,
+    };
 
     use std::path::Path;
     use std::time::Duration;
@@ -1067,6 +1332,145 @@ mod tests {
         assert!(matches!(unknown_protocol, Protocol::Unknown));
     }
 
+    #[test]
+    fn test_composite_protocol() {
+        let merged: CompositeProtocol =
+            serde_json::from_str("\"http_dash_segments+m3u8_native\"").unwrap();
+        assert_eq!(
+            merged.protocols(),
+            [Protocol::HttpDashSegments, Protocol::M3U8Native]
+        );
+        assert!(merged.is_merged() && merged.is_dash() && merged.is_hls());
+        assert!(merged.is_fragmented());
+
+        let single: CompositeProtocol = serde_json::from_str("\"https\"").unwrap();
+        assert_eq!(single.protocols(), [Protocol::Https]);
+        assert!(!single.is_merged() && !single.is_fragmented());
+        assert_eq!(single, CompositeProtocol::from(Protocol::Https));
+
+        // Unknown components keep their original name
+        let unknown: CompositeProtocol = serde_json::from_str("\"https+new_proto\"").unwrap();
+        assert_eq!(unknown.protocols(), [Protocol::Https, Protocol::Unknown]);
+        assert_eq!(
+            serde_json::to_string(&unknown).unwrap(),
+            "\"https+new_proto\""
+        );
+    }
+
+    #[test]
+    fn test_live_status_parsing() {
+        let video: SingleVideo = serde_json::from_str(
+            r#"{"id": "abc", "live_status": "is_upcoming", "release_timestamp": 1700000000}"#,
+        )
+        .unwrap();
+        assert_eq!(video.live_status, Some(LiveStatus::IsUpcoming));
+        assert_eq!(video.release_timestamp, Some(1700000000));
+
+        let unknown: LiveStatus = serde_json::from_str("\"some_new_status\"").unwrap();
+        assert_eq!(unknown, LiveStatus::Unknown);
+    }
+
+    #[test]
+    fn test_availability_fields() {
+        let video: SingleVideo = serde_json::from_str(
+            r#"{
+                "id": "abc",
+                "availability": "needs_auth",
+                "media_type": "short",
+                "channel_follower_count": 1200,
+                "was_live": false,
+                "requested_downloads": [
+                    {"format_id": "251", "acodec": "opus", "vcodec": "none", "protocol": "https",
+                     "filepath": "/tmp/abc.webm", "_filename": "/tmp/abc.webm"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(video.availability, Some(Availability::NeedsAuth));
+        assert_eq!(video.media_type, Some(MediaType::Short));
+        assert_eq!(video.channel_follower_count, Some(1200));
+        assert_eq!(video.was_live, Some(false));
+        let download = &video.requested_downloads.unwrap()[0];
+        assert_eq!(download.filepath.as_deref(), Some("/tmp/abc.webm"));
+        assert_eq!(download.format.format_id.as_deref(), Some("251"));
+        assert_eq!(download.format.vcodec, None);
+
+        let unknown: Availability = serde_json::from_str("\"members_only\"").unwrap();
+        assert_eq!(unknown, Availability::Unknown);
+        let unknown: MediaType = serde_json::from_str("\"podcast\"").unwrap();
+        assert_eq!(unknown, MediaType::Unknown);
+    }
+
+    #[test]
+    fn test_live_args() {
+        let mut ytdl = YoutubeDl::new("https://www.youtube.com/watch?v=7XGyWcuYVrg");
+        ytdl.wait_for_video(Duration::from_secs(30), Some(Duration::from_secs(120)))
+            .live_from_start(true);
+        let args = ytdl.common_args();
+        assert!(args.windows(2).any(|w| w == ["--wait-for-video", "30-120"]));
+        assert!(args.contains(&"--live-from-start"));
+    }
+
+    #[test]
+    fn test_download_sections_args() {
+        let mut ytdl = YoutubeDl::new("https://www.youtube.com/watch?v=7XGyWcuYVrg");
+        ytdl.download_section(TimeRange::new(90.0, 312.5))
+            .download_section(TimeRange::from_start(3600.0))
+            .download_section(DownloadSection::Chapters("intro|outro".to_string()));
+        let sections: Vec<_> = ytdl
+            .common_args()
+            .windows(2)
+            .filter(|w| w[0] == "--download-sections")
+            .map(|w| w[1])
+            .collect();
+        assert_eq!(sections, ["*90-312.5", "*3600-inf", "intro|outro"]);
+    }
+
+    #[test]
+    fn test_sponsorblock() {
+        let mut ytdl = YoutubeDl::new("https://www.youtube.com/watch?v=7XGyWcuYVrg");
+        ytdl.sponsorblock_mark([SponsorBlockCategory::Intro, SponsorBlockCategory::Outro])
+            .sponsorblock_remove([SponsorBlockCategory::Sponsor, SponsorBlockCategory::Unknown]);
+        let args = ytdl.common_args();
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["--sponsorblock-mark", "intro,outro"]));
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["--sponsorblock-remove", "sponsor"]));
+
+        let video: SingleVideo = serde_json::from_str(
+            r#"{"id": "abc", "sponsorblock_chapters": [
+                {"start_time": 10.5, "end_time": 42.0, "category": "sponsor", "title": "Sponsor", "type": "skip"},
+                {"start_time": 50.0, "end_time": 50.0, "category": "some_new_category", "type": "poi"}
+            ]}"#,
+        )
+        .unwrap();
+        let chapters = video.sponsorblock_chapters.unwrap();
+        assert_eq!(chapters[0].category, Some(SponsorBlockCategory::Sponsor));
+        assert_eq!(chapters[0].segment_type.as_deref(), Some("skip"));
+        assert_eq!(chapters[1].category, Some(SponsorBlockCategory::Unknown));
+    }
+
+    #[test]
+    fn test_comment_args() {
+        let mut ytdl = YoutubeDl::new("https://www.youtube.com/watch?v=7XGyWcuYVrg");
+        ytdl.write_comments(true)
+            .max_comments(MaxComments {
+                total: Some(500),
+                replies_per_thread: Some(10),
+                ..Default::default()
+            })
+            .comment_sort(CommentSort::Top);
+        let args = ytdl.common_args();
+        assert!(args.contains(&"--write-comments"));
+        assert!(args.windows(2).any(|w| w
+            == [
+                "--extractor-args",
+                "youtube:max_comments=500,all,all,10;comment_sort=top"
+            ]));
+    }
+
     #[test]
     fn test_download_to_destination() {
         let dir = tempfile::tempdir().unwrap();
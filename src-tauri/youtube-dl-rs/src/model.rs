@@ -3,9 +3,11 @@
 
 #![allow(missing_docs)]
 
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 use std::collections::BTreeMap;
+use std::fmt;
 
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct Chapter {
@@ -20,6 +22,8 @@ pub struct Comment {
     pub author_id: Option<String>,
     pub html: Option<String>,
     pub id: Option<String>,
+    pub is_pinned: Option<bool>,
+    pub like_count: Option<i64>,
     pub parent: Option<String>,
     pub text: Option<String>,
     pub timestamp: Option<f64>,
@@ -50,7 +54,7 @@ pub struct Format {
     pub no_resume: Option<bool>,
     pub player_url: Option<String>,
     pub preference: Option<Value>,
-    pub protocol: Option<Protocol>,
+    pub protocol: Option<CompositeProtocol>,
     pub quality: Option<f64>,
     pub resolution: Option<String>,
     pub source_preference: Option<i64>,
@@ -87,9 +91,11 @@ pub struct JsonOutput {
     pub alt_title: Option<String>,
     pub artist: Option<String>,
     pub automatic_captions: Option<BTreeMap<String, Vec<Subtitle>>>,
+    pub availability: Option<Availability>,
     pub average_rating: Option<Value>,
     pub categories: Option<Vec<Option<String>>>,
     pub channel: Option<String>,
+    pub channel_follower_count: Option<i64>,
     pub channel_id: Option<String>,
     pub channel_url: Option<String>,
     pub chapter: Option<String>,
@@ -119,7 +125,9 @@ pub struct JsonOutput {
     pub is_live: Option<bool>,
     pub license: Option<String>,
     pub like_count: Option<i64>,
+    pub live_status: Option<LiveStatus>,
     pub location: Option<String>,
+    pub media_type: Option<MediaType>,
     pub playlist: Option<String>,
     pub playlist_id: Option<String>,
     pub playlist_index: Option<Value>,
@@ -127,13 +135,16 @@ pub struct JsonOutput {
     pub playlist_uploader: Option<String>,
     pub playlist_uploader_id: Option<String>,
     pub release_date: Option<String>,
+    pub release_timestamp: Option<i64>,
     pub release_year: Option<i64>,
     pub repost_count: Option<i64>,
+    pub requested_downloads: Option<Vec<RequestedDownload>>,
     pub requested_subtitles: Option<BTreeMap<String, Subtitle>>,
     pub season: Option<String>,
     pub season_id: Option<String>,
     pub season_number: Option<i32>,
     pub series: Option<String>,
+    pub sponsorblock_chapters: Option<Vec<SponsorBlockChapter>>,
     pub start_time: Option<String>,
     pub subtitles: Option<BTreeMap<String, Option<Vec<Subtitle>>>>,
     pub tags: Option<Vec<Option<String>>>,
@@ -149,6 +160,7 @@ pub struct JsonOutput {
     pub uploader_id: Option<String>,
     pub uploader_url: Option<String>,
     pub view_count: Option<i64>,
+    pub was_live: Option<bool>,
     pub webpage_url: Option<String>,
 }
 
@@ -180,9 +192,11 @@ pub struct SingleVideo {
     pub artist: Option<String>,
     pub asr: Option<f64>,
     pub automatic_captions: Option<BTreeMap<String, Vec<Subtitle>>>,
+    pub availability: Option<Availability>,
     pub average_rating: Option<Value>,
     pub categories: Option<Vec<Option<String>>>,
     pub channel: Option<String>,
+    pub channel_follower_count: Option<i64>,
     pub channel_id: Option<String>,
     pub channel_url: Option<String>,
     pub chapter: Option<String>,
@@ -227,8 +241,10 @@ pub struct SingleVideo {
     pub language_preference: Option<i64>,
     pub license: Option<String>,
     pub like_count: Option<i64>,
+    pub live_status: Option<LiveStatus>,
     pub location: Option<String>,
     pub manifest_url: Option<String>,
+    pub media_type: Option<MediaType>,
     pub no_resume: Option<bool>,
     pub player_url: Option<String>,
     pub playlist: Option<String>,
@@ -238,11 +254,13 @@ pub struct SingleVideo {
     pub playlist_uploader: Option<String>,
     pub playlist_uploader_id: Option<String>,
     pub preference: Option<Value>,
-    pub protocol: Option<Protocol>,
+    pub protocol: Option<CompositeProtocol>,
     pub quality: Option<f64>,
     pub release_date: Option<String>,
+    pub release_timestamp: Option<i64>,
     pub release_year: Option<i64>,
     pub repost_count: Option<i64>,
+    pub requested_downloads: Option<Vec<RequestedDownload>>,
     pub requested_subtitles: Option<BTreeMap<String, Subtitle>>,
     pub resolution: Option<String>,
     pub season: Option<String>,
@@ -250,6 +268,7 @@ pub struct SingleVideo {
     pub season_number: Option<i32>,
     pub series: Option<String>,
     pub source_preference: Option<i64>,
+    pub sponsorblock_chapters: Option<Vec<SponsorBlockChapter>>,
     pub start_time: Option<String>,
     pub stretched_ratio: Option<f64>,
     pub subtitles: Option<BTreeMap<String, Option<Vec<Subtitle>>>>,
@@ -270,10 +289,35 @@ pub struct SingleVideo {
     pub vbr: Option<f64>,
     pub vcodec: Option<String>,
     pub view_count: Option<i64>,
+    pub was_live: Option<bool>,
     pub webpage_url: Option<String>,
     pub width: Option<f64>,
 }
 
+/// A format selected for download, with the path it was written to (only present after
+/// an actual download).
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct RequestedDownload {
+    #[serde(rename = "_filename")]
+    pub filename: Option<String>,
+    pub filepath: Option<String>,
+    #[serde(flatten)]
+    pub format: Format,
+}
+
+/// A segment fetched from SponsorBlock, present when `--sponsorblock-mark` or
+/// `--sponsorblock-remove` is used.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct SponsorBlockChapter {
+    pub category: Option<SponsorBlockCategory>,
+    pub end_time: Option<f64>,
+    pub start_time: Option<f64>,
+    pub title: Option<String>,
+    /// `skip`, `chapter` or `poi`
+    #[serde(rename = "type")]
+    pub segment_type: Option<String>,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct Subtitle {
     pub data: Option<String>,
@@ -291,7 +335,7 @@ pub struct Thumbnail {
     pub width: Option<f64>,
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum Protocol {
     #[serde(rename = "http")]
     Http,
@@ -315,25 +359,220 @@ pub enum Protocol {
     M3U8Native,
     #[serde(rename = "http_dash_segments")]
     HttpDashSegments,
+    #[serde(rename = "http_dash_segments_generator")]
+    HttpDashSegmentsGenerator,
     #[serde(rename = "mhtml")]
     Mhtml,
-    #[serde(rename = "https+https")]
-    HttpsHttps,
-    #[serde(rename = "http_dash_segments+https")]
-    HttpDashSegmentsHttps,
-    #[serde(rename = "http_dash_segments+http_dash_segments")]
-    HttpDashSegmentsHttpDashSegments,
-    #[serde(rename = "m3u8_native+m3u8_native")]
-    M3U8NativeM3U8Native,
+    #[serde(rename = "websocket_frag")]
+    WebsocketFrag,
     #[serde(rename = "niconico_dmc")]
     NicoNicoDmc,
-    #[serde(rename = "m3u8_native+https")]
-    M3U8NativeHttps,
     /// Fallback for cases where the library does not keep up with youtube-dl/yt-dlp
     #[serde(other)]
     Unknown,
 }
 
+impl Protocol {
+    /// HTTP Live Streaming (`m3u8`, `m3u8_native`).
+    pub fn is_hls(self) -> bool {
+        matches!(self, Protocol::M3U8 | Protocol::M3U8Native)
+    }
+
+    /// MPEG-DASH (`http_dash_segments`, `http_dash_segments_generator`).
+    pub fn is_dash(self) -> bool {
+        matches!(
+            self,
+            Protocol::HttpDashSegments | Protocol::HttpDashSegmentsGenerator
+        )
+    }
+
+    /// Protocols that are downloaded as a sequence of fragments.
+    pub fn is_fragmented(self) -> bool {
+        self.is_hls()
+            || self.is_dash()
+            || matches!(
+                self,
+                Protocol::F4M | Protocol::Ism | Protocol::Mhtml | Protocol::WebsocketFrag
+            )
+    }
+}
+
+/// The protocol of a format. Merged formats (e.g. `http_dash_segments+https`) consist of
+/// one protocol per merged component, in the same order as in `format_id`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompositeProtocol {
+    raw: String,
+    protocols: Vec<Protocol>,
+}
+
+impl CompositeProtocol {
+    /// The protocol exactly as reported by yt-dlp, including unknown components.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The component protocols, a single one unless the format is merged.
+    pub fn protocols(&self) -> &[Protocol] {
+        &self.protocols
+    }
+
+    /// Whether the format is a merge of several formats.
+    pub fn is_merged(&self) -> bool {
+        self.protocols.len() > 1
+    }
+
+    /// Whether any component uses HLS.
+    pub fn is_hls(&self) -> bool {
+        self.protocols.iter().any(|p| p.is_hls())
+    }
+
+    /// Whether any component uses DASH.
+    pub fn is_dash(&self) -> bool {
+        self.protocols.iter().any(|p| p.is_dash())
+    }
+
+    /// Whether any component is downloaded in fragments.
+    pub fn is_fragmented(&self) -> bool {
+        self.protocols.iter().any(|p| p.is_fragmented())
+    }
+}
+
+impl From<Protocol> for CompositeProtocol {
+    fn from(protocol: Protocol) -> Self {
+        let raw = serde_json::to_value(protocol)
+            .ok()
+            .and_then(|value| value.as_str().map(String::from))
+            .unwrap_or_default();
+        Self {
+            raw,
+            protocols: vec![protocol],
+        }
+    }
+}
+
+impl fmt::Display for CompositeProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl Serialize for CompositeProtocol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompositeProtocol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        let protocols = raw
+            .split('+')
+            .map(|component| Protocol::deserialize(component.into_deserializer()))
+            .collect::<Result<_, D::Error>>()?;
+        Ok(Self { raw, protocols })
+    }
+}
+
+/// Live state of a video, as reported by the `live_status` field of yt-dlp.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum LiveStatus {
+    #[serde(rename = "not_live")]
+    NotLive,
+    #[serde(rename = "is_live")]
+    IsLive,
+    #[serde(rename = "is_upcoming")]
+    IsUpcoming,
+    #[serde(rename = "was_live")]
+    WasLive,
+    #[serde(rename = "post_live")]
+    PostLive,
+    /// Fallback for cases where the library does not keep up with youtube-dl/yt-dlp
+    #[serde(other)]
+    Unknown,
+}
+
+/// Who can watch a video, as reported by the `availability` field of yt-dlp.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum Availability {
+    #[serde(rename = "private")]
+    Private,
+    #[serde(rename = "premium_only")]
+    PremiumOnly,
+    #[serde(rename = "subscriber_only")]
+    SubscriberOnly,
+    #[serde(rename = "needs_auth")]
+    NeedsAuth,
+    #[serde(rename = "unlisted")]
+    Unlisted,
+    #[serde(rename = "public")]
+    Public,
+    /// Fallback for cases where the library does not keep up with youtube-dl/yt-dlp
+    #[serde(other)]
+    Unknown,
+}
+
+/// Kind of media, as reported by the `media_type` field of yt-dlp.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum MediaType {
+    #[serde(rename = "video")]
+    Video,
+    #[serde(rename = "short")]
+    Short,
+    #[serde(rename = "livestream")]
+    Livestream,
+    /// Fallback for cases where the library does not keep up with youtube-dl/yt-dlp
+    #[serde(other)]
+    Unknown,
+}
+
+/// SponsorBlock segment categories, as used by the `--sponsorblock-mark` and
+/// `--sponsorblock-remove` options and the `sponsorblock_chapters` field.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum SponsorBlockCategory {
+    #[serde(rename = "sponsor")]
+    Sponsor,
+    #[serde(rename = "intro")]
+    Intro,
+    #[serde(rename = "outro")]
+    Outro,
+    #[serde(rename = "selfpromo")]
+    SelfPromo,
+    #[serde(rename = "preview")]
+    Preview,
+    #[serde(rename = "filler")]
+    Filler,
+    #[serde(rename = "interaction")]
+    Interaction,
+    #[serde(rename = "music_offtopic")]
+    MusicOfftopic,
+    #[serde(rename = "poi_highlight")]
+    PoiHighlight,
+    #[serde(rename = "chapter")]
+    Chapter,
+    /// Fallback for cases where the library does not keep up with youtube-dl/yt-dlp
+    #[serde(other)]
+    Unknown,
+}
+
+impl SponsorBlockCategory {
+    /// The name of the category on the command line, `None` for `Unknown`.
+    pub fn as_arg(self) -> Option<&'static str> {
+        match self {
+            SponsorBlockCategory::Sponsor => Some("sponsor"),
+            SponsorBlockCategory::Intro => Some("intro"),
+            SponsorBlockCategory::Outro => Some("outro"),
+            SponsorBlockCategory::SelfPromo => Some("selfpromo"),
+            SponsorBlockCategory::Preview => Some("preview"),
+            SponsorBlockCategory::Filler => Some("filler"),
+            SponsorBlockCategory::Interaction => Some("interaction"),
+            SponsorBlockCategory::MusicOfftopic => Some("music_offtopic"),
+            SponsorBlockCategory::PoiHighlight => Some("poi_highlight"),
+            SponsorBlockCategory::Chapter => Some("chapter"),
+            SponsorBlockCategory::Unknown => None,
+        }
+    }
+}
+
 // Codec values are set explicitly, and when there is no codec, it is sometimes
 // given as "none" (instead of simply missing from the JSON).
 // Default decoding in this case would result in `Some("none".to_string())`, which is why
@@ -0,0 +1,103 @@
+//! Picking the most suitable thumbnail for a given display size.
+
+use crate::model::{SingleVideo, Thumbnail};
+use std::cmp::Ordering;
+
+/// Returns the thumbnail that best fits `width`x`height`: the smallest one that is at least
+/// that large, otherwise the largest one available. Thumbnails without dimensions are only
+/// picked when no other thumbnail has them. Ties are broken by yt-dlp's `preference`.
+pub fn best_thumbnail(thumbnails: &[Thumbnail], width: f64, height: f64) -> Option<&Thumbnail> {
+    thumbnails
+        .iter()
+        .filter(|thumbnail| thumbnail.url.is_some())
+        .min_by(|a, b| compare(a, b, width, height))
+}
+
+fn compare(a: &Thumbnail, b: &Thumbnail, width: f64, height: f64) -> Ordering {
+    let fit = |t: &Thumbnail| match (t.width, t.height) {
+        (Some(w), Some(h)) if w >= width && h >= height => Fit::LargeEnough(w * h),
+        (Some(w), Some(h)) => Fit::TooSmall(w * h),
+        _ => Fit::Unknown,
+    };
+
+    let by_fit = match (fit(a), fit(b)) {
+        // Smallest that still covers the target size
+        (Fit::LargeEnough(a), Fit::LargeEnough(b)) => a.total_cmp(&b),
+        // Otherwise the largest one
+        (Fit::TooSmall(a), Fit::TooSmall(b)) => b.total_cmp(&a),
+        (Fit::Unknown, Fit::Unknown) => Ordering::Equal,
+        (a, b) => a.rank().cmp(&b.rank()),
+    };
+    by_fit.then_with(|| b.preference.unwrap_or(0).cmp(&a.preference.unwrap_or(0)))
+}
+
+enum Fit {
+    LargeEnough(f64),
+    TooSmall(f64),
+    Unknown,
+}
+
+impl Fit {
+    fn rank(&self) -> u8 {
+        match self {
+            Fit::LargeEnough(_) => 0,
+            Fit::TooSmall(_) => 1,
+            Fit::Unknown => 2,
+        }
+    }
+}
+
+impl SingleVideo {
+    /// The thumbnail that best fits `width`x`height`, see `best_thumbnail`. Falls back to the
+    /// `thumbnail` field when yt-dlp did not return a list of thumbnails.
+    pub fn best_thumbnail(&self, width: f64, height: f64) -> Option<Thumbnail> {
+        self.thumbnails
+            .as_deref()
+            .and_then(|thumbnails| best_thumbnail(thumbnails, width, height))
+            .cloned()
+            .or_else(|| {
+                self.thumbnail.as_ref().map(|url| Thumbnail {
+                    url: Some(url.clone()),
+                    ..Default::default()
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::best_thumbnail;
+    use crate::model::Thumbnail;
+
+    fn thumbnail(id: &str, size: Option<(f64, f64)>, preference: i64) -> Thumbnail {
+        Thumbnail {
+            id: Some(id.to_string()),
+            url: Some(format!("https://i.ytimg.com/vi/abc/{}.jpg", id)),
+            width: size.map(|(w, _)| w),
+            height: size.map(|(_, h)| h),
+            preference: Some(preference),
+            ..Default::default()
+        }
+    }
+
+    fn best(thumbnails: &[Thumbnail], width: f64, height: f64) -> Option<&str> {
+        best_thumbnail(thumbnails, width, height).and_then(|t| t.id.as_deref())
+    }
+
+    #[test]
+    fn test_best_thumbnail() {
+        let thumbnails = vec![
+            thumbnail("unknown", None, 10),
+            thumbnail("default", Some((120.0, 90.0)), -10),
+            thumbnail("hq", Some((480.0, 360.0)), -5),
+            thumbnail("hq_webp", Some((480.0, 360.0)), -4),
+            thumbnail("maxres", Some((1280.0, 720.0)), -1),
+        ];
+
+        assert_eq!(best(&thumbnails, 320.0, 180.0), Some("hq_webp"));
+        assert_eq!(best(&thumbnails, 100.0, 50.0), Some("default"));
+        assert_eq!(best(&thumbnails, 1920.0, 1080.0), Some("maxres"));
+        assert_eq!(best(&thumbnails[..1], 320.0, 180.0), Some("unknown"));
+        assert_eq!(best(&[], 320.0, 180.0), None);
+    }
+}
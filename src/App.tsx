@@ -10,8 +10,10 @@ import { YoutubeInput } from "./components/YoutubeInput";
 import "./styles/components.css";
 
 import { getVersion } from "@tauri-apps/api/app";
+import { invoke } from "@tauri-apps/api/core";
 import { listen } from '@tauri-apps/api/event';
 import { getCurrentWindow } from '@tauri-apps/api/window';
+import { message } from "@tauri-apps/plugin-dialog";
 import { getCurrent as getCurrentDeepLink, onOpenUrl } from '@tauri-apps/plugin-deep-link';
 
 
@@ -62,6 +64,7 @@ interface ChapterResponse {
 function App() {
   const [audioPath, setAudioPath] = useState("");
   const [transcriptionResult, setTranscriptionResult] = useState("");
+  const [chapters, setChapters] = useState<ChapterResponse[]>([]);
   const [loading, setLoading] = useState(false);
   const [timeLapse, setTimeLapse] = useState("");
   
@@ -146,6 +149,7 @@ function App() {
 
   const handleDownloadComplete = (path: string, chapters: ChapterResponse[], time: string) => {
     setAudioPath(path);
+    setChapters(chapters);
     setTranscriptionResult(_chapter_to_string(chapters));
     setTimeLapse(time);
     // Quando conclui com sucesso, limpa qualquer erro anterior
@@ -221,6 +225,18 @@ function App() {
     });
   };
 
+  // Grava os capítulos no próprio arquivo de áudio, para aparecerem nos players
+  const embedChapters = async () => {
+    const mediaId = audioPath.split("/").pop()?.replace(/\.[^.]+$/, "");
+    try {
+      const path = await invoke<string>("embed_chapters", { mediaId, chapters });
+      await message(`Capítulos gravados em ${path}`, { title: "Capítulos Gravados", kind: "info" });
+    } catch (error) {
+      console.error("Erro ao gravar capítulos no arquivo:", error);
+      await message(`Não foi possível gravar os capítulos: ${error}`, { title: "Erro", kind: "error" });
+    }
+  };
+
   // Função para lidar com o login bem-sucedido
   const handleLoginSuccess = (session: any) => {
     try {
@@ -351,6 +367,7 @@ function App() {
           <TranscriptionDisplay 
             transcription={transcriptionResult} 
             onCopyToClipboard={copyToClipboard} 
+            onEmbedChapters={chapters.length > 0 ? embedChapters : undefined}
           />
           
           <p style={{ textAlign: 'left' }}>{audioPath}</p>
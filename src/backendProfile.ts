@@ -0,0 +1,38 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * Perfil dos serviços escolhido no backend, por `backend.json` ou pelas variáveis `CAPITUAI_*`.
+ */
+export interface BackendProfile {
+    profile: "production" | "staging" | "local";
+    source: "default" | "settings" | "environment";
+    transcribe_url: string;
+    chapters_url: string;
+}
+
+let cachedProfile: Promise<BackendProfile> | null = null;
+
+/**
+ * Obtém o perfil ativo, consultado uma vez por sessão.
+ * @returns O perfil e os endereços que o backend também usa
+ */
+export function getBackendProfile(): Promise<BackendProfile> {
+    if (!cachedProfile) {
+        cachedProfile = invoke<BackendProfile>("backend_profile").catch((error) => {
+            cachedProfile = null;
+            throw error;
+        });
+    }
+    return cachedProfile;
+}
+
+/**
+ * Lista dos serviços do perfil, para verificar se estão no ar.
+ */
+export async function getApiServices(): Promise<{ name: string; url: string }[]> {
+    const profile = await getBackendProfile();
+    return [
+        { name: "Capitu AI", url: profile.chapters_url },
+        { name: "Transcriber", url: profile.transcribe_url },
+    ];
+}
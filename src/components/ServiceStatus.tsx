@@ -1,5 +1,7 @@
+import { invoke } from "@tauri-apps/api/core";
+import { message } from "@tauri-apps/plugin-dialog";
 import { useEffect, useState } from "react";
-import { API_SERVICES } from "./YoutubeInput";
+import { BackendProfile, getApiServices, getBackendProfile } from "../backendProfile";
 
 interface Service {
   name: string;
@@ -44,18 +46,41 @@ export async function checkServices(services: { name: string; url: string }[]) {
   return results;
 }
 
+const PROFILE_LABELS: Record<BackendProfile["profile"], string> = {
+  production: "Produção",
+  staging: "Staging",
+  local: "Local",
+};
+
 export function ServiceStatus() {
-  const [services, setServices] = useState<Service[]>(API_SERVICES.map(service => ({
-    ...service,
-    status: "checking" as const
-  })));
+  const [services, setServices] = useState<Service[]>([]);
+  const [profile, setProfile] = useState<BackendProfile | null>(null);
 
   const checkServiceStatus = async () => {
-    const updatedServices = await checkServices(services);
-    setServices(updatedServices);
+    try {
+      const apiServices = await getApiServices();
+      setServices(apiServices.map(service => ({ ...service, status: "checking" as const })));
+      setServices(await checkServices(apiServices));
+    } catch (error) {
+      console.error("Erro ao carregar o perfil dos serviços:", error);
+    }
+  };
+
+  // Copia as informações do ambiente, incluindo o perfil e os endereços, para relatar problemas
+  const copyDiagnostics = async () => {
+    try {
+      const report = await invoke<string>("diagnostics");
+      await navigator.clipboard.writeText(report);
+      await message("Diagnóstico copiado para a área de transferência.", { title: "Diagnóstico", kind: "info" });
+    } catch (error) {
+      await message(`Não foi possível gerar o diagnóstico: ${error}`, { title: "Diagnóstico", kind: "error" });
+    }
   };
 
   useEffect(() => {
+    getBackendProfile()
+      .then(setProfile)
+      .catch(error => console.error("Erro ao carregar o perfil dos serviços:", error));
     checkServiceStatus();
     const interval = setInterval(checkServiceStatus, 60000);
     return () => clearInterval(interval);
@@ -64,8 +89,16 @@ export function ServiceStatus() {
   return (
     <div className="service-status-compact">
       <div className="services-pills">
+        {profile && (
+          <div
+            className={`service-pill profile ${profile.profile}`}
+            title={`Transcrição: ${profile.transcribe_url}\nCapítulos: ${profile.chapters_url}\nOrigem: ${profile.source}`}
+          >
+            {PROFILE_LABELS[profile.profile]}
+          </div>
+        )}
         {services.map((service) => (
-          <div key={service.name} className={`service-pill ${service.status}`}>
+          <div key={service.name} className={`service-pill ${service.status}`} title={service.url}>
             {service.name}
           </div>
         ))}
@@ -73,6 +106,9 @@ export function ServiceStatus() {
       <button onClick={checkServiceStatus} className="refresh-btn-small" title="Atualizar status">
         ↻
       </button>
+      <button onClick={copyDiagnostics} className="refresh-btn-small" title="Copiar diagnóstico">
+        ⓘ
+      </button>
     </div>
   );
-}
\ No newline at end of file
+}
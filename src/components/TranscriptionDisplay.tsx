@@ -1,9 +1,10 @@
 type TranscriptionDisplayProps = {
   transcription: string;
   onCopyToClipboard: () => void;
+  onEmbedChapters?: () => void;
 }
 
-export function TranscriptionDisplay({ transcription, onCopyToClipboard }: TranscriptionDisplayProps) {
+export function TranscriptionDisplay({ transcription, onCopyToClipboard, onEmbedChapters }: TranscriptionDisplayProps) {
   if (!transcription) return null;
   
   return (
@@ -11,6 +12,11 @@ export function TranscriptionDisplay({ transcription, onCopyToClipboard }: Trans
       <button onClick={onCopyToClipboard}>
         Copiar para Área de Transferência
       </button>
+      {onEmbedChapters && (
+        <button onClick={onEmbedChapters}>
+          Gravar Capítulos no Arquivo
+        </button>
+      )}
       
       <div style={{ textAlign: 'left' }}>
         {transcription.split('\n').map((value, index) => {
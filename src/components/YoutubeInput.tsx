@@ -1,25 +1,10 @@
 import { invoke } from "@tauri-apps/api/core";
 import { message } from "@tauri-apps/plugin-dialog";
 import { useState } from "react";
+import { getApiServices, getBackendProfile } from "../backendProfile";
 import { getUpdatedToken, supabase } from "../supabaseClient";
 import { checkServices } from "./ServiceStatus";
 
-// Verificar os serviços antes de começar o processo
-export const API_SERVICES = [
-  {
-    name: "Capitu AI",
-    url: "https://kamikazebr--capitu-ai-langchain-fastapi-app.modal.run",
-  },
-  {
-    name: "Transcriber",
-    url: "https://kamikazebr--transcribe-youtube-fastapi-app.modal.run",
-  },
-];
-
-const TRANSCRIBER_URL = API_SERVICES.find(s => s.name === "Transcriber")?.url || "";
-// Extraindo a URL da API do Transcriber para uso em outras funções
-const API_URL = API_SERVICES.find(s => s.name === "Capitu AI")?.url || "";
-
 // const OUTPUT_FOLDER = "../output"; // TODO: Change to see if it's working on macos
 
 type YoutubeInputProps = {
@@ -36,6 +21,21 @@ interface ChapterResponse {
   title: string;
 }
 
+// Trecho mais assistido do vídeo, devolvido por `most_replayed`
+interface ReplayRange {
+  start_time: number;
+  end_time: number;
+  peak_time: number;
+  score: number;
+}
+
+// Motor de transcrição salvo nas configurações, `null` usa o serviço
+// Ex.: { "engine": "local", "model_path": "/caminho/ggml-base.bin", "language": "pt" }
+const getTranscriberEngine = () => {
+  const saved = localStorage.getItem("transcriber");
+  return saved ? JSON.parse(saved) : null;
+};
+
 // Função para esperar um determinado tempo (em milissegundos)
 const sleep = (ms: number) => new Promise(resolve => setTimeout(resolve, ms));
 
@@ -77,6 +77,7 @@ export function YoutubeInput({
 }: YoutubeInputProps) {
   const [youtubeUrl, setYoutubeUrl] = useState(initialUrl);
   const [isLoading, setIsLoading] = useState(false);
+  const [thumbnail, setThumbnail] = useState<string | null>(null);
 
   // Função para tentar obter a transcrição com exponential backoff
   // const pollForTranscription = async (filenameId: string, maxAttempts: number = POLLING_CONFIG.MAX_ATTEMPTS.TRANSCRIPTION): Promise<string> => {
@@ -185,7 +186,7 @@ export function YoutubeInput({
 
   // Verifica serviços online
   const checkOnlineServices = async () => {
-    const serviceStatus = await checkServices(API_SERVICES);
+    const serviceStatus = await checkServices(await getApiServices());
     const offlineServices = serviceStatus.filter(s => s.status === "offline");
 
     if (offlineServices.length > 0) {
@@ -202,7 +203,8 @@ export function YoutubeInput({
   // Verifica cache do arquivo
   const checkFileCache = async (filename_id: string) => {
     console.log("Verificando se arquivo já existe:", filename_id);
-    const checkResponse = await fetch(`${TRANSCRIBER_URL}/check-cache/${filename_id}`);
+    const { transcribe_url } = await getBackendProfile();
+    const checkResponse = await fetch(`${transcribe_url}/check-cache/${filename_id}`);
     return await checkResponse.json();
   };
 
@@ -291,13 +293,14 @@ export function YoutubeInput({
   const pollForChapters = async (filenameId: string, maxAttempts: number = POLLING_CONFIG.MAX_ATTEMPTS.CHAPTERS): Promise<any> => {
     let attempt = 1;
     let delay = POLLING_CONFIG.INITIAL_DELAY;
+    const { transcribe_url } = await getBackendProfile();
 
     while (attempt <= maxAttempts) {
       try {
         console.log(`Tentativa ${attempt}/${maxAttempts} de obter os capítulos (aguardando ${delay / 1000}s)`);
         onProgressUpdate(PROGRESS_STEPS.CHAPTERS, (attempt / maxAttempts) * 90);
 
-        const chaptersResponse = await fetch(`${TRANSCRIBER_URL}/chapters/${filenameId}`);
+        const chaptersResponse = await fetch(`${transcribe_url}/chapters/${filenameId}`);
         const chaptersData = await chaptersResponse.json();
 
         if (chaptersData.status === "completed" && chaptersData.chapters) {
@@ -361,7 +364,7 @@ export function YoutubeInput({
   // };
 
   // Obtém os capítulos
-  const getChapters = async (filenameId: string, checkResult: any) => {
+  const getChapters = async (filenameId: string, checkResult: any, mostReplayed: ReplayRange[] | null) => {
     onProgressUpdate(PROGRESS_STEPS.CHAPTERS, 0);
 
     try {
@@ -370,7 +373,8 @@ export function YoutubeInput({
 
       if (checkResult.status?.has_chapters && checkResult.status?.processing_complete) {
         console.log("Capítulos encontrados no cache e ainda válidos");
-        const chaptersResponse = await fetch(`${TRANSCRIBER_URL}/chapters/${filenameId}`);
+        const { transcribe_url } = await getBackendProfile();
+        const chaptersResponse = await fetch(`${transcribe_url}/chapters/${filenameId}`);
         const chaptersData = await chaptersResponse.json();
 
         if (chaptersData.status === "completed" && chaptersData.chapters) {
@@ -395,7 +399,12 @@ export function YoutubeInput({
           console.log("userSession", userSession);
           const updatedToken = await getUpdatedToken();
           console.log("updatedToken", updatedToken);
-          await invoke<string>("take_transcription", { filenameId, authToken: updatedToken });
+          await invoke<string>("take_transcription", {
+            filenameId,
+            authToken: updatedToken,
+            mostReplayed,
+            engine: getTranscriberEngine(),
+          });
 
           // Após o processamento, fazemos polling para obter os capítulos
           console.log("Processamento completo, aguardando geração dos capítulos");
@@ -462,16 +471,17 @@ export function YoutubeInput({
         throw new Error("ID do arquivo não fornecido");
       }
 
-      // Verifica os créditos antes do processamento
+      // Verifica os créditos antes do processamento, a transcrição local não gasta créditos
       const updatedToken = await getUpdatedToken();
+      const engine = getTranscriberEngine();
       const audioPath = `${filenameId}.mp3`;
-      if (!(await checkUserCredits(audioPath, updatedToken))) {
+      if (engine?.engine !== "local" && !(await checkUserCredits(audioPath, updatedToken))) {
         throw new Error("Créditos insuficientes para processar o áudio");
       }
 
       console.log("processTranscription", filenameId);
       console.log("updatedToken", updatedToken);
-      const transcriptionResponse = await invoke<string>("process_transcription", { filenameId, authToken: updatedToken });
+      const transcriptionResponse = await invoke<string>("process_transcription", { filenameId, authToken: updatedToken ?? "", engine });
       const transcriptionData = JSON.parse(transcriptionResponse);
       console.log("transcriptionData", transcriptionData);
 
@@ -479,30 +489,42 @@ export function YoutubeInput({
         throw new Error(transcriptionData.error);
       }
 
-      const taskId = transcriptionData.task_id;
+      // O motor local só responde quando a transcrição já terminou
+      if (!transcriptionData.task_id) {
+        return transcriptionData;
+      }
 
-      // Polling para verificar o status da transcrição
-      while (true) {
-        const statusResponse = await fetch(`${API_URL}/task-status/${taskId}`);
-        const statusData = await statusResponse.json();
-        console.log("statusData", statusData);
+      // Áudios enviados em pedaços têm uma tarefa por pedaço, todas precisam terminar
+      const taskIds: string[] = transcriptionData.task_ids ?? [transcriptionData.task_id];
+      const { chapters_url } = await getBackendProfile();
+
+      let taskResult = null;
+      for (const taskId of taskIds) {
+        // Polling para verificar o status da transcrição
+        while (true) {
+          const statusResponse = await fetch(`${chapters_url}/task-status/${taskId}`);
+          const statusData = await statusResponse.json();
+          console.log("statusData", statusData);
+
+          if (statusData.task_result?.result === "pending") {
+            // Aguarda 5 segundos antes de verificar novamente
+            await new Promise(resolve => setTimeout(resolve, 5000));
+            continue;
+          }
 
-        if (statusData.task_result?.result === "pending") {
-          // Aguarda 5 segundos antes de verificar novamente
-          await new Promise(resolve => setTimeout(resolve, 5000));
-          continue;
-        }
+          if (statusData.task_result?.result === "timeout_get") {
+            throw new Error("Timeout ao processar a transcrição");
+          }
 
-        if (statusData.task_result?.result === "timeout_get") {
-          throw new Error("Timeout ao processar a transcrição");
-        }
+          if (statusData.task_result?.result === "success") {
+            taskResult = statusData.task_result;
+            break;
+          }
 
-        if (statusData.task_result?.result === "success") {
-          return statusData.task_result;
+          throw new Error("Erro inesperado ao processar a transcrição");
         }
-
-        throw new Error("Erro inesperado ao processar a transcrição");
       }
+      return taskResult;
     } catch (error) {
       console.error("Erro ao processar transcrição:", error);
       throw error;
@@ -525,6 +547,7 @@ export function YoutubeInput({
 
       const startTime = Date.now();
 
+      setThumbnail(null);
       onProgressUpdate(PROGRESS_STEPS.DOWNLOAD, 0);
       console.log("Downloading audio from YouTube");
       const result = await invoke<string>("download_audio", { url: youtubeUrl });
@@ -534,10 +557,37 @@ export function YoutubeInput({
       console.log("filename", filename);
       if (!filename) throw new Error("Nome do arquivo inválido");
 
-      const checkResult = await checkFileCache(filename);
+      // A miniatura já fica no cache local depois do download
+      invoke<string>("cache_thumbnail", { url: youtubeUrl })
+        .then(setThumbnail)
+        .catch((error) => console.warn("Não foi possível obter a miniatura:", error));
+
+      // Os trechos mais assistidos são opcionais e só usados na geração dos capítulos
+      const mostReplayedPromise = invoke<ReplayRange[]>("most_replayed", { url: youtubeUrl }).catch((error) => {
+        console.warn("Não foi possível obter os trechos mais assistidos:", error);
+        return null;
+      });
+
+      // O mesmo áudio já processado com outro ID reaproveita a transcrição e os capítulos dele.
+      // Só voltam duplicatas alinhadas, então os tempos delas valem para este áudio
+      const duplicate = await invoke<{ media_id: string; audio_file: string; offset: number } | null>(
+        "find_duplicate",
+        { mediaId: filename.replace(/\.[^.]+$/, "") }
+      ).catch((error) => {
+        console.warn("Não foi possível procurar duplicatas:", error);
+        return null;
+      });
+      if (duplicate) console.log("Áudio duplicado de", duplicate.media_id);
+
+      // Na transcrição local o áudio não é enviado, nem o cache do servidor é consultado
+      const isLocal = getTranscriberEngine()?.engine === "local";
+      const checkResult = isLocal ? {} : await checkFileCache(duplicate?.audio_file ?? filename);
       console.log("checkResult", checkResult);
 
-      const uploadResultJson = await processFileUpload(result, checkResult);
+      // O motor local reaproveita a transcrição salva da duplicata
+      const uploadResultJson = isLocal
+        ? { filename_id: duplicate?.media_id ?? filename.replace(/\.[^.]+$/, "") }
+        : await processFileUpload(result, checkResult);
       const filenameId = uploadResultJson["filename_id"];
       if (uploadResultJson.error) {
         console.error("Erro durante o processamento do upload:", uploadResultJson.error);
@@ -561,7 +611,7 @@ export function YoutubeInput({
         }
       }
 
-      const chaptersResult = await getChapters(filenameId, checkResult);
+      const chaptersResult = await getChapters(filenameId, checkResult, await mostReplayedPromise);
       console.log("Chapters result:", chaptersResult);
 
       processResult(result, chaptersResult, startTime, checkResult.status?.has_transcript || false);
@@ -597,6 +647,7 @@ export function YoutubeInput({
       >
         {isLoading ? 'Processando...' : 'Gerar Capítulos'}
       </button>
+      {thumbnail && <img src={thumbnail} alt="Miniatura do vídeo" className="video-thumbnail" />}
     </div>
   );
 } 
\ No newline at end of file